@@ -1,4 +1,5 @@
 use std::error::Error;
+use crate::db::STATION_ORDERS;
 use serde_with::skip_serializing_none;
 
 #[skip_serializing_none]
@@ -38,18 +39,38 @@ pub struct AllParameters {
     pub param_language: Option<String>,
     #[serde(rename = "lx")]
     pub param_language_exact: bool,
+    #[serde(rename = "lc")]
+    pub param_languagecodes: Option<String>,
     #[serde(rename = "tg")]
     pub param_tag: Option<String>,
     #[serde(rename = "tx")]
     pub param_tag_exact: bool,
     #[serde(rename = "tl")]
     pub param_tag_list: Vec<String>,
+    #[serde(rename = "tm")]
+    pub param_tag_match: String,
+    #[serde(rename = "te")]
+    pub param_tag_exclude: Vec<String>,
     #[serde(rename = "co")]
     pub param_codec: Option<String>,
+    #[serde(rename = "ce")]
+    pub param_codec_exclude: Vec<String>,
     #[serde(rename = "bi")]
     pub param_bitrate_min: u32,
     #[serde(rename = "ba")]
     pub param_bitrate_max: u32,
+    #[serde(rename = "hl")]
+    pub param_hls: Option<bool>,
+    #[serde(rename = "lo")]
+    pub param_lastcheckok: Option<bool>,
+    #[serde(rename = "vm")]
+    pub param_votes_min: u32,
+    #[serde(rename = "cm")]
+    pub param_clickcount_min: u32,
+    #[serde(rename = "hf")]
+    pub param_has_favicon: Option<bool>,
+    #[serde(rename = "la")]
+    pub param_lastcheck_age_max: u32,
     #[serde(rename = "or")]
     pub param_order: String,
     #[serde(rename = "re")]
@@ -64,10 +85,44 @@ pub struct AllParameters {
     pub param_seconds: u32,
     #[serde(rename = "up")]
     pub param_url: Option<String>,
+    #[serde(rename = "fr")]
+    pub param_from: Option<String>,
+    #[serde(rename = "to")]
+    pub param_to: Option<String>,
+    #[serde(rename = "rs")]
+    pub param_resolution: String,
 }
 
+/// Allowed values of the order parameter for lists of tags, countries, ..
+const LIST_ORDERS: &[&str] = &["name", "stationcount"];
+/// Allowed values of the resolution parameter for the stats history
+const RESOLUTIONS: &[&str] = &["hour", "day", "week", "month"];
+
 impl AllParameters {
     pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
         Ok(serde_json::to_string(&self)?)
     }
+
+    pub fn is_valid_order(&self) -> bool {
+        let path = self.url.split('?').next().unwrap_or("");
+        let command = path.split('/').nth(2).unwrap_or("");
+        match command {
+            "languages" | "languagecodes" | "countries" | "countrycodes" | "states" | "codecs" | "tags" => LIST_ORDERS.contains(&self.param_order.as_str()),
+            _ => STATION_ORDERS.iter().any(|(name, _)| *name == self.param_order),
+        }
+    }
+
+    pub fn is_valid_tag_match(&self) -> bool {
+        self.param_tag_match == "all" || self.param_tag_match == "any"
+    }
+
+    pub fn is_valid_resolution(&self) -> bool {
+        RESOLUTIONS.contains(&self.param_resolution.as_str())
+    }
+
+    /// Times are accepted as "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS" in UTC
+    pub fn is_valid_time(value: &str) -> bool {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+            || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").is_ok()
+    }
 }
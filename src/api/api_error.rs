@@ -1,3 +1,4 @@
+use crate::db::DbError;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
@@ -6,12 +7,97 @@ use std::fmt::Result;
 #[derive(Debug, Clone)]
 pub enum ApiError {
     InternalError(String),
+    IllegalParameter(String),
+    IllegalOrder(String),
+    StationNotFound(String),
+    NotFound(String),
+    NotEnabled(String),
+    VoteRejected(String),
+    InvalidStation(String),
+    RateLimited(String),
+    Unauthorized(String),
+}
+
+impl ApiError {
+    /// Convert any error into an api error, errors that are not known
+    /// are internal errors
+    pub fn from_error(err: &Box<dyn Error>) -> ApiError {
+        if let Some(api_error) = err.downcast_ref::<ApiError>() {
+            return api_error.clone();
+        }
+        if let Some(db_error) = err.downcast_ref::<DbError>() {
+            return match db_error {
+                DbError::IllegalOrderError(ref v) => ApiError::IllegalOrder(v.clone()),
+                DbError::StationNotFoundError(ref v) => ApiError::StationNotFound(v.clone()),
+                DbError::VoteError(ref v) => ApiError::VoteRejected(v.clone()),
+                DbError::AddStationError(ref v) => ApiError::InvalidStation(v.clone()),
+                DbError::ConnectionError(ref v) => ApiError::InternalError(v.clone()),
+                DbError::RateLimitError(ref v) => ApiError::RateLimited(v.clone()),
+            };
+        }
+        ApiError::InternalError(err.to_string())
+    }
+
+    /// Stable identifier of the error, clients should use this instead of the message
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ApiError::InternalError(_) => "INTERNAL_ERROR",
+            ApiError::IllegalParameter(_) => "ILLEGAL_PARAMETER",
+            ApiError::IllegalOrder(_) => "ILLEGAL_ORDER",
+            ApiError::StationNotFound(_) => "STATION_NOT_FOUND",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::NotEnabled(_) => "NOT_ENABLED",
+            ApiError::VoteRejected(_) => "VOTE_REJECTED",
+            ApiError::InvalidStation(_) => "INVALID_STATION",
+            ApiError::RateLimited(_) => "RATE_LIMITED",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+        }
+    }
+
+    pub fn status_code(&self) -> u16 {
+        match *self {
+            ApiError::InternalError(_) => 500,
+            ApiError::IllegalParameter(_) => 400,
+            ApiError::IllegalOrder(_) => 400,
+            ApiError::StationNotFound(_) => 404,
+            ApiError::NotFound(_) => 404,
+            ApiError::NotEnabled(_) => 423,
+            ApiError::VoteRejected(_) => 409,
+            ApiError::InvalidStation(_) => 422,
+            ApiError::RateLimited(_) => 429,
+            ApiError::Unauthorized(_) => 401,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match *self {
+            ApiError::InternalError(ref v) => v.clone(),
+            ApiError::IllegalParameter(ref v) => v.clone(),
+            ApiError::IllegalOrder(ref v) => format!("order by '{}' is not allowed", v),
+            ApiError::StationNotFound(ref v) => v.clone(),
+            ApiError::NotFound(ref v) => v.clone(),
+            ApiError::NotEnabled(ref v) => v.clone(),
+            ApiError::VoteRejected(ref v) => v.clone(),
+            ApiError::InvalidStation(ref v) => v.clone(),
+            ApiError::RateLimited(ref v) => v.clone(),
+            ApiError::Unauthorized(ref v) => v.clone(),
+        }
+    }
 }
 
 impl Display for ApiError {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match *self {
             ApiError::InternalError(ref v) => write!(f, "InternalError '{}'", v),
+            ApiError::IllegalParameter(ref v) => write!(f, "IllegalParameter '{}'", v),
+            ApiError::IllegalOrder(ref v) => write!(f, "IllegalOrder '{}'", v),
+            ApiError::StationNotFound(ref v) => write!(f, "StationNotFound '{}'", v),
+            ApiError::NotFound(ref v) => write!(f, "NotFound '{}'", v),
+            ApiError::NotEnabled(ref v) => write!(f, "NotEnabled '{}'", v),
+            ApiError::VoteRejected(ref v) => write!(f, "VoteRejected '{}'", v),
+            ApiError::InvalidStation(ref v) => write!(f, "InvalidStation '{}'", v),
+            ApiError::RateLimited(ref v) => write!(f, "RateLimited '{}'", v),
+            ApiError::Unauthorized(ref v) => write!(f, "Unauthorized '{}'", v),
         }
     }
 }
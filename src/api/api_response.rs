@@ -6,6 +6,5 @@ pub enum ApiResponse {
     ServerError(String),
     NotFound,
     UnknownContentType,
-    //ParameterError(String),
     Locked(String),
 }
@@ -0,0 +1,32 @@
+use crate::db::models::StationCheckItemNew;
+
+/// Request of a check worker for a batch of stations to check
+#[derive(Serialize, Deserialize)]
+pub struct CheckLeaseRequest {
+    pub source: String,
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CheckLeaseStation {
+    pub stationuuid: String,
+    pub name: String,
+    pub url: String,
+}
+
+/// Stations that are reserved for one check worker until the lease expires,
+/// other workers and the local check of the coordinator skip them
+#[derive(Serialize, Deserialize)]
+pub struct CheckLease {
+    pub leaseuuid: String,
+    pub timeout_seconds: u64,
+    pub stations: Vec<CheckLeaseStation>,
+}
+
+/// Check results of a worker for the stations of a lease
+#[derive(Serialize, Deserialize)]
+pub struct CheckLeaseResult {
+    pub leaseuuid: String,
+    pub source: String,
+    pub checks: Vec<StationCheckItemNew>,
+}
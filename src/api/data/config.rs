@@ -22,6 +22,9 @@ pub struct ApiConfig {
     pub api_threads: usize,
     pub cache_type: String,
     pub cache_ttl: u64,
+    pub add_validate_stream: bool,
+    pub now_playing_enabled: bool,
+    pub deep_check_enabled: bool,
 }
 
 impl ApiConfig {
@@ -85,6 +88,9 @@ impl ApiConfig {
         xml.elem_text("api_threads", &config.api_threads.to_string())?;
         xml.elem_text("cache_type", &config.cache_type.to_string())?;
         xml.elem_text("cache_ttl", &config.cache_ttl.to_string())?;
+        xml.elem_text("add_validate_stream", &config.add_validate_stream.to_string())?;
+        xml.elem_text("now_playing_enabled", &config.now_playing_enabled.to_string())?;
+        xml.elem_text("deep_check_enabled", &config.deep_check_enabled.to_string())?;
         xml.end_elem()?;
         xml.close()?;
         xml.flush()?;
@@ -123,6 +129,9 @@ impl From<Config> for ApiConfig {
             api_threads: item.threads,
             cache_type: item.cache_type.into(),
             cache_ttl: item.cache_ttl.as_secs(),
+            add_validate_stream: item.add_validate_stream,
+            now_playing_enabled: item.now_playing,
+            deep_check_enabled: item.deep_check,
         }
     }
 }
@@ -0,0 +1,30 @@
+#[derive(Serialize, Deserialize)]
+pub struct ErrorMessage {
+    ok: bool,
+    code: String,
+    message: String,
+}
+
+impl ErrorMessage {
+    pub fn new(code: &str, message: String) -> Self {
+        ErrorMessage{
+            ok: false,
+            code: code.to_string(),
+            message
+        }
+    }
+
+    pub fn serialize_xml(&self) -> std::io::Result<String> {
+        let mut xml = xml_writer::XmlWriter::new(Vec::new());
+        xml.begin_elem("result")?;
+            xml.begin_elem("error")?;
+                xml.attr_esc("ok", &self.ok.to_string())?;
+                xml.attr_esc("code", &self.code)?;
+                xml.attr_esc("message", &self.message)?;
+            xml.end_elem()?;
+        xml.end_elem()?;
+        xml.close()?;
+        xml.flush()?;
+        Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
+    }
+}
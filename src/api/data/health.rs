@@ -0,0 +1,16 @@
+#[derive(Serialize, Deserialize)]
+pub struct HealthWorker {
+    pub name: String,
+    pub healthy: bool,
+    pub lastrun: Option<String>,
+    pub lastok: Option<String>,
+    pub lasterror: Option<String>,
+}
+
+/// Health of the roles that run in one process, healthy only if all workers are healthy
+#[derive(Serialize, Deserialize)]
+pub struct Health {
+    pub healthy: bool,
+    pub roles: Vec<String>,
+    pub workers: Vec<HealthWorker>,
+}
@@ -3,9 +3,14 @@ mod station_check;
 mod station;
 mod station_history;
 mod status;
+mod station_stats;
 mod result_message;
+mod error_message;
 mod station_click;
 mod config;
+mod check_lease;
+mod health;
+mod station_now_playing;
 
 pub use self::station_add_result::StationAddResult;
 pub use self::station_check::StationCheck;
@@ -18,5 +23,18 @@ pub use self::station_check::StationCheckV0;
 pub use self::station_click::StationClick;
 pub use self::station_click::StationClickV0;
 pub use self::status::Status;
+pub use self::station_stats::StationStats;
+pub use self::station_stats::StationStatsClicks;
+pub use self::station_stats::StationStatsSource;
+pub use self::station_stats::StationStatsStream;
+pub use self::station_stats::uptime;
 pub use self::result_message::ResultMessage;
-pub use self::config::ApiConfig;
\ No newline at end of file
+pub use self::error_message::ErrorMessage;
+pub use self::config::ApiConfig;
+pub use self::check_lease::CheckLease;
+pub use self::check_lease::CheckLeaseRequest;
+pub use self::check_lease::CheckLeaseResult;
+pub use self::check_lease::CheckLeaseStation;
+pub use self::health::Health;
+pub use self::health::HealthWorker;
+pub use self::station_now_playing::StationNowPlaying;
@@ -1,6 +1,7 @@
 use crate::api::api_response::ApiResponse;
 use crate::api::data::StationHistoryCurrent;
 use crate::db::models::StationItem;
+use std::collections::BTreeMap;
 use std::error::Error;
 
 #[derive(PartialEq, Eq, Serialize, Deserialize)]
@@ -70,6 +71,8 @@ pub struct Station {
     pub countrycode: String,
     pub state: String,
     pub language: String,
+    #[serde(default)]
+    pub languagecodes: String,
     pub votes: i32,
     pub lastchangetime: String,
     pub codec: String,
@@ -82,6 +85,14 @@ pub struct Station {
     pub clicktimestamp: String,
     pub clickcount: u32,
     pub clicktrend: i32,
+    #[serde(default)]
+    pub audio_codec: String,
+    #[serde(default)]
+    pub audio_bitrate: u32,
+    #[serde(default)]
+    pub audio_samplerate: u32,
+    #[serde(default)]
+    pub audio_channels: u32,
 }
 
 impl Station {
@@ -124,6 +135,7 @@ impl Station {
             xml.attr_esc("countrycode", &entry.countrycode)?;
             xml.attr_esc("state", &entry.state)?;
             xml.attr_esc("language", &entry.language)?;
+            xml.attr_esc("languagecodes", &entry.languagecodes)?;
             let station_votes_str = format!("{}", entry.votes);
             xml.attr_esc("votes", &station_votes_str)?;
             let station_lastchangetime_str = format!("{}", entry.lastchangetime);
@@ -146,6 +158,10 @@ impl Station {
             xml.attr_esc("clickcount", &station_clickcount)?;
             let station_clicktrend = format!("{}", entry.clicktrend);
             xml.attr_esc("clicktrend", &station_clicktrend)?;
+            xml.attr_esc("audio_codec", &entry.audio_codec)?;
+            xml.attr_esc("audio_bitrate", &entry.audio_bitrate.to_string())?;
+            xml.attr_esc("audio_samplerate", &entry.audio_samplerate.to_string())?;
+            xml.attr_esc("audio_channels", &entry.audio_channels.to_string())?;
             xml.end_elem()?;
         }
         xml.end_elem()?;
@@ -220,6 +236,72 @@ impl Station {
         Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
     }
 
+    /// Get the names of the groups a station belongs to in opml output.
+    /// Multi value columns (language, tags) put the station into every group.
+    fn get_opml_groups(&self, group_by: &str) -> Vec<String> {
+        let value = match group_by {
+            "language" => &self.language,
+            "tags" => &self.tags,
+            _ => &self.country,
+        };
+        value.split(',').map(|item| item.trim().to_string()).filter(|item| item != "").collect()
+    }
+
+    // RadioTime/TuneIn style outlines
+    pub fn serialize_to_opml(entries: Vec<Station>, group_by: &str) -> std::io::Result<String> {
+        let mut groups: BTreeMap<String, Vec<&Station>> = BTreeMap::new();
+        let mut ungrouped: Vec<&Station> = vec![];
+        for entry in entries.iter() {
+            let entry_groups = entry.get_opml_groups(group_by);
+            if entry_groups.len() > 0 {
+                for group in entry_groups {
+                    groups.entry(group).or_insert(vec![]).push(entry);
+                }
+            } else {
+                ungrouped.push(entry);
+            }
+        }
+
+        let mut xml = xml_writer::XmlWriter::new(Vec::new());
+        xml.dtd("UTF-8")?;
+        xml.begin_elem("opml")?;
+        xml.attr_esc("version", "1.0")?;
+        xml.begin_elem("head")?;
+        xml.elem_text("title", "radio-browser.info")?;
+        xml.end_elem()?;
+        xml.begin_elem("body")?;
+        for (group, group_entries) in groups {
+            xml.begin_elem("outline")?;
+            xml.attr_esc("text", &group)?;
+            for entry in group_entries {
+                Station::serialize_to_opml_single(&mut xml, entry)?;
+            }
+            xml.end_elem()?;
+        }
+        for entry in ungrouped {
+            Station::serialize_to_opml_single(&mut xml, entry)?;
+        }
+        xml.end_elem()?;
+        xml.end_elem()?;
+        xml.close()?;
+        xml.flush()?;
+        Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
+    }
+
+    fn serialize_to_opml_single(xml: &mut xml_writer::XmlWriter<Vec<u8>>, entry: &Station) -> std::io::Result<()> {
+        xml.begin_elem("outline")?;
+        xml.attr_esc("type", "audio")?;
+        xml.attr_esc("item", "station")?;
+        xml.attr_esc("text", &entry.name)?;
+        xml.attr_esc("URL", &entry.url)?;
+        xml.attr_esc("image", &entry.favicon)?;
+        xml.attr_esc("bitrate", &entry.bitrate.to_string())?;
+        xml.attr_esc("formats", &entry.codec.to_lowercase())?;
+        xml.attr_esc("guide_id", &entry.stationuuid)?;
+        xml.end_elem()?;
+        Ok(())
+    }
+
     // Syntax checked with http://ttl.summerofcode.be/
     fn serialize_to_ttl_single(&self) -> String {
         format!(
@@ -340,6 +422,12 @@ impl Station {
     }
 
     pub fn get_response(list: Vec<Station>, format: &str) -> Result<ApiResponse, Box<dyn Error>> {
+        Station::get_response_grouped(list, format, "country")
+    }
+
+    /// Same as get_response, but group_by selects the station column
+    /// ("country", "language" or "tags") used to build folders in opml output
+    pub fn get_response_grouped(list: Vec<Station>, format: &str, group_by: &str) -> Result<ApiResponse, Box<dyn Error>> {
         Ok(match format {
             "csv" => ApiResponse::Text(Station::serialize_to_csv(list)?),
             "json" => ApiResponse::Text(serde_json::to_string(&list)?),
@@ -348,6 +436,7 @@ impl Station {
             "pls" => ApiResponse::Text(Station::serialize_to_pls(list, false)),
             "xspf" => ApiResponse::Text(Station::serialize_to_xspf(list)?),
             "ttl" => ApiResponse::Text(Station::serialize_to_ttl(list)),
+            "opml" => ApiResponse::Text(Station::serialize_to_opml(list, group_by)?),
             _ => ApiResponse::UnknownContentType,
         })
     }
@@ -367,6 +456,7 @@ impl From<&StationHistoryCurrent> for Station {
             countrycode: item.countrycode.clone(),
             state: item.state.clone(),
             language: item.language.clone(),
+            languagecodes: String::from(""),
             votes: item.votes,
             lastchangetime: item.lastchangetime.clone(),
             bitrate: 0,
@@ -380,6 +470,10 @@ impl From<&StationHistoryCurrent> for Station {
             lastchecktime: String::from(""),
             lastlocalchecktime: String::from(""),
             url_resolved: String::from(""),
+            audio_codec: String::from(""),
+            audio_bitrate: 0,
+            audio_samplerate: 0,
+            audio_channels: 0,
         }
     }
 }
@@ -398,6 +492,7 @@ impl From<StationItem> for Station {
             countrycode: item.countrycode,
             state: item.state,
             language: item.language,
+            languagecodes: item.languagecodes,
             votes: item.votes,
             lastchangetime: item.lastchangetime,
             bitrate: item.bitrate,
@@ -411,6 +506,10 @@ impl From<StationItem> for Station {
             lastchecktime: item.lastchecktime,
             lastlocalchecktime: item.lastlocalchecktime,
             url_resolved: item.url_resolved,
+            audio_codec: item.audio_codec,
+            audio_bitrate: item.audio_bitrate,
+            audio_samplerate: item.audio_samplerate,
+            audio_channels: item.audio_channels,
         }
     }
 }
@@ -429,6 +528,7 @@ impl From<StationV0> for Station {
             countrycode: item.countrycode,
             state: item.state,
             language: item.language,
+            languagecodes: String::from(""),
             votes: item.votes.parse().unwrap_or(0),
             lastchangetime: item.lastchangetime,
             bitrate: item.bitrate.parse().unwrap_or(0),
@@ -442,6 +542,109 @@ impl From<StationV0> for Station {
             lastchecktime: item.lastchecktime,
             lastlocalchecktime: String::from(""),
             url_resolved: String::from(""),
+            audio_codec: String::from(""),
+            audio_bitrate: 0,
+            audio_samplerate: 0,
+            audio_channels: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(stationuuid: &str, name: &str, country: &str, tags: &str) -> Station {
+        Station {
+            changeuuid: String::from(""),
+            stationuuid: stationuuid.to_string(),
+            name: name.to_string(),
+            url: format!("http://example.com/{}?a=1&b=2", stationuuid),
+            url_resolved: String::from(""),
+            homepage: String::from(""),
+            favicon: String::from(""),
+            tags: tags.to_string(),
+            country: country.to_string(),
+            countrycode: String::from(""),
+            state: String::from(""),
+            language: String::from(""),
+            languagecodes: String::from(""),
+            votes: 0,
+            lastchangetime: String::from(""),
+            codec: String::from("MP3"),
+            bitrate: 128,
+            hls: 0,
+            lastcheckok: 1,
+            lastchecktime: String::from(""),
+            lastcheckoktime: String::from(""),
+            lastlocalchecktime: String::from(""),
+            clicktimestamp: String::from(""),
+            clickcount: 0,
+            clicktrend: 0,
+            audio_codec: String::from(""),
+            audio_bitrate: 0,
+            audio_samplerate: 0,
+            audio_channels: 0,
         }
     }
+
+    fn stations() -> Vec<Station> {
+        vec![
+            station("1", "Rock & Roll", "Germany", "rock, pop"),
+            station("2", "\"Quoted\" <Jazz>", "Austria", "jazz"),
+            station("3", "Nowhere", "", ""),
+        ]
+    }
+
+    /// Expected opml outline of a station from stations()
+    fn outline(stationuuid: &str, text: &str) -> String {
+        format!(r#"<outline type="audio" item="station" text="{}" URL="http://example.com/{}?a=1&amp;b=2" image="" bitrate="128" formats="mp3" guide_id="{}"></outline>"#, text, stationuuid, stationuuid)
+    }
+
+    /// Opml document without whitespace between the elements
+    fn compact(opml: &str) -> String {
+        opml.lines().map(|line| line.trim()).collect::<Vec<&str>>().join("")
+    }
+
+    fn opml(body: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="UTF-8" ?><opml version="1.0"><head><title>radio-browser.info</title></head><body>{}</body></opml>"#, body)
+    }
+
+    #[test]
+    fn opml_groups() {
+        let list = stations();
+        assert_eq!(list[0].get_opml_groups("country"), vec!["Germany"]);
+        assert_eq!(list[0].get_opml_groups("tags"), vec!["rock", "pop"]);
+        assert_eq!(list[2].get_opml_groups("tags"), Vec::<String>::new());
+        assert_eq!(list[0].get_opml_groups("unknown"), vec!["Germany"]);
+    }
+
+    #[test]
+    fn opml_grouped_by_country() {
+        let result = Station::serialize_to_opml(stations(), "country").unwrap();
+        let expected = opml(&format!(
+            r#"<outline text="Austria">{}</outline><outline text="Germany">{}</outline>{}"#,
+            outline("2", "&quot;Quoted&quot; &lt;Jazz&gt;"),
+            outline("1", "Rock &amp; Roll"),
+            outline("3", "Nowhere"),
+        ));
+        assert_eq!(compact(&result), expected);
+    }
+
+    #[test]
+    fn opml_grouped_by_tags() {
+        let mut list = stations();
+        list.push(station("4", "Escaped", "Germany", "drum & bass,<rock>"));
+        let result = Station::serialize_to_opml(list, "tags").unwrap();
+        let expected = opml(&format!(
+            r#"<outline text="&lt;rock&gt;">{}</outline><outline text="drum &amp; bass">{}</outline><outline text="jazz">{}</outline><outline text="pop">{}</outline><outline text="rock">{}</outline>{}"#,
+            outline("4", "Escaped"),
+            outline("4", "Escaped"),
+            outline("2", "&quot;Quoted&quot; &lt;Jazz&gt;"),
+            outline("1", "Rock &amp; Roll"),
+            outline("1", "Rock &amp; Roll"),
+            outline("3", "Nowhere"),
+        ));
+        assert_eq!(compact(&result), expected);
+    }
 }
@@ -17,14 +17,6 @@ impl StationAddResult {
         }
     }
 
-    pub fn new_err(err: &str) -> StationAddResult {
-        StationAddResult {
-            ok: false,
-            message: err.to_string(),
-            uuid: "".to_string(),
-        }
-    }
-
     pub fn serialize_xml(&self) -> std::io::Result<String> {
         let mut xml = xml_writer::XmlWriter::new(Vec::new());
         xml.begin_elem("result")?;
@@ -39,13 +31,6 @@ impl StationAddResult {
         Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
     }
 
-    pub fn from(result: Result<String, Box<dyn Error>>) -> StationAddResult {
-        match result {
-            Ok(res) => StationAddResult::new_ok(res),
-            Err(err) => StationAddResult::new_err(&err.to_string()),
-        }
-    }
-
     pub fn get_response(&self, format: &str) -> Result<ApiResponse, Box<dyn Error>> {
         Ok(match format {
             "json" => ApiResponse::Text(serde_json::to_string(&self)?),
@@ -37,6 +37,14 @@ pub struct StationCheck {
     pub homepage: Option<String>,
     pub favicon: Option<String>,
     pub loadbalancer: Option<String>,
+
+    pub failure_reason: Option<String>,
+    pub failure_message: Option<String>,
+
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<u32>,
+    pub audio_samplerate: Option<u32>,
+    pub audio_channels: Option<u8>,
 }
 
 impl StationCheck {
@@ -60,6 +68,14 @@ impl StationCheck {
         homepage: Option<String>,
         favicon: Option<String>,
         loadbalancer: Option<String>,
+
+        failure_reason: Option<String>,
+        failure_message: Option<String>,
+
+        audio_codec: Option<String>,
+        audio_bitrate: Option<u32>,
+        audio_samplerate: Option<u32>,
+        audio_channels: Option<u8>,
     ) -> Self {
         StationCheck {
             stationuuid,
@@ -81,6 +97,14 @@ impl StationCheck {
             homepage,
             favicon,
             loadbalancer,
+
+            failure_reason,
+            failure_message,
+
+            audio_codec,
+            audio_bitrate,
+            audio_samplerate,
+            audio_channels,
         }
     }
 
@@ -120,6 +144,12 @@ impl StationCheck {
             xml.attr_esc("loadbalancer", &entry.loadbalancer.unwrap_or_default())?;
             xml.attr_esc("favicon", &entry.favicon.unwrap_or_default())?;
             xml.attr_esc("countrycode", &entry.countrycode.unwrap_or_default())?;
+            xml.attr_esc("failure_reason", &entry.failure_reason.unwrap_or_default())?;
+            xml.attr_esc("failure_message", &entry.failure_message.unwrap_or_default())?;
+            xml.attr_esc("audio_codec", &entry.audio_codec.unwrap_or_default())?;
+            xml.attr_esc("audio_bitrate", &entry.audio_bitrate.unwrap_or(0).to_string())?;
+            xml.attr_esc("audio_samplerate", &entry.audio_samplerate.unwrap_or(0).to_string())?;
+            xml.attr_esc("audio_channels", &entry.audio_channels.unwrap_or(0).to_string())?;
             xml.end_elem()?;
         }
         xml.end_elem()?;
@@ -162,6 +192,14 @@ impl TryFrom<StationCheckV0> for StationCheck {
             homepage: None,
             favicon: None,
             loadbalancer: None,
+
+            failure_reason: None,
+            failure_message: None,
+
+            audio_codec: None,
+            audio_bitrate: None,
+            audio_samplerate: None,
+            audio_channels: None,
         })
     }
 }
@@ -188,6 +226,14 @@ impl From<StationCheckItem> for StationCheck {
             item.homepage,
             item.favicon,
             item.loadbalancer,
+
+            item.failure_reason,
+            item.failure_message,
+
+            item.audio_codec,
+            item.audio_bitrate,
+            item.audio_samplerate,
+            item.audio_channels,
         )
     }
 }
\ No newline at end of file
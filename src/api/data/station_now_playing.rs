@@ -0,0 +1,74 @@
+use crate::api::api_response::ApiResponse;
+use crate::db::models::StationTitleItem;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize)]
+pub struct StationNowPlayingTitle {
+    pub title: String,
+    pub time: String,
+}
+
+impl From<StationTitleItem> for StationNowPlayingTitle {
+    fn from(item: StationTitleItem) -> Self {
+        StationNowPlayingTitle {
+            title: item.title,
+            time: item.time,
+        }
+    }
+}
+
+/// Now playing title of a station and the last titles before it, newest first
+#[derive(Serialize, Deserialize)]
+pub struct StationNowPlaying {
+    pub stationuuid: String,
+    /// None if the stream does not send titles or is broken
+    pub title: Option<String>,
+    /// Time the title was read from the stream
+    pub time: String,
+    pub history: Vec<StationNowPlayingTitle>,
+}
+
+impl StationNowPlaying {
+    pub fn new(stationuuid: String, current: StationTitleItem, history: Vec<StationTitleItem>) -> Self {
+        StationNowPlaying {
+            stationuuid,
+            title: Some(current.title).filter(|title| !title.is_empty()),
+            time: current.time,
+            history: history.into_iter().map(|item| item.into()).collect(),
+        }
+    }
+
+    pub fn serialize_xml(&self) -> std::io::Result<String> {
+        let mut xml = xml_writer::XmlWriter::new(Vec::new());
+        xml.begin_elem("result")?;
+        {
+            xml.begin_elem("nowplaying")?;
+            xml.attr_esc("stationuuid", &self.stationuuid)?;
+            if let Some(title) = &self.title {
+                xml.attr_esc("title", title)?;
+            }
+            xml.attr_esc("time", &self.time)?;
+            xml.begin_elem("history")?;
+            for item in self.history.iter() {
+                xml.begin_elem("title")?;
+                xml.attr_esc("title", &item.title)?;
+                xml.attr_esc("time", &item.time)?;
+                xml.end_elem()?;
+            }
+            xml.end_elem()?;
+            xml.end_elem()?;
+        }
+        xml.end_elem()?;
+        xml.close()?;
+        xml.flush()?;
+        Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
+    }
+
+    pub fn get_response(&self, format: &str) -> Result<ApiResponse, Box<dyn Error>> {
+        Ok(match format {
+            "json" => ApiResponse::Text(serde_json::to_string(&self)?),
+            "xml" => ApiResponse::Text(self.serialize_xml()?),
+            _ => ApiResponse::UnknownContentType,
+        })
+    }
+}
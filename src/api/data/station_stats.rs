@@ -0,0 +1,109 @@
+use crate::api::api_response::ApiResponse;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize)]
+pub struct StationStatsClicks {
+    pub time: String,
+    pub clicks: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StationStatsStream {
+    pub time: String,
+    pub codec: String,
+    pub bitrate: u32,
+    pub hls: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StationStatsSource {
+    pub source: String,
+    pub checks: u32,
+    pub checks_ok: u32,
+    pub uptime: f64,
+    pub lastchecktime: Option<String>,
+    pub lastcheckok: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StationStats {
+    pub stationuuid: String,
+    pub clicks_hourly: Vec<StationStatsClicks>,
+    pub clicks_daily: Vec<StationStatsClicks>,
+    pub checks: u32,
+    pub checks_ok: u32,
+    pub uptime: f64,
+    pub stream_changes: Vec<StationStatsStream>,
+    pub sources: Vec<StationStatsSource>,
+}
+
+/// Percentage of successful checks, 0 if there were no checks
+pub fn uptime(checks: u32, checks_ok: u32) -> f64 {
+    if checks == 0 {
+        return 0.0;
+    }
+    (checks_ok as f64 * 10000.0 / checks as f64).round() / 100.0
+}
+
+impl StationStats {
+    pub fn serialize_xml(&self) -> std::io::Result<String> {
+        let mut xml = xml_writer::XmlWriter::new(Vec::new());
+        xml.begin_elem("result")?;
+        {
+            xml.begin_elem("stats")?;
+            xml.attr_esc("stationuuid", &self.stationuuid)?;
+            xml.attr_esc("checks", &self.checks.to_string())?;
+            xml.attr_esc("checks_ok", &self.checks_ok.to_string())?;
+            xml.attr_esc("uptime", &self.uptime.to_string())?;
+            for (name, list) in &[("clicks_hourly", &self.clicks_hourly), ("clicks_daily", &self.clicks_daily)] {
+                xml.begin_elem(name)?;
+                for item in list.iter() {
+                    xml.begin_elem("clicks")?;
+                    xml.attr_esc("time", &item.time)?;
+                    xml.attr_esc("clicks", &item.clicks.to_string())?;
+                    xml.end_elem()?;
+                }
+                xml.end_elem()?;
+            }
+            xml.begin_elem("stream_changes")?;
+            for item in self.stream_changes.iter() {
+                xml.begin_elem("stream")?;
+                xml.attr_esc("time", &item.time)?;
+                xml.attr_esc("codec", &item.codec)?;
+                xml.attr_esc("bitrate", &item.bitrate.to_string())?;
+                xml.attr_esc("hls", &item.hls.to_string())?;
+                xml.end_elem()?;
+            }
+            xml.end_elem()?;
+            xml.begin_elem("sources")?;
+            for item in self.sources.iter() {
+                xml.begin_elem("source")?;
+                xml.attr_esc("source", &item.source)?;
+                xml.attr_esc("checks", &item.checks.to_string())?;
+                xml.attr_esc("checks_ok", &item.checks_ok.to_string())?;
+                xml.attr_esc("uptime", &item.uptime.to_string())?;
+                if let Some(lastchecktime) = &item.lastchecktime {
+                    xml.attr_esc("lastchecktime", lastchecktime)?;
+                }
+                if let Some(lastcheckok) = item.lastcheckok {
+                    xml.attr_esc("lastcheckok", &lastcheckok.to_string())?;
+                }
+                xml.end_elem()?;
+            }
+            xml.end_elem()?;
+            xml.end_elem()?;
+        }
+        xml.end_elem()?;
+        xml.close()?;
+        xml.flush()?;
+        Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
+    }
+
+    pub fn get_response(&self, format: &str) -> Result<ApiResponse, Box<dyn Error>> {
+        Ok(match format {
+            "json" => ApiResponse::Text(serde_json::to_string(&self)?),
+            "xml" => ApiResponse::Text(self.serialize_xml()?),
+            _ => ApiResponse::UnknownContentType,
+        })
+    }
+}
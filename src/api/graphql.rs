@@ -0,0 +1,546 @@
+use crate::api::data::Station;
+use crate::api::data::StationCheck;
+use crate::api::data::StationClick;
+use crate::api::data::StationHistoryCurrent;
+use crate::db::models::ExtraInfo;
+use crate::db::DbConnection;
+use crate::normalize::TagNormalizer;
+
+use juniper::{
+    DefaultScalarValue, EmptyMutation, Executor, FieldError, FieldResult, LookAheadMethods,
+    LookAheadSelection, LookAheadValue, RootNode, Value,
+};
+
+/// Maximum nesting of fields below a root field (station { checks { ok } } has depth 3)
+const MAX_DEPTH: usize = 5;
+/// Maximum estimated count of resolved fields, list fields count once for every
+/// item they may return according to their limit argument
+const MAX_COMPLEXITY: u64 = 20000;
+/// Estimated cost of a nested field that needs its own database query,
+/// it is counted once for every item of the parent list
+const NESTED_QUERY_COMPLEXITY: u64 = 20;
+
+pub struct GraphQLContext {
+    pub connection: Box<dyn DbConnection + Send + Sync>,
+    pub tag_normalizer: TagNormalizer,
+}
+
+impl juniper::Context for GraphQLContext {}
+
+pub struct Query;
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<GraphQLContext>>;
+
+pub fn create_schema() -> Schema {
+    Schema::new(Query, EmptyMutation::new())
+}
+
+fn to_u32(value: i32) -> u32 {
+    if value < 0 {
+        0
+    } else {
+        value as u32
+    }
+}
+
+fn to_i32(value: u32) -> i32 {
+    if value > i32::MAX as u32 {
+        i32::MAX
+    } else {
+        value as i32
+    }
+}
+
+/// Default value of the limit argument for list fields, has to match
+/// the defaults in the field definitions below
+fn get_default_list_size(field_name: &str) -> u64 {
+    match field_name {
+        "stations" | "tags" | "tagList" | "countries" | "languages" | "languagecodes" | "codecs" | "checks" | "clicks" => 100,
+        "history" => 10,
+        _ => 1,
+    }
+}
+
+fn get_list_size(selection: &LookAheadSelection<DefaultScalarValue>) -> u64 {
+    if let Some(argument) = selection.argument("uuids") {
+        if let LookAheadValue::List(list) = argument.value() {
+            return list.len() as u64;
+        }
+    }
+    if let Some(argument) = selection.argument("limit") {
+        if let LookAheadValue::Scalar(DefaultScalarValue::Int(limit)) = argument.value() {
+            return to_u32(*limit) as u64;
+        }
+    }
+    get_default_list_size(selection.field_name())
+}
+
+fn get_depth(selection: &LookAheadSelection<DefaultScalarValue>) -> usize {
+    let child_depth = selection
+        .child_names()
+        .iter()
+        .filter_map(|name| selection.select_child(name))
+        .map(|child| get_depth(child))
+        .max()
+        .unwrap_or(0);
+    1 + child_depth
+}
+
+/// Cost of resolving a field once, without its children
+fn get_field_complexity(field_name: &str) -> u64 {
+    match field_name {
+        "checks" | "clicks" | "history" | "tagList" => NESTED_QUERY_COMPLEXITY,
+        _ => 1,
+    }
+}
+
+fn get_complexity(selection: &LookAheadSelection<DefaultScalarValue>) -> u64 {
+    let child_complexity: u64 = selection
+        .child_names()
+        .iter()
+        .filter_map(|name| selection.select_child(name))
+        .map(|child| get_complexity(child))
+        .sum();
+    get_field_complexity(selection.field_name()).saturating_add(get_list_size(selection).saturating_mul(child_complexity))
+}
+
+/// Reject queries that would put too much load on the database, before
+/// any of the nested fields get resolved
+fn check_limits(executor: &Executor<GraphQLContext>) -> FieldResult<()> {
+    let selection = executor.look_ahead();
+    let depth = get_depth(&selection);
+    if depth > MAX_DEPTH {
+        return Err(FieldError::new(
+            format!("Query depth {} is higher than the allowed maximum of {}", depth, MAX_DEPTH),
+            Value::null(),
+        ));
+    }
+    let complexity = get_complexity(&selection);
+    if complexity > MAX_COMPLEXITY {
+        return Err(FieldError::new(
+            format!("Query complexity {} is higher than the allowed maximum of {}", complexity, MAX_COMPLEXITY),
+            Value::null(),
+        ));
+    }
+    Ok(())
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl Query {
+    /// Get a single station by its uuid
+    fn station(executor: &Executor<GraphQLContext>, uuid: String) -> FieldResult<Option<Station>> {
+        check_limits(executor)?;
+        let mut list = executor.context().connection.get_station_by_uuid(&uuid)?;
+        Ok(list.pop().map(|x| x.into()))
+    }
+
+    /// Search stations, if uuids is given all other filters are ignored
+    #[graphql(arguments(
+        name_exact(default = false),
+        tag_exact(default = false),
+        language_exact(default = false),
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn stations(
+        executor: &Executor<GraphQLContext>,
+        uuids: Option<Vec<String>>,
+        name: Option<String>,
+        name_exact: bool,
+        country: Option<String>,
+        countrycode: Option<String>,
+        state: Option<String>,
+        language: Option<String>,
+        language_exact: bool,
+        languagecode: Option<String>,
+        tag: Option<String>,
+        tag_exact: bool,
+        codec: Option<String>,
+        order: String,
+        reverse: bool,
+        hidebroken: bool,
+        offset: i32,
+        limit: i32,
+    ) -> FieldResult<Vec<Station>> {
+        check_limits(executor)?;
+        let connection = &executor.context().connection;
+        let tag = if tag_exact { executor.context().tag_normalizer.canonical_option(tag) } else { tag };
+        let mut list = match uuids {
+            Some(uuids) => connection.get_stations_by_uuid(uuids)?,
+            None => connection.get_stations_advanced(
+                name, name_exact, country, false, countrycode.into_iter().collect(), state.into_iter().collect(), false,
+                language.into_iter().collect(), language_exact, languagecode.into_iter().collect(), tag, tag_exact, vec![], true, vec![],
+                codec.into_iter().collect(), vec![], 0, 1000000,
+                None, None, 0, 0, None, 0, &order, reverse, hidebroken, to_u32(offset), to_u32(limit),
+            )?,
+        };
+        Ok(list.drain(..).map(|x| x.into()).collect())
+    }
+
+    #[graphql(arguments(
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn tags(executor: &Executor<GraphQLContext>, filter: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: i32, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        check_limits(executor)?;
+        Ok(executor.context().connection.get_extra("TagCache", "TagName", filter, order, reverse, hidebroken, to_u32(offset), to_u32(limit))?)
+    }
+
+    #[graphql(arguments(
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn languages(executor: &Executor<GraphQLContext>, filter: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: i32, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        check_limits(executor)?;
+        Ok(executor.context().connection.get_extra("LanguageCache", "LanguageName", filter, order, reverse, hidebroken, to_u32(offset), to_u32(limit))?)
+    }
+
+    #[graphql(arguments(
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn languagecodes(executor: &Executor<GraphQLContext>, filter: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: i32, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        check_limits(executor)?;
+        Ok(executor.context().connection.get_extra("LanguageCodeCache", "LanguageCode", filter, order, reverse, hidebroken, to_u32(offset), to_u32(limit))?)
+    }
+
+    #[graphql(arguments(
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn countries(executor: &Executor<GraphQLContext>, filter: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: i32, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        check_limits(executor)?;
+        Ok(executor.context().connection.get_extra("CountryCache", "CountryName", filter, order, reverse, hidebroken, to_u32(offset), to_u32(limit))?)
+    }
+
+    #[graphql(arguments(
+        order(default = String::from("name")),
+        reverse(default = false),
+        hidebroken(default = false),
+        offset(default = 0),
+        limit(default = 100),
+    ))]
+    fn codecs(executor: &Executor<GraphQLContext>, filter: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: i32, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        check_limits(executor)?;
+        Ok(executor.context().connection.get_extra("CodecCache", "CodecName", filter, order, reverse, hidebroken, to_u32(offset), to_u32(limit))?)
+    }
+
+    /// Latest checks of all stations or the check history of a single station
+    #[graphql(arguments(seconds(default = 0), limit(default = 100)))]
+    fn checks(executor: &Executor<GraphQLContext>, stationuuid: Option<String>, seconds: i32, limit: i32) -> FieldResult<Vec<StationCheck>> {
+        check_limits(executor)?;
+        let include_history = stationuuid.is_some();
+        let mut list = executor.context().connection.get_checks(stationuuid, None, to_u32(seconds), include_history, to_u32(limit))?;
+        Ok(list.drain(..).map(|x| x.into()).collect())
+    }
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl Station {
+    fn stationuuid(&self) -> &str {
+        &self.stationuuid
+    }
+
+    fn changeuuid(&self) -> &str {
+        &self.changeuuid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn url_resolved(&self) -> &str {
+        &self.url_resolved
+    }
+
+    fn homepage(&self) -> &str {
+        &self.homepage
+    }
+
+    fn favicon(&self) -> &str {
+        &self.favicon
+    }
+
+    fn tags(&self) -> &str {
+        &self.tags
+    }
+
+    fn country(&self) -> &str {
+        &self.country
+    }
+
+    fn countrycode(&self) -> &str {
+        &self.countrycode
+    }
+
+    fn state(&self) -> &str {
+        &self.state
+    }
+
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn languagecodes(&self) -> &str {
+        &self.languagecodes
+    }
+
+    fn votes(&self) -> i32 {
+        self.votes
+    }
+
+    fn lastchangetime(&self) -> &str {
+        &self.lastchangetime
+    }
+
+    fn codec(&self) -> &str {
+        &self.codec
+    }
+
+    fn bitrate(&self) -> i32 {
+        to_i32(self.bitrate)
+    }
+
+    fn hls(&self) -> bool {
+        self.hls == 1
+    }
+
+    fn lastcheckok(&self) -> bool {
+        self.lastcheckok == 1
+    }
+
+    fn lastchecktime(&self) -> &str {
+        &self.lastchecktime
+    }
+
+    fn lastcheckoktime(&self) -> &str {
+        &self.lastcheckoktime
+    }
+
+    fn lastlocalchecktime(&self) -> &str {
+        &self.lastlocalchecktime
+    }
+
+    fn clicktimestamp(&self) -> &str {
+        &self.clicktimestamp
+    }
+
+    fn clickcount(&self) -> i32 {
+        to_i32(self.clickcount)
+    }
+
+    fn clicktrend(&self) -> i32 {
+        self.clicktrend
+    }
+
+    /// Codec that was found in the audio frames by the last deep check
+    fn audio_codec(&self) -> &str {
+        &self.audio_codec
+    }
+
+    fn audio_bitrate(&self) -> i32 {
+        to_i32(self.audio_bitrate)
+    }
+
+    fn audio_samplerate(&self) -> i32 {
+        to_i32(self.audio_samplerate)
+    }
+
+    fn audio_channels(&self) -> i32 {
+        to_i32(self.audio_channels)
+    }
+
+
+    /// Current check of every check server, or all stored checks if history is true
+    #[graphql(arguments(history(default = false), seconds(default = 0), limit(default = 100)))]
+    fn checks(&self, context: &GraphQLContext, history: bool, seconds: i32, limit: i32) -> FieldResult<Vec<StationCheck>> {
+        let mut list = context.connection.get_checks(Some(self.stationuuid.clone()), None, to_u32(seconds), history, to_u32(limit))?;
+        Ok(list.drain(..).map(|x| x.into()).collect())
+    }
+
+    #[graphql(arguments(seconds(default = 0), limit(default = 100)))]
+    fn clicks(&self, context: &GraphQLContext, seconds: i32, limit: i32) -> FieldResult<Vec<StationClick>> {
+        let mut list = context.connection.get_clicks(Some(self.stationuuid.clone()), None, to_u32(seconds), to_u32(limit))?;
+        Ok(list.drain(..).map(|x| x.into()).collect())
+    }
+
+    /// Older versions of this station, newest first
+    #[graphql(arguments(limit(default = 10)))]
+    fn history(&self, context: &GraphQLContext, limit: i32) -> FieldResult<Vec<StationHistoryCurrent>> {
+        let mut list = context.connection.get_changes(Some(self.stationuuid.clone()), None, true, to_u32(limit))?;
+        Ok(list.drain(..).map(|x| x.into()).collect())
+    }
+
+    /// Tags of this station with the count of stations that share them
+    #[graphql(arguments(hidebroken(default = false), limit(default = 100)))]
+    fn tag_list(&self, context: &GraphQLContext, hidebroken: bool, limit: i32) -> FieldResult<Vec<ExtraInfo>> {
+        let tags: Vec<String> = self.tags.split(',').map(|tag| tag.trim().to_string()).filter(|tag| tag != "").take(to_u32(limit) as usize).collect();
+        let mut found = context.connection.get_extra_by_names("TagCache", "TagName", &tags, hidebroken)?;
+        // same order as the tags of the station
+        Ok(tags.iter().filter_map(|tag| found.iter().position(|item| item.name.to_lowercase() == tag.to_lowercase()).map(|index| found.swap_remove(index))).collect())
+    }
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl StationCheck {
+    fn checkuuid(&self) -> &str {
+        &self.checkuuid
+    }
+
+    fn stationuuid(&self) -> &str {
+        &self.stationuuid
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn codec(&self) -> &str {
+        &self.codec
+    }
+
+    fn bitrate(&self) -> i32 {
+        to_i32(self.bitrate)
+    }
+
+    fn hls(&self) -> bool {
+        self.hls == 1
+    }
+
+    fn ok(&self) -> bool {
+        self.ok == 1
+    }
+
+    fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    fn urlcache(&self) -> &str {
+        &self.urlcache
+    }
+
+    fn failure_reason(&self) -> Option<&String> {
+        self.failure_reason.as_ref()
+    }
+
+    fn failure_message(&self) -> Option<&String> {
+        self.failure_message.as_ref()
+    }
+
+    /// Only set by deep checks
+    fn audio_codec(&self) -> Option<&String> {
+        self.audio_codec.as_ref()
+    }
+
+    fn audio_bitrate(&self) -> Option<i32> {
+        self.audio_bitrate.map(to_i32)
+    }
+
+    fn audio_samplerate(&self) -> Option<i32> {
+        self.audio_samplerate.map(to_i32)
+    }
+
+    fn audio_channels(&self) -> Option<i32> {
+        self.audio_channels.map(|channels| channels as i32)
+    }
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl StationClick {
+    fn clickuuid(&self) -> &str {
+        &self.clickuuid
+    }
+
+    fn stationuuid(&self) -> &str {
+        &self.stationuuid
+    }
+
+    fn clicktimestamp(&self) -> &str {
+        &self.clicktimestamp
+    }
+}
+
+#[juniper::object(Context = GraphQLContext, name = "StationChange")]
+impl StationHistoryCurrent {
+    fn changeuuid(&self) -> &str {
+        &self.changeuuid
+    }
+
+    fn stationuuid(&self) -> &str {
+        &self.stationuuid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn homepage(&self) -> &str {
+        &self.homepage
+    }
+
+    fn favicon(&self) -> &str {
+        &self.favicon
+    }
+
+    fn tags(&self) -> &str {
+        &self.tags
+    }
+
+    fn country(&self) -> &str {
+        &self.country
+    }
+
+    fn countrycode(&self) -> &str {
+        &self.countrycode
+    }
+
+    fn state(&self) -> &str {
+        &self.state
+    }
+
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn votes(&self) -> i32 {
+        self.votes
+    }
+
+    fn lastchangetime(&self) -> &str {
+        &self.lastchangetime
+    }
+}
+
+#[juniper::object(Context = GraphQLContext, name = "Category")]
+impl ExtraInfo {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn stationcount(&self) -> i32 {
+        to_i32(self.stationcount)
+    }
+}
@@ -6,11 +6,13 @@ extern crate dns_lookup;
 
 pub mod data;
 mod parameters;
-mod prometheus_exporter;
+pub mod prometheus_exporter;
 mod api_error;
 mod api_response;
 mod cache;
 mod all_params;
+mod graphql;
+mod openapi;
 
 use all_params::AllParameters;
 use prometheus_exporter::RegistryLinks;
@@ -18,7 +20,10 @@ use prometheus_exporter::RegistryLinks;
 use api_response::ApiResponse;
 
 use std::error::Error;
+use std::collections::HashSet;
 use std::convert::TryInto;
+use std::io::Read;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 use api_error::ApiError;
@@ -26,18 +31,36 @@ use api_error::ApiError;
 use self::parameters::RequestParameters;
 
 use crate::api::data::ResultMessage;
+use crate::api::data::ErrorMessage;
 use crate::api::data::StationCachedInfo;
 use crate::api::data::StationHistoryCurrent;
 use crate::api::data::Station;
 use crate::api::data::StationCheck;
 use crate::api::data::StationAddResult;
 use crate::api::data::Status;
+use crate::api::data::StationStats;
+use crate::api::data::StationStatsClicks;
+use crate::api::data::StationStatsSource;
+use crate::api::data::StationStatsStream;
 use crate::api::data::StationClick;
 use crate::api::data::ApiConfig;
+use crate::api::data::CheckLease;
+use crate::api::data::CheckLeaseRequest;
+use crate::api::data::CheckLeaseResult;
+use crate::api::data::CheckLeaseStation;
+use crate::api::data::HealthWorker;
+use crate::api::data::StationNowPlaying;
+use crate::check;
+use crate::health;
 use crate::db::DbConnection;
+use crate::db::DbError;
 use crate::db::models::ExtraInfo;
 use crate::db::models::State;
 use crate::db::models::StationItem;
+use crate::db::models::StatsHistoryItem;
+use crate::db::models::StationCheckItemNew;
+use crate::db::models::StationTitleItem;
+use crate::uuid::Uuid;
 use crate::api::rouille::Response;
 use crate::api::rouille::Request;
 use std;
@@ -46,6 +69,8 @@ use self::dns_lookup::lookup_addr;
 
 use crate::config;
 use crate::config::Config;
+use crate::config::Role;
+use crate::normalize::TagNormalizer;
 
 use std::fs::File;
 use self::serde_json::value::{Map};
@@ -106,24 +131,36 @@ fn encode_changes(list : Vec<StationHistoryCurrent>, format : &str) -> Result<Ap
     })
 }
 
-fn encode_message(status: Result<String, Box<dyn Error>>, format : &str) -> Result<ApiResponse, Box<dyn Error>> {
+fn encode_message(message: String, format : &str) -> Result<ApiResponse, Box<dyn Error>> {
     Ok(match format {
         "json" => {
-            match status {
-                Ok(message) => ApiResponse::Text(serde_json::to_string(&ResultMessage::new(true,message))?),
-                Err(err) => ApiResponse::Text(serde_json::to_string(&ResultMessage::new(false,err.to_string()))?),
-            }
+            ApiResponse::Text(serde_json::to_string(&ResultMessage::new(true,message))?)
         },
         "xml" => {
-            match status {
-                Ok(message) => ApiResponse::Text(ResultMessage::new(true,message).serialize_xml()?),
-                Err(err) => ApiResponse::Text(ResultMessage::new(false,err.to_string()).serialize_xml()?),
-            }
+            ApiResponse::Text(ResultMessage::new(true,message).serialize_xml()?)
         },
         _ => ApiResponse::UnknownContentType
     })
 }
 
+/// Error responses use the output format of the request if possible,
+/// all other formats get a json body
+fn encode_error(err: &ApiError, format : &str) -> rouille::Response {
+    let error_message = ErrorMessage::new(err.code(), err.message());
+    let response = match format {
+        "xml" => error_message.serialize_xml()
+            .map(|text| rouille::Response::text(text).with_unique_header("Content-Type", "text/xml"))
+            .map_err(|e| e.to_string()),
+        _ => serde_json::to_string(&error_message)
+            .map(|text| rouille::Response::text(text).with_unique_header("Content-Type", "application/json"))
+            .map_err(|e| e.to_string()),
+    };
+    match response {
+        Ok(response) => response.with_status_code(err.status_code()),
+        Err(e) => rouille::Response::text(e).with_status_code(500),
+    }
+}
+
 fn encode_station_url<A>(connection_new: &A, station: Option<StationItem>, ip: &str, format : &str, seconds: u64, registry: RegistryLinks) -> Result<ApiResponse, Box<dyn Error>> where A: DbConnection {
     Ok(match station {
         Some(station) => {
@@ -150,7 +187,21 @@ fn encode_station_url<A>(connection_new: &A, station: Option<StationItem>, ip: &
                 _ => ApiResponse::UnknownContentType
             }
         },
-        _ => ApiResponse::NotFound
+        None => {
+            return Err(Box::new(ApiError::StationNotFound(String::from("could not find station with matching id"))));
+        }
+    })
+}
+
+fn encode_stats_history(list : Vec<StatsHistoryItem>, format : &str) -> Result<ApiResponse, Box<dyn Error>> {
+    Ok(match format {
+        "json" => {
+            ApiResponse::Text(serde_json::to_string(&list)?)
+        },
+        "xml" => {
+            ApiResponse::Text(StatsHistoryItem::serialize_stats_history(list)?)
+        },
+        _ => ApiResponse::UnknownContentType
     })
 }
 
@@ -247,7 +298,7 @@ pub fn start<A: 'static +  std::clone::Clone>(
     let listen_str = format!("{}:{}", config.listen_host, config.listen_port);
     info!("Listen on {} with {} threads", listen_str, config.threads);
 
-    let registry = prometheus_exporter::create_registry(&config.prometheus_exporter_prefix);
+    let registry = prometheus_exporter::create_registry(&config.prometheus_exporter_prefix, config.roles.contains(&Role::Check));
     if let Ok(registry) = registry {
         let cache = cache::GenericCache::new(config.cache_type.clone().into(), config.cache_url.clone(), config.cache_ttl.as_secs().try_into().expect("cache-ttl is too high"));
 
@@ -262,8 +313,10 @@ pub fn start<A: 'static +  std::clone::Clone>(
             });
         }
 
+        let schema = Arc::new(graphql::create_schema());
+
         rouille::start_server_with_pool(listen_str, Some(config.threads), move |request| {
-            handle_connection(&connection_new, request, config.clone(), registry.clone(), cache.clone())
+            handle_connection(&connection_new, request, config.clone(), registry.clone(), cache.clone(), &schema)
         });
     }
 }
@@ -286,6 +339,187 @@ fn get_status<A>(connection_new: &A) -> Result<Status, Box<dyn std::error::Error
     )
 }
 
+fn not_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+/// Add a station, if add_validate_stream is enabled the stream is checked first,
+/// broken streams and with deep_check streams without audio frames are rejected,
+/// the check result is stored with the new station
+fn add_station<A>(connection_new: &A, config: &Config, all_params: AllParameters, format: &str) -> Result<ApiResponse, Box<dyn std::error::Error>> where A: DbConnection {
+    let mut name = all_params.param_name;
+    let mut homepage = all_params.param_homepage;
+    let mut favicon = all_params.param_favicon;
+    let mut tags = all_params.param_tags;
+
+    let check = match (config.add_validate_stream, &all_params.param_url) {
+        (true, Some(url)) => {
+            let check = check::check_stream(url, "", &config.source, config.add_validate_timeout.as_secs(), config.max_depth, 1, false, config.deep_check);
+            if !check.check_ok {
+                return Err(Box::new(ApiError::InvalidStation(format!(
+                    "url is not a working stream ({}): {}",
+                    check.failure_reason.clone().unwrap_or_default(),
+                    check.failure_message.clone().unwrap_or_default(),
+                ))));
+            }
+            // the codec from the http headers is often unknown, only the frames found by a deep check prove that there is no audio
+            if config.deep_check && check.audio_codec.is_none() {
+                return Err(Box::new(ApiError::InvalidStation(String::from("url is not an audio stream, no audio frames found"))));
+            }
+            // metadata of the stream is used for fields that were not given
+            name = not_empty(name).or(not_empty(check.name.clone()));
+            homepage = not_empty(homepage).or(not_empty(check.homepage.clone()));
+            favicon = not_empty(favicon).or(not_empty(check.favicon.clone()));
+            tags = not_empty(tags).or(not_empty(check.tags.clone()));
+            Some(check)
+        }
+        _ => None,
+    };
+
+    let tags = tags.map(|tags| TagNormalizer::new(&config.tag_synonyms).canonical_list(&tags));
+    let stationuuid = connection_new.add_station_opt(name, all_params.param_url, homepage, favicon, all_params.param_countrycode, all_params.param_state, all_params.param_language, tags)?;
+    if let Some(mut check) = check {
+        check.station_uuid = stationuuid.clone();
+        let list = vec![check];
+        connection_new.insert_checks(&list)?;
+        connection_new.update_station_with_check_data(&list, true)?;
+    }
+    StationAddResult::new_ok(stationuuid).get_response(format)
+}
+
+/// Check a station right now with the same logic as the check worker and return the new check,
+/// limited per station by check_on_demand_interval and per ip by check_on_demand_per_hour
+fn check_station_on_demand<A>(connection_new: &A, config: &Config, stationuuid: &str, remote_ip: &str, format: &str) -> Result<ApiResponse, Box<dyn std::error::Error>> where A: DbConnection {
+    if config.check_on_demand_per_hour == 0 {
+        return Ok(ApiResponse::Locked("On demand checks not enabled!".to_string()));
+    }
+    let station = get_only_first_item(connection_new.get_station_by_uuid(stationuuid)?)
+        .ok_or(ApiError::StationNotFound(String::from("could not find station with matching id")))?;
+
+    connection_new.insert_check_request(remote_ip, &station.stationuuid, "check", config.check_on_demand_interval.as_secs(), config.check_on_demand_per_hour)?;
+
+    check::dbcheck_station(
+        connection_new,
+        &station,
+        &config.source,
+        &config.useragent,
+        config.tcp_timeout.as_secs(),
+        config.max_depth,
+        config.retries,
+        config.favicon,
+        config.now_playing,
+        config.deep_check,
+        config.now_playing_history,
+    )?;
+    let list: Vec<StationCheck> = connection_new.get_checks(Some(station.stationuuid.clone()), None, 0, false, u32::MAX)?
+        .drain(..)
+        .filter(|item| item.source == config.source)
+        .map(|item| item.into())
+        .collect();
+    StationCheck::get_response(list, format)
+}
+
+/// Now playing title of a station, the stream is only asked if the stored title is older than now_playing_cache,
+/// so the stored titles work as cache for all api servers of the database
+fn get_station_now_playing<A>(connection_new: &A, config: &Config, stationuuid: &str, remote_ip: &str, format: &str) -> Result<ApiResponse, Box<dyn std::error::Error>> where A: DbConnection {
+    if !config.now_playing {
+        return Ok(ApiResponse::Locked("Now playing not enabled!".to_string()));
+    }
+    let station = get_only_first_item(connection_new.get_station_by_uuid(stationuuid)?)
+        .ok_or(ApiError::StationNotFound(String::from("could not find station with matching id")))?;
+
+    let current = match connection_new.get_station_now_playing(&station.stationuuid)? {
+        Some((current, age)) if age < config.now_playing_cache.as_secs() => current,
+        stored => match ask_station_now_playing(connection_new, config, &station, remote_ip)? {
+            Some(current) => current,
+            // another request is already asking the stream or the ip is over its limit
+            None => stored.map(|(current, _)| current).unwrap_or_else(|| StationTitleItem {
+                title: String::new(),
+                time: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            }),
+        },
+    };
+    let history = if config.now_playing_history > 0 { connection_new.get_station_titles(&station.stationuuid)? } else { vec![] };
+    StationNowPlaying::new(station.stationuuid, current, history).get_response(format)
+}
+
+/// Ask the stream of a station for its title, only one request per station and now_playing_cache
+/// asks the stream and every request counts to the on demand check limit of the ip,
+/// None if the limits do not allow it
+fn ask_station_now_playing<A>(connection_new: &A, config: &Config, station: &StationItem, remote_ip: &str) -> Result<Option<StationTitleItem>, Box<dyn std::error::Error>> where A: DbConnection {
+    if config.check_on_demand_per_hour == 0 {
+        return Ok(None);
+    }
+    if let Err(err) = connection_new.insert_check_request(remote_ip, &station.stationuuid, "nowplaying", config.now_playing_cache.as_secs(), config.check_on_demand_per_hour) {
+        if let Some(DbError::RateLimitError(_)) = err.downcast_ref::<DbError>() {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+    let check = check::check_stream(&station.url, &station.stationuuid, &config.source, config.tcp_timeout.as_secs(), config.max_depth, 1, true, false);
+    // streams without title are stored with an empty one, so that they are not asked on every request
+    let title = check.title.unwrap_or_default();
+    connection_new.insert_station_titles(&[(station.stationuuid.clone(), title.clone())], config.now_playing_history)?;
+    Ok(Some(StationTitleItem {
+        title,
+        time: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    }))
+}
+
+fn get_station_stats<A>(connection_new: &A, stationuuid: &str) -> Result<StationStats, Box<dyn std::error::Error>> where A: DbConnection {
+    if connection_new.get_station_by_uuid(stationuuid)?.len() == 0 {
+        return Err(Box::new(ApiError::StationNotFound(String::from("could not find station with matching id"))));
+    }
+
+    let clicks_hourly = connection_new.get_station_clicks_hourly(stationuuid, 48)?
+        .drain(..).map(|(time, clicks)| StationStatsClicks { time, clicks }).collect();
+    let clicks_daily = connection_new.get_station_clicks_daily(stationuuid, 30)?
+        .drain(..).map(|(time, clicks)| StationStatsClicks { time, clicks }).collect();
+
+    // only keep checks where codec, bitrate or hls differ from the check before
+    let mut stream_changes: Vec<StationStatsStream> = vec![];
+    for (time, codec, bitrate, hls) in connection_new.get_station_stream_history(stationuuid, 30, 10000)? {
+        let changed = match stream_changes.last() {
+            Some(last) => last.codec != codec || last.bitrate != bitrate || last.hls != hls,
+            None => true,
+        };
+        if changed {
+            stream_changes.push(StationStatsStream { time, codec, bitrate, hls });
+        }
+    }
+
+    let last_checks = connection_new.get_checks(Some(stationuuid.to_string()), None, 0, false, u32::MAX)?;
+    let mut checks = 0;
+    let mut checks_ok = 0;
+    let sources: Vec<StationStatsSource> = connection_new.get_station_check_sources(stationuuid)?
+        .drain(..)
+        .map(|(source, source_checks, source_checks_ok)| {
+            checks += source_checks;
+            checks_ok += source_checks_ok;
+            let last_check = last_checks.iter().find(|check| check.source == source);
+            StationStatsSource {
+                uptime: data::uptime(source_checks, source_checks_ok),
+                lastchecktime: last_check.map(|check| check.check_time.clone()),
+                lastcheckok: last_check.map(|check| check.check_ok),
+                source,
+                checks: source_checks,
+                checks_ok: source_checks_ok,
+            }
+        })
+        .collect();
+
+    Ok(StationStats {
+        stationuuid: stationuuid.to_string(),
+        clicks_hourly,
+        clicks_daily,
+        checks,
+        checks_ok,
+        uptime: data::uptime(checks, checks_ok),
+        stream_changes,
+        sources,
+    })
+}
+
 fn send_file(path: &str, content_type: &'static str) -> ApiResponse {
     let file = File::open(path);
     match file {
@@ -357,7 +591,8 @@ fn handle_connection<A>(
     config: Config,
     registry: RegistryLinks,
     cache: cache::GenericCache,
-) -> rouille::Response where A: DbConnection {
+    schema: &graphql::Schema,
+) -> rouille::Response where A: DbConnection, A: std::clone::Clone + std::marker::Send + std::marker::Sync + 'static {
     let remote_ip: String = request.header("X-Forwarded-For").unwrap_or(&request.remote_addr().ip().to_string()).to_string();
     let referer: String = request.header("Referer").unwrap_or(&"-".to_string()).to_string();
     let user_agent: String = request.header("User-agent").unwrap_or(&"-".to_string()).to_string();
@@ -399,52 +634,27 @@ fn handle_connection<A>(
     };
     rouille::log_custom(request, log_ok, log_err, || {
         let timer = registry.timer.with_label_values(&[request.method()]).start_timer();
-        let result = handle_cached_connection(connection_new, request, config, registry, cache);
+        let result = handle_cached_connection(connection_new, request, config, registry, cache, schema);
         let r = match result {
             Ok(response) => add_cors(response),
             Err(err) => {
-                let err_str = err.to_string();
-                error!("{}", err_str);
-                add_cors(rouille::Response::text(err_str).with_status_code(500))
-            } 
+                let api_error = ApiError::from_error(&err);
+                if api_error.status_code() >= 500 {
+                    error!("{}", err);
+                } else {
+                    debug!("{}", err);
+                }
+                let format = request.url().split('/').nth(1).unwrap_or("").to_string();
+                add_cors(encode_error(&api_error, &format))
+            }
         };
         timer.observe_duration();
         r
     })
 }
 
-fn handle_cached_connection<A>(
-    connection_new: &A,
-    request: &rouille::Request,
-    config: Config,
-    registry: RegistryLinks,
-    mut cache: cache::GenericCache,
-) -> Result<rouille::Response, Box<dyn std::error::Error>> where A: DbConnection {
-    if request.method() == "OPTIONS" {
-        return Ok(rouille::Response::empty_204());
-    }
-    if request.method() != "POST" && request.method() != "GET" {
-        return Ok(rouille::Response::empty_404());
-    }
-
-    let header_host = request.header("X-Forwarded-Host").or(request.header("Host"));
-    let base_url = match header_host {
-        Some(header_host) => format!("http://{host}", host = header_host),
-        None => config.server_url.clone(),
-    };
-    trace!("header_host: {:?}", header_host);
-    trace!("base_url: {:?}", base_url);
-    let content_type_raw: &str = request.header("Content-Type").unwrap_or("nothing");
-    let content_type_arr: Vec<&str> = content_type_raw.split(";").collect();
-    if content_type_arr.len() == 0{
-        return Ok(rouille::Response::empty_400());
-    }
-    let content_type = content_type_arr[0].trim();
-
-    let remote_ip: String = request.header("X-Forwarded-For").unwrap_or(&request.remote_addr().ip().to_string()).to_string();
-
-    let ppp = RequestParameters::new(&request);
-
+/// Read all parameters of a request, values that are not allowed are added as errors to ppp
+fn get_all_parameters(request: &rouille::Request, ppp: &RequestParameters) -> AllParameters {
     let allparams = AllParameters {
         url: request.raw_url().to_string(),
         param_uuids: str_to_arr(&ppp.get_string("uuids").unwrap_or(String::new())),
@@ -465,13 +675,23 @@ fn handle_cached_connection<A>(
         param_state_exact: ppp.get_bool("stateExact", false),
         param_language: ppp.get_string("language"),
         param_language_exact: ppp.get_bool("languageExact", false),
+        param_languagecodes: ppp.get_string("languagecodes"),
         param_tag: ppp.get_string("tag"),
         param_tag_exact: ppp.get_bool("tagExact", false),
         param_tag_list: str_to_arr(&ppp.get_string("tagList").unwrap_or(String::new())),
+        param_tag_match: ppp.get_string("tagMatch").unwrap_or(String::from("all")),
+        param_tag_exclude: str_to_arr(&ppp.get_string("tagExclude").unwrap_or(String::new())),
         param_codec: ppp.get_string("codec"),
+        param_codec_exclude: str_to_arr(&ppp.get_string("codecExclude").unwrap_or(String::new())),
     
         param_bitrate_min: ppp.get_number("bitrateMin", 0),
         param_bitrate_max: ppp.get_number("bitrateMax", 1000000),
+        param_hls: ppp.get_bool_option("hls"),
+        param_lastcheckok: ppp.get_bool_option("lastcheckok"),
+        param_votes_min: ppp.get_number("votesMin", 0),
+        param_clickcount_min: ppp.get_number("clickcountMin", 0),
+        param_has_favicon: ppp.get_bool_option("hasFavicon"),
+        param_lastcheck_age_max: ppp.get_number("lastcheckAgeMax", 0),
         param_order: ppp.get_string("order").unwrap_or(String::from("name")),
         param_reverse: ppp.get_bool("reverse", false),
         param_hidebroken: ppp.get_bool("hidebroken", false),
@@ -480,8 +700,76 @@ fn handle_cached_connection<A>(
     
         param_seconds: ppp.get_number("seconds", 0),
         param_url: ppp.get_string("url"),
+        param_from: ppp.get_string("from"),
+        param_to: ppp.get_string("to"),
+        param_resolution: ppp.get_string("resolution").unwrap_or(String::from("day")),
     };
 
+    if !allparams.is_valid_order() {
+        ppp.add_error(format!("order by '{}' is not allowed", allparams.param_order));
+    }
+    if !allparams.is_valid_tag_match() {
+        ppp.add_error(format!("parameter 'tagMatch' has to be 'any' or 'all', got '{}'", allparams.param_tag_match));
+    }
+    if !allparams.is_valid_resolution() {
+        ppp.add_error(format!("parameter 'resolution' has to be 'hour', 'day', 'week' or 'month', got '{}'", allparams.param_resolution));
+    }
+    for (name, value) in &[("from", &allparams.param_from), ("to", &allparams.param_to)] {
+        if let Some(value) = value {
+            if !AllParameters::is_valid_time(value) {
+                ppp.add_error(format!("parameter '{}' has to be a time like '2020-12-24' or '2020-12-24 18:00:00', got '{}'", name, value));
+            }
+        }
+    }
+    allparams
+}
+
+fn handle_cached_connection<A>(
+    connection_new: &A,
+    request: &rouille::Request,
+    config: Config,
+    registry: RegistryLinks,
+    mut cache: cache::GenericCache,
+    schema: &graphql::Schema,
+) -> Result<rouille::Response, Box<dyn std::error::Error>> where A: DbConnection, A: std::clone::Clone + std::marker::Send + std::marker::Sync + 'static {
+    if request.method() == "OPTIONS" {
+        return Ok(rouille::Response::empty_204());
+    }
+    if request.method() != "POST" && request.method() != "GET" {
+        return Err(Box::new(ApiError::NotFound(format!("method '{}' is not supported", request.method()))));
+    }
+
+    let header_host = request.header("X-Forwarded-Host").or(request.header("Host"));
+    let base_url = match header_host {
+        Some(header_host) => format!("http://{host}", host = header_host),
+        None => config.server_url.clone(),
+    };
+    trace!("header_host: {:?}", header_host);
+    trace!("base_url: {:?}", base_url);
+    let content_type_raw: &str = request.header("Content-Type").unwrap_or("nothing");
+    let content_type_arr: Vec<&str> = content_type_raw.split(";").collect();
+    if content_type_arr.len() == 0{
+        return Err(Box::new(ApiError::IllegalParameter(String::from("header 'Content-Type' is malformed"))));
+    }
+    let content_type = content_type_arr[0].trim();
+
+    let remote_ip: String = request.header("X-Forwarded-For").unwrap_or(&request.remote_addr().ip().to_string()).to_string();
+
+    if request.url() == "/graphql" {
+        return handle_graphql(connection_new, request, content_type, schema, &config);
+    }
+    if request.url() == "/health" {
+        return Ok(handle_health(connection_new, &config));
+    }
+    if request.url().starts_with("/checkworker/") {
+        return handle_check_worker(connection_new, request, &config);
+    }
+
+    let ppp = RequestParameters::new(&request);
+
+    let allparams = get_all_parameters(request, &ppp);
+    ppp.validate(config.lenient_parameters)?;
+
     let key = allparams.to_string()?;
     let cached_item = cache.get(&key);
     let mut is_text = false;
@@ -508,23 +796,19 @@ fn handle_cached_connection<A>(
                 ApiResponse::File(content_type, file) => {
                     rouille::Response::from_file(content_type, file)
                 },
+                // errors are answered by encode_error in the output format of the request
                 ApiResponse::NotFound => {
-                    rouille::Response::empty_404()
+                    return Err(Box::new(ApiError::NotFound(format!("unknown path '{}'", request.url()))));
                 },
                 ApiResponse::UnknownContentType => {
                     rouille::Response::empty_406()
                 },
                 ApiResponse::ServerError(msg) => {
-                    rouille::Response::text(msg).with_status_code(500)
+                    return Err(Box::new(ApiError::InternalError(msg)));
                 },
                 ApiResponse::Locked(msg) => {
-                    rouille::Response::text(msg).with_status_code(423)
-                },
-                /*
-                ApiResponse::ParameterError(msg) => {
-                    rouille::Response::text(msg).with_status_code(400)
+                    return Err(Box::new(ApiError::NotEnabled(msg)));
                 },
-                */
             }
         }
     };
@@ -539,11 +823,13 @@ fn handle_cached_connection<A>(
                 "html" => result.with_unique_header("Content-Type", "text/html"),
                 "" => result.with_unique_header("Content-Type", "text/html"),
                 "json" => result.with_unique_header("Content-Type", "application/json"),
+                "openapi.json" => result.with_unique_header("Content-Type", "application/json"),
                 "xml" => result.with_unique_header("Content-Type", "text/xml"),
                 "m3u" => result.with_unique_header("Content-Type", "audio/mpegurl").with_unique_header("Content-Disposition", r#"inline; filename="playlist.m3u""#),
                 "pls" => result.with_unique_header("Content-Type", "audio/x-scpls").with_unique_header("Content-Disposition", r#"inline; filename="playlist.pls""#),
                 "xspf" => result.with_unique_header("Content-Type", "application/xspf+xml").with_unique_header("Content-Disposition", r#"inline; filename="playlist.xspf""#),
                 "ttl" => result.with_unique_header("Content-Type", "text/turtle"),
+                "opml" => result.with_unique_header("Content-Type", "text/x-opml").with_unique_header("Content-Disposition", r#"inline; filename="playlist.opml""#),
                 _ => result,
             }
         }else{
@@ -556,6 +842,183 @@ fn handle_cached_connection<A>(
     }
 }
 
+/// GraphQL queries are never cached, because the query text may contain
+/// any combination of fields and arguments
+fn handle_graphql<A>(
+    connection_new: &A,
+    request: &rouille::Request,
+    content_type: &str,
+    schema: &graphql::Schema,
+    config: &Config,
+) -> Result<rouille::Response, Box<dyn std::error::Error>> where A: DbConnection, A: std::clone::Clone + std::marker::Send + std::marker::Sync + 'static {
+    let graphql_request: juniper::http::GraphQLRequest = if request.method() == "POST" {
+        let mut body = String::new();
+        match request.data() {
+            Some(mut data) => {
+                data.read_to_string(&mut body)?;
+            }
+            None => {
+                return Err(Box::new(ApiError::IllegalParameter(String::from("request body is missing"))));
+            }
+        }
+        match content_type {
+            "application/json" => serde_json::from_str(&body)?,
+            "application/graphql" => juniper::http::GraphQLRequest::new(body, None, None),
+            _ => {
+                return Ok(rouille::Response::empty_406());
+            }
+        }
+    } else {
+        match request.get_param("query") {
+            Some(query) => {
+                let variables = match request.get_param("variables") {
+                    Some(variables) => Some(serde_json::from_str(&variables)?),
+                    None => None,
+                };
+                juniper::http::GraphQLRequest::new(query, request.get_param("operationName"), variables)
+            }
+            None => {
+                return Err(Box::new(ApiError::IllegalParameter(String::from("parameter 'query' is missing"))));
+            }
+        }
+    };
+
+    let context = graphql::GraphQLContext {
+        connection: Box::new(connection_new.clone()),
+        tag_normalizer: TagNormalizer::new(&config.tag_synonyms),
+    };
+    let graphql_response = graphql_request.execute(schema, &context);
+    let status_code = if graphql_response.is_ok() { 200 } else { 400 };
+    Ok(rouille::Response::text(serde_json::to_string(&graphql_response)?)
+        .with_unique_header("Content-Type", "application/json")
+        .with_no_cache()
+        .with_status_code(status_code))
+}
+
+/// Health of the roles of this process, the api is healthy if the database answers
+fn handle_health<A>(connection_new: &A, config: &Config) -> rouille::Response where A: DbConnection {
+    let mut health = health::get_health(&config.roles);
+    let api_result = connection_new.get_station_count_working();
+    health.healthy = health.healthy && api_result.is_ok();
+    health.workers.insert(0, HealthWorker {
+        name: String::from("api"),
+        healthy: api_result.is_ok(),
+        lastrun: None,
+        lastok: None,
+        lasterror: api_result.err().map(|err| err.to_string()),
+    });
+    health::get_response(&health)
+}
+
+/// Maximum count of stations in one lease of a check worker
+const MAX_CHECK_LEASE_STATIONS: u32 = 1000;
+
+/// Reserve the next stations to check for a check worker, the same priority
+/// as for the local check is used
+fn lease_check_stations<A>(connection_new: &A, config: &Config, lease_request: CheckLeaseRequest) -> Result<CheckLease, Box<dyn std::error::Error>> where A: DbConnection, A: std::clone::Clone {
+    if lease_request.source.trim().is_empty() {
+        return Err(Box::new(ApiError::IllegalParameter(String::from("source of check worker is missing"))));
+    }
+    let check_priority = &config.check_priority;
+    let mut connection = connection_new.clone();
+    let stations = connection.get_stations_to_check(
+        check_priority.interval.as_secs(),
+        check_priority.interval_popular.as_secs(),
+        check_priority.popular_clickcount,
+        check_priority.popular_votes,
+        check_priority.interval_broken.as_secs(),
+        check_priority.broken_recent.as_secs(),
+        check_priority.backoff_step.as_secs(),
+        check_priority.interval_max.as_secs(),
+        lease_request.limit.min(MAX_CHECK_LEASE_STATIONS),
+    )?;
+
+    let leaseuuid = Uuid::new_v4().to_hyphenated().to_string();
+    let timeout_seconds = config.check_lease_timeout.as_secs();
+    let stationuuids: Vec<String> = stations.iter().map(|station| station.stationuuid.clone()).collect();
+    connection_new.insert_check_lease(&leaseuuid, &lease_request.source, &stationuuids, timeout_seconds)?;
+    // stations that another worker leased at the same time are not part of this lease
+    let leased: HashSet<String> = connection_new.get_check_lease_stations(&leaseuuid, &lease_request.source)?.into_iter().collect();
+    Ok(CheckLease {
+        leaseuuid,
+        timeout_seconds,
+        stations: stations
+            .into_iter()
+            .filter(|station| leased.contains(&station.stationuuid))
+            .map(|station| CheckLeaseStation {
+                stationuuid: station.stationuuid,
+                name: station.name,
+                url: station.url,
+            })
+            .collect(),
+    })
+}
+
+/// Store the checks of a check worker for the stations of its lease and end the lease
+fn store_check_lease_result<A>(connection_new: &A, config: &Config, result: CheckLeaseResult) -> Result<ResultMessage, Box<dyn std::error::Error>> where A: DbConnection {
+    let leased: HashSet<String> = connection_new.get_check_lease_stations(&result.leaseuuid, &result.source)?.into_iter().collect();
+    if leased.is_empty() {
+        return Err(Box::new(ApiError::IllegalParameter(format!("lease '{}' is unknown or expired", result.leaseuuid))));
+    }
+    let source = result.source;
+    let list: Vec<StationCheckItemNew> = result.checks
+        .into_iter()
+        .filter(|check| leased.contains(&check.station_uuid))
+        .map(|mut check| {
+            check.checkuuid = None;
+            check.timestamp = None;
+            check.source = source.clone();
+            check
+        })
+        .collect();
+    connection_new.insert_checks(&list)?;
+    connection_new.update_station_with_check_data(&list, true)?;
+    if config.now_playing {
+        check::insert_titles(connection_new, &list, config.now_playing_history)?;
+    }
+    connection_new.delete_check_lease(&result.leaseuuid)?;
+    Ok(ResultMessage::new(true, format!("stored {} checks", list.len())))
+}
+
+/// Check workers lease stations with /checkworker/lease and post the checks to /checkworker/results,
+/// both as json and with the token of check-coordinator-token in the Authorization header
+fn handle_check_worker<A>(
+    connection_new: &A,
+    request: &rouille::Request,
+    config: &Config,
+) -> Result<rouille::Response, Box<dyn std::error::Error>> where A: DbConnection, A: std::clone::Clone {
+    if config.check_coordinator_token.is_empty() {
+        return Err(Box::new(ApiError::NotEnabled(String::from("Check coordinator not enabled!"))));
+    }
+    let token = request.header("Authorization").map(|value| value.trim_start_matches("Bearer ").trim());
+    if !token.map(|token| config.check_coordinator_token.matches(token)).unwrap_or(false) {
+        return Err(Box::new(ApiError::Unauthorized(String::from("token of check worker is missing or wrong"))));
+    }
+    if request.method() != "POST" {
+        return Err(Box::new(ApiError::NotFound(format!("method '{}' is not supported", request.method()))));
+    }
+    let mut body = String::new();
+    match request.data() {
+        Some(mut data) => {
+            data.read_to_string(&mut body)?;
+        }
+        None => {
+            return Err(Box::new(ApiError::IllegalParameter(String::from("request body is missing"))));
+        }
+    }
+    let decode_error = |err: serde_json::Error| ApiError::IllegalParameter(format!("unable to decode json body: {}", err));
+    let text = match request.url().as_str() {
+        "/checkworker/lease" => serde_json::to_string(&lease_check_stations(connection_new, config, serde_json::from_str(&body).map_err(decode_error)?)?)?,
+        "/checkworker/results" => serde_json::to_string(&store_check_lease_result(connection_new, config, serde_json::from_str(&body).map_err(decode_error)?)?)?,
+        _ => {
+            return Err(Box::new(ApiError::NotFound(format!("unknown path '{}'", request.url()))));
+        }
+    };
+    Ok(rouille::Response::text(text)
+        .with_unique_header("Content-Type", "application/json")
+        .with_no_cache())
+}
+
 fn do_api_calls<A>(all_params: AllParameters,
     connection_new: &A,
     config: Config,
@@ -573,6 +1036,11 @@ fn do_api_calls<A>(all_params: AllParameters,
         y.into_owned()
     }).collect();
 
+    // only paths of the openapi routes are handled, so that the document lists every endpoint
+    if openapi::get_route(&items).is_none() {
+        return Ok((false, ApiResponse::NotFound));
+    }
+
     if items.len() == 2 {
         let file_name: &str = &items[1];
         match file_name {
@@ -586,6 +1054,7 @@ fn do_api_calls<A>(all_params: AllParameters,
             "favicon.ico" => Ok((true,send_file(&format!("{}/{}",config.static_files_dir,"favicon.ico"), "image/png"))),
             "robots.txt" => Ok((true,send_file(&format!("{}/{}",config.static_files_dir,"robots.txt"), "text/plain"))),
             "main.css" => Ok((true,send_file(&format!("{}/{}",config.static_files_dir,"main.css"),"text/css"))),
+            "openapi.json" => Ok((true,ApiResponse::Text(openapi::render(&base_url)?))),
             "" => {
                 let mut handlebars = Handlebars::new();
                 let y = handlebars.register_template_file("docs.hbs", &format!("{}/{}",config.static_files_dir,"docs.hbs"));
@@ -609,18 +1078,19 @@ fn do_api_calls<A>(all_params: AllParameters,
         let filter : Option<String> = None;
 
         match command {
-            "languages" => Ok((true,encode_extra(connection_new.get_extra("LanguageCache", "LanguageName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "language")?)),
-            "countries" => Ok((true,encode_extra(connection_new.get_1_n("Country", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "country")?)),
-            "countrycodes" => Ok((true,encode_extra(connection_new.get_1_n("CountryCode", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "countrycode")?)),
+            "languages" => Ok((true,encode_extra(connection_new.get_extra("LanguageCache", "LanguageName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "language")?)),
+            "languagecodes" => Ok((true,encode_extra(connection_new.get_extra("LanguageCodeCache", "LanguageCode", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "languagecode")?)),
+            "countries" => Ok((true,encode_extra(connection_new.get_extra("CountryCache", "CountryName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "country")?)),
+            "countrycodes" => Ok((true,encode_extra(connection_new.get_extra("CountryCodeCache", "CountryCode", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "countrycode")?)),
             "states" => Ok((true,encode_states(connection_new.get_states(None, filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format)?)),
-            "codecs" => Ok((true,encode_extra(connection_new.get_1_n("Codec", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "codec")?)),
-            "tags" => Ok((true,encode_extra(connection_new.get_extra("TagCache", "TagName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "tag")?)),
+            "codecs" => Ok((true,encode_extra(connection_new.get_extra("CodecCache", "CodecName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "codec")?)),
+            "tags" => Ok((true,encode_extra(connection_new.get_extra("TagCache", "TagName", filter, all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "tag")?)),
             "stations" => Ok((true,Station::get_response(connection_new.get_stations_by_all(&all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, all_params.param_offset, all_params.param_limit)?.drain(..).map(|x|x.into()).collect(), format)?)),
             "servers" => Ok((true,dns_resolve(format)?)),
             "stats" => Ok((true,encode_status(get_status(connection_new)?, format, &config.static_files_dir))),
-            "checks" => Ok((true,StationCheck::get_response(connection_new.get_checks(None, all_params.param_last_checkuuid, all_params.param_seconds, false)?.drain(..).map(|x|x.into()).collect(),format)?)),
-            "clicks" => Ok((true,StationClick::get_response(connection_new.get_clicks(None, all_params.param_last_clickuuid, all_params.param_seconds)?.drain(..).map(|x|x.into()).collect(),format)?)),
-            "add" => Ok((false,StationAddResult::from(connection_new.add_station_opt(all_params.param_name, all_params.param_url, all_params.param_homepage, all_params.param_favicon, all_params.param_countrycode, all_params.param_state, all_params.param_language, all_params.param_tags)).get_response(format)?)),
+            "checks" => Ok((true,StationCheck::get_response(connection_new.get_checks(None, all_params.param_last_checkuuid, all_params.param_seconds, false, u32::MAX)?.drain(..).map(|x|x.into()).collect(),format)?)),
+            "clicks" => Ok((true,StationClick::get_response(connection_new.get_clicks(None, all_params.param_last_clickuuid, all_params.param_seconds, u32::MAX)?.drain(..).map(|x|x.into()).collect(),format)?)),
+            "add" => Ok((false,add_station(connection_new, &config, all_params, format)?)),
             "config" => Ok((true,ApiConfig::get_response(config.into(),format)?)),
             _ => Ok((true,ApiResponse::NotFound)),
         }
@@ -629,37 +1099,65 @@ fn do_api_calls<A>(all_params: AllParameters,
         let command:&str = &items[2];
         let parameter:&str = &items[3];
 
-        // None => connection_new.get_1_n("Country", filter, param_order, param_reverse, param_hidebroken)?, format, "country")?,
         match command {
-            "languages" => Ok((true,encode_extra(connection_new.get_extra("LanguageCache", "LanguageName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "language")?)),
-            "countries" => Ok((true,encode_extra(connection_new.get_1_n("Country", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "country")?)),
-            "countrycodes" => Ok((true,encode_extra(connection_new.get_1_n("CountryCode", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "countrycode")?)),
-            "codecs" => Ok((true,encode_extra(connection_new.get_1_n("Codec", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "codec")?)),
-            "tags" => Ok((true,encode_extra(connection_new.get_extra("TagCache", "TagName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format, "tag")?)),
+            "languages" => Ok((true,encode_extra(connection_new.get_extra("LanguageCache", "LanguageName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "language")?)),
+            "languagecodes" => Ok((true,encode_extra(connection_new.get_extra("LanguageCodeCache", "LanguageCode", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "languagecode")?)),
+            "countries" => Ok((true,encode_extra(connection_new.get_extra("CountryCache", "CountryName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "country")?)),
+            "countrycodes" => Ok((true,encode_extra(connection_new.get_extra("CountryCodeCache", "CountryCode", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "countrycode")?)),
+            "codecs" => Ok((true,encode_extra(connection_new.get_extra("CodecCache", "CodecName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "codec")?)),
+            "tags" => Ok((true,encode_extra(connection_new.get_extra("TagCache", "TagName", Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken, 0, u32::MAX)?, format, "tag")?)),
             "states" => Ok((true,encode_states(connection_new.get_states(None, Some(String::from(parameter)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format)?)),
-            "vote" => Ok((false,encode_message(connection_new.vote_for_station(&remote_ip, get_only_first_item(connection_new.get_station_by_uuid(parameter)?)), format)?)),
+            "stats" => {
+                match parameter {
+                    "history" => {
+                        let from = all_params.param_from.filter(|value| AllParameters::is_valid_time(value));
+                        let to = all_params.param_to.filter(|value| AllParameters::is_valid_time(value));
+                        Ok((true,encode_stats_history(connection_new.get_stats_history(from, to, &all_params.param_resolution, str_to_arr(&all_params.param_countrycode.unwrap_or_default()))?, format)?))
+                    },
+                    _ => Ok((true,ApiResponse::NotFound)),
+                }
+            },
+            "check" => Ok((false,check_station_on_demand(connection_new, &config, parameter, &remote_ip, format)?)),
+            "nowplaying" => Ok((false,get_station_now_playing(connection_new, &config, parameter, &remote_ip, format)?)),
+            "vote" => Ok((false,encode_message(connection_new.vote_for_station(&remote_ip, get_only_first_item(connection_new.get_station_by_uuid(parameter)?))?, format)?)),
             "url" => Ok((false,encode_station_url(connection_new, get_only_first_item(connection_new.get_station_by_uuid(parameter)?), &remote_ip, format, config.click_valid_timeout.as_secs(),registry)?)),
             "stations" => {
                 match parameter {
                     "topvote" => Ok((true,Station::get_response(connection_new.get_stations_topvote(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "topclick" => Ok((true,Station::get_response(connection_new.get_stations_topclick(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                    "trending" => Ok((true,Station::get_response(connection_new.get_stations_trending(None, None, all_params.param_hidebroken, all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "lastclick" => Ok((true,Station::get_response(connection_new.get_stations_lastclick(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "lastchange" => Ok((true,Station::get_response(connection_new.get_stations_lastchange(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "broken" => Ok((true,Station::get_response(connection_new.get_stations_broken(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "improvable" => Ok((true,Station::get_response(connection_new.get_stations_improvable(all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                    "changed" => Ok((true,encode_changes(connection_new.get_changes(None, all_params.param_last_changeuuid)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                    "changed" => Ok((true,encode_changes(connection_new.get_changes(None, all_params.param_last_changeuuid, false, u32::MAX)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "byurl" => Ok((true,Station::get_response(connection_new.get_stations_by_column_multiple("Url", all_params.param_url,true,&all_params.param_order,all_params.param_reverse,
                         all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                     "byuuid" => Ok((true,Station::get_response(connection_new.get_stations_by_uuid(all_params.param_uuids)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                    "search" => Ok((true,Station::get_response(connection_new.get_stations_advanced(all_params.param_name, all_params.param_name_exact, all_params.param_country,
-                        all_params.param_country_exact, all_params.param_countrycode, all_params.param_state, all_params.param_state_exact, all_params.param_language, all_params.param_language_exact, all_params.param_tag,
-                        all_params.param_tag_exact, all_params.param_tag_list, all_params.param_codec, all_params.param_bitrate_min, all_params.param_bitrate_max, &all_params.param_order,all_params.param_reverse,
-                        all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                    "search" => {
+                        // exact tag searches find stations by the canonical form of the tag
+                        let tag_normalizer = TagNormalizer::new(&config.tag_synonyms);
+                        let (tag, tag_list) = if all_params.param_tag_exact {
+                            (tag_normalizer.canonical_option(all_params.param_tag), tag_normalizer.canonical_vec(all_params.param_tag_list))
+                        } else {
+                            (all_params.param_tag, all_params.param_tag_list)
+                        };
+                        let tag_exclude = tag_normalizer.canonical_vec(all_params.param_tag_exclude);
+                        Ok((true,Station::get_response(connection_new.get_stations_advanced(all_params.param_name, all_params.param_name_exact, all_params.param_country,
+                            all_params.param_country_exact, str_to_arr(&all_params.param_countrycode.unwrap_or_default()), str_to_arr(&all_params.param_state.unwrap_or_default()), all_params.param_state_exact,
+                            str_to_arr(&all_params.param_language.unwrap_or_default()), all_params.param_language_exact,
+                            str_to_arr(&all_params.param_languagecodes.unwrap_or_default()), tag,
+                            all_params.param_tag_exact, tag_list, all_params.param_tag_match != "any", tag_exclude,
+                            str_to_arr(&all_params.param_codec.unwrap_or_default()), all_params.param_codec_exclude, all_params.param_bitrate_min, all_params.param_bitrate_max,
+                            all_params.param_hls, all_params.param_lastcheckok, all_params.param_votes_min, all_params.param_clickcount_min, all_params.param_has_favicon, all_params.param_lastcheck_age_max,
+                            &all_params.param_order,all_params.param_reverse,
+                            all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?))
+                    },
                     _ => Ok((true,ApiResponse::NotFound)),
                 }
             },
-            "checks" => Ok((true,StationCheck::get_response(connection_new.get_checks(Some(parameter.to_string()), all_params.param_last_checkuuid, all_params.param_seconds, true)?.drain(..).map(|x|x.into()).collect(), format)?)),
-            "clicks" => Ok((true,StationClick::get_response(connection_new.get_clicks(Some(parameter.to_string()), all_params.param_last_clickuuid, all_params.param_seconds)?.drain(..).map(|x|x.into()).collect(), format)?)),
+            "checks" => Ok((true,StationCheck::get_response(connection_new.get_checks(Some(parameter.to_string()), all_params.param_last_checkuuid, all_params.param_seconds, true, u32::MAX)?.drain(..).map(|x|x.into()).collect(), format)?)),
+            "clicks" => Ok((true,StationClick::get_response(connection_new.get_clicks(Some(parameter.to_string()), all_params.param_last_clickuuid, all_params.param_seconds, u32::MAX)?.drain(..).map(|x|x.into()).collect(), format)?)),
             _ => Ok((true,ApiResponse::NotFound)),
         }
     } else if items.len() == 5 {
@@ -677,12 +1175,21 @@ fn do_api_calls<A>(all_params: AllParameters,
             }
         }else{
             match command {
+                "stats" => {
+                    match parameter {
+                        "station" => Ok((true,get_station_stats(connection_new, search)?.get_response(format)?)),
+                        _ => Ok((true,ApiResponse::NotFound)),
+                    }
+                },
                 "states" => Ok((true,encode_states(connection_new.get_states(Some(String::from(parameter)), Some(String::from(search)), all_params.param_order, all_params.param_reverse, all_params.param_hidebroken)?, format)?)),
                 
                 "stations" => {
                     match parameter {
                         "topvote" => Ok((true,Station::get_response(connection_new.get_stations_topvote(search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "topclick" => Ok((true,Station::get_response(connection_new.get_stations_topclick(search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "trending" => Ok((true,Station::get_response(connection_new.get_stations_trending(None, None, all_params.param_hidebroken, search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "trendingbycountrycode" => Ok((true,Station::get_response(connection_new.get_stations_trending(Some(search.to_uppercase()), None, all_params.param_hidebroken, all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "trendingbytag" => Ok((true,Station::get_response(connection_new.get_stations_trending(None, Some(TagNormalizer::new(&config.tag_synonyms).canonical(search)), all_params.param_hidebroken, all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "lastclick" => Ok((true,Station::get_response(connection_new.get_stations_lastclick(search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "lastchange" => Ok((true,Station::get_response(connection_new.get_stations_lastchange(search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "broken" => Ok((true,Station::get_response(connection_new.get_stations_broken(search.parse().unwrap_or(0))?.drain(..).map(|x| x.into()).collect(), format)?)),
@@ -696,12 +1203,19 @@ fn do_api_calls<A>(all_params: AllParameters,
                         "bycountrycodeexact" => Ok((true,Station::get_response(connection_new.get_stations_by_column("CountryCode", search.to_string(),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "bystate" => Ok((true,Station::get_response(connection_new.get_stations_by_column("Subcountry", search.to_string(),false,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
                         "bystateexact" => Ok((true,Station::get_response(connection_new.get_stations_by_column("Subcountry", search.to_string(),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                        "bytag" => Ok((true,Station::get_response(connection_new.get_stations_by_column_multiple("Tags", Some(search.to_string()),false,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                        "bytagexact" => Ok((true,Station::get_response(connection_new.get_stations_by_column_multiple("Tags", Some(search.to_string()),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                        "bylanguage" => Ok((true,Station::get_response(connection_new.get_stations_by_column_multiple("Language", Some(search.to_string()),false,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                        "bylanguageexact" => Ok((true,Station::get_response(connection_new.get_stations_by_column_multiple("Language", Some(search.to_string()),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "bytag" => Ok((true,Station::get_response_grouped(connection_new.get_stations_by_column_multiple("Tags", Some(search.to_string()),false,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format, "tags")?)),
+                        "bytagexact" => Ok((true,Station::get_response_grouped(connection_new.get_stations_by_column_multiple("Tags", Some(TagNormalizer::new(&config.tag_synonyms).canonical(search)),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format, "tags")?)),
+                        "bylanguage" => Ok((true,Station::get_response_grouped(connection_new.get_stations_by_column_multiple("Language", Some(search.to_string()),false,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format, "language")?)),
+                        "bylanguageexact" => Ok((true,Station::get_response_grouped(connection_new.get_stations_by_column_multiple("Language", Some(search.to_string()),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format, "language")?)),
+                        "bylanguagecode" => Ok((true,Station::get_response_grouped(connection_new.get_stations_by_column_multiple("LanguageCodes", Some(search.to_lowercase()),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format, "language")?)),
                         "byuuid" => Ok((true,Station::get_response(connection_new.get_stations_by_column("StationUuid", search.to_string(),true,&all_params.param_order,all_params.param_reverse,all_params.param_hidebroken,all_params.param_offset,all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?)),
-                        "changed" => Ok((true,encode_changes(connection_new.get_changes(Some(search.to_string()),all_params.param_last_changeuuid)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "changed" => Ok((true,encode_changes(connection_new.get_changes(Some(search.to_string()),all_params.param_last_changeuuid, false, u32::MAX)?.drain(..).map(|x| x.into()).collect(), format)?)),
+                        "similar" => {
+                            if connection_new.get_station_by_uuid(search)?.len() == 0 {
+                                return Err(Box::new(ApiError::StationNotFound(String::from("could not find station with matching id"))));
+                            }
+                            Ok((true,Station::get_response(connection_new.get_stations_similar(search, all_params.param_hidebroken, all_params.param_limit)?.drain(..).map(|x| x.into()).collect(), format)?))
+                        },
                         _ => Ok((true,ApiResponse::NotFound)),
                     }
                 },
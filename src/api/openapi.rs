@@ -0,0 +1,639 @@
+use serde_json::json;
+use serde_json::value::Map;
+use serde_json::Value;
+
+/// Type of a request parameter, as parsed by RequestParameters
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ParameterType {
+    String,
+    Bool,
+    Number,
+    /// comma separated list of strings
+    List,
+}
+
+pub struct ParameterInfo {
+    pub name: &'static str,
+    pub param_type: ParameterType,
+    pub default: Option<&'static str>,
+    pub description: &'static str,
+}
+
+pub struct RouteInfo {
+    /// path with placeholders in curly braces, {format} is replaced by one of the formats
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub formats: &'static [&'static str],
+    pub parameters: &'static [&'static str],
+    /// name of the schema in components, None for responses without a schema
+    pub schema: Option<&'static str>,
+    pub is_list: bool,
+    pub deprecated: bool,
+}
+
+pub struct SchemaInfo {
+    pub name: &'static str,
+    /// (name, type, nullable), type "array:Name" is an array of the schema Name
+    pub properties: &'static [(&'static str, &'static str, bool)],
+}
+
+const FORMATS_STATIONS: &[&str] = &["json", "xml", "csv", "m3u", "pls", "xspf", "ttl", "opml"];
+const FORMATS_LIST: &[&str] = &["json", "xml", "csv"];
+const FORMATS_SINGLE: &[&str] = &["json", "xml"];
+const FORMATS_URL: &[&str] = &["json", "xml", "m3u", "pls"];
+const FORMATS_STATUS: &[&str] = &["json", "xml", "html"];
+const FORMATS_NONE: &[&str] = &[];
+
+const PARAMS_NONE: &[&str] = &[];
+const PARAMS_LIST: &[&str] = &["order", "reverse", "hidebroken"];
+const PARAMS_STATIONS: &[&str] = &["order", "reverse", "hidebroken", "offset", "limit"];
+const PARAMS_LIMIT: &[&str] = &["limit"];
+const PARAMS_CHECKS: &[&str] = &["lastcheckuuid", "seconds"];
+const PARAMS_CLICKS: &[&str] = &["lastclickuuid", "seconds"];
+const PARAMS_CHANGES: &[&str] = &["lastchangeuuid"];
+const PARAMS_ADD: &[&str] = &["name", "url", "homepage", "favicon", "countrycode", "state", "language", "tags"];
+const PARAMS_BYURL: &[&str] = &["url", "order", "reverse", "hidebroken", "offset", "limit"];
+const PARAMS_BYUUID: &[&str] = &["uuids"];
+const PARAMS_SIMILAR: &[&str] = &["hidebroken", "limit"];
+const PARAMS_TRENDING: &[&str] = &["hidebroken", "limit"];
+const PARAMS_HIDEBROKEN: &[&str] = &["hidebroken"];
+const PARAMS_STATS_HISTORY: &[&str] = &["from", "to", "resolution", "countrycode"];
+const PARAMS_SEARCH: &[&str] = &[
+    "name", "nameExact", "country", "countryExact", "countrycode", "state", "stateExact", "language", "languageExact", "languagecodes",
+    "tag", "tagExact", "tagList", "tagMatch", "tagExclude", "codec", "codecExclude", "bitrateMin", "bitrateMax",
+    "hls", "lastcheckok", "votesMin", "clickcountMin", "hasFavicon", "lastcheckAgeMax", "order", "reverse", "hidebroken", "offset", "limit",
+];
+
+/// All parameters that are parsed into AllParameters
+pub const PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo { name: "uuids", param_type: ParameterType::List, default: None, description: "Comma separated list of station uuids" },
+    ParameterInfo { name: "tags", param_type: ParameterType::String, default: None, description: "Comma separated list of tags" },
+    ParameterInfo { name: "homepage", param_type: ParameterType::String, default: None, description: "Homepage of the station" },
+    ParameterInfo { name: "favicon", param_type: ParameterType::String, default: None, description: "Link to an image file of the station" },
+    ParameterInfo { name: "lastchangeuuid", param_type: ParameterType::String, default: None, description: "Return only changes after the change with this uuid" },
+    ParameterInfo { name: "lastcheckuuid", param_type: ParameterType::String, default: None, description: "Return only checks after the check with this uuid" },
+    ParameterInfo { name: "lastclickuuid", param_type: ParameterType::String, default: None, description: "Return only clicks after the click with this uuid" },
+    ParameterInfo { name: "name", param_type: ParameterType::String, default: None, description: "Name of the station" },
+    ParameterInfo { name: "nameExact", param_type: ParameterType::Bool, default: Some("false"), description: "Only exact matches of name" },
+    ParameterInfo { name: "country", param_type: ParameterType::String, default: None, description: "Country of the station" },
+    ParameterInfo { name: "countryExact", param_type: ParameterType::Bool, default: Some("false"), description: "Only exact matches of country" },
+    ParameterInfo { name: "countrycode", param_type: ParameterType::String, default: None, description: "2-digit countrycode (ISO 3166-1 alpha-2), comma separated for multiple countries" },
+    ParameterInfo { name: "state", param_type: ParameterType::String, default: None, description: "State of the station, comma separated for multiple states" },
+    ParameterInfo { name: "stateExact", param_type: ParameterType::Bool, default: Some("false"), description: "Only exact matches of state" },
+    ParameterInfo { name: "language", param_type: ParameterType::String, default: None, description: "Language of the station, comma separated for multiple languages" },
+    ParameterInfo { name: "languageExact", param_type: ParameterType::Bool, default: Some("false"), description: "Only exact matches of language" },
+    ParameterInfo { name: "languagecodes", param_type: ParameterType::String, default: None, description: "ISO 639 language code of the station, comma separated for multiple languages" },
+    ParameterInfo { name: "tag", param_type: ParameterType::String, default: None, description: "A tag of the station" },
+    ParameterInfo { name: "tagExact", param_type: ParameterType::Bool, default: Some("false"), description: "Only exact matches of tag" },
+    ParameterInfo { name: "tagList", param_type: ParameterType::List, default: None, description: "Comma separated list of tags, tagMatch decides if all or any of them have to match" },
+    ParameterInfo { name: "tagMatch", param_type: ParameterType::String, default: Some("all"), description: "Stations have to match 'all' or 'any' of the tags in tagList" },
+    ParameterInfo { name: "tagExclude", param_type: ParameterType::List, default: None, description: "Comma separated list of tags, stations with any of them are excluded" },
+    ParameterInfo { name: "codec", param_type: ParameterType::String, default: None, description: "Codec of the stream, comma separated for multiple codecs" },
+    ParameterInfo { name: "codecExclude", param_type: ParameterType::List, default: None, description: "Comma separated list of codecs, stations with any of them are excluded" },
+    ParameterInfo { name: "bitrateMin", param_type: ParameterType::Number, default: Some("0"), description: "Minimum bitrate in kbps" },
+    ParameterInfo { name: "bitrateMax", param_type: ParameterType::Number, default: Some("1000000"), description: "Maximum bitrate in kbps" },
+    ParameterInfo { name: "hls", param_type: ParameterType::Bool, default: None, description: "Only HLS streams if true, only other streams if false" },
+    ParameterInfo { name: "lastcheckok", param_type: ParameterType::Bool, default: None, description: "Only working stations if true, only broken stations if false" },
+    ParameterInfo { name: "votesMin", param_type: ParameterType::Number, default: Some("0"), description: "Minimum number of votes" },
+    ParameterInfo { name: "clickcountMin", param_type: ParameterType::Number, default: Some("0"), description: "Minimum clickcount of the station, the clicks that are kept for clicks-timeout (30 days by default)" },
+    ParameterInfo { name: "hasFavicon", param_type: ParameterType::Bool, default: None, description: "Only stations with a favicon if true, only stations without if false" },
+    ParameterInfo { name: "lastcheckAgeMax", param_type: ParameterType::Number, default: Some("0"), description: "Only stations that were checked in the last seconds, 0 for no limit" },
+    ParameterInfo { name: "order", param_type: ParameterType::String, default: Some("name"), description: "Name of the attribute the result list will be sorted by" },
+    ParameterInfo { name: "reverse", param_type: ParameterType::Bool, default: Some("false"), description: "Reverse the result list" },
+    ParameterInfo { name: "hidebroken", param_type: ParameterType::Bool, default: Some("false"), description: "Do not count or return broken stations" },
+    ParameterInfo { name: "offset", param_type: ParameterType::Number, default: Some("0"), description: "Starting value of the result list" },
+    ParameterInfo { name: "limit", param_type: ParameterType::Number, default: Some("999999"), description: "Number of returned rows" },
+    ParameterInfo { name: "seconds", param_type: ParameterType::Number, default: Some("0"), description: "Return only items of the last seconds, 0 for no limit" },
+    ParameterInfo { name: "url", param_type: ParameterType::String, default: None, description: "URL of the stream" },
+    ParameterInfo { name: "from", param_type: ParameterType::String, default: None, description: "Start of the time range in UTC (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS), default is 30 days ago" },
+    ParameterInfo { name: "to", param_type: ParameterType::String, default: None, description: "End of the time range in UTC (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS), default is now" },
+    ParameterInfo { name: "resolution", param_type: ParameterType::String, default: Some("day"), description: "Snapshots are averaged per 'hour', 'day', 'week' or 'month'" },
+];
+
+macro_rules! route {
+    ($path:expr, $summary:expr, $formats:expr, $parameters:expr, $schema:expr, $is_list:expr) => {
+        RouteInfo { path: $path, summary: $summary, formats: $formats, parameters: $parameters, schema: $schema, is_list: $is_list, deprecated: false }
+    };
+}
+
+/// All routes that are handled by do_api_calls, other paths are answered with NOT_FOUND
+pub const ROUTES: &[RouteInfo] = &[
+    route!("/", "HTML documentation", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/metrics", "Prometheus metrics", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/openapi.json", "This OpenAPI document", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/favicon.ico", "Icon of the HTML documentation", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/robots.txt", "Rules for web crawlers", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/main.css", "Stylesheet of the HTML documentation", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/graphql", "GraphQL endpoint, query in parameter query or in the request body", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/health", "Health of the roles that run in this process, HTTP status 503 if one is not healthy", FORMATS_NONE, PARAMS_NONE, None, false),
+    route!("/{format}/languages", "List of languages", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/languages/{filter}", "List of languages that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/languagecodes", "List of ISO 639 language codes", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/languagecodes/{filter}", "List of ISO 639 language codes that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/countries", "List of countries", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/countries/{filter}", "List of countries that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/countrycodes", "List of countrycodes", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/countrycodes/{filter}", "List of countrycodes that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/codecs", "List of codecs", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/codecs/{filter}", "List of codecs that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/tags", "List of tags", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/tags/{filter}", "List of tags that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("ExtraInfo"), true),
+    route!("/{format}/states", "List of states", FORMATS_LIST, PARAMS_LIST, Some("State"), true),
+    route!("/{format}/states/{filter}", "List of states that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("State"), true),
+    route!("/{format}/states/{country}/{filter}", "List of states of a country that contain the filter", FORMATS_LIST, PARAMS_LIST, Some("State"), true),
+    route!("/{format}/stations", "List of all stations", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/servers", "List of all api servers", FORMATS_SINGLE, PARAMS_NONE, Some("ServerEntry"), true),
+    route!("/{format}/stats", "Server stats", FORMATS_STATUS, PARAMS_NONE, Some("Status"), false),
+    route!("/{format}/stats/history", "Server stats over time, averaged per resolution", FORMATS_SINGLE, PARAMS_STATS_HISTORY, Some("StatsHistory"), true),
+    route!("/{format}/stats/station/{stationuuid}", "Click histograms, uptime, stream changes and check results per source of a station", FORMATS_SINGLE, PARAMS_NONE, Some("StationStats"), false),
+    route!("/{format}/checks", "Latest check results of all stations", FORMATS_LIST, PARAMS_CHECKS, Some("StationCheck"), true),
+    route!("/{format}/checks/{stationuuid}", "Check history of a station", FORMATS_LIST, PARAMS_CHECKS, Some("StationCheck"), true),
+    route!("/{format}/check/{stationuuid}", "Check a station now and return the new check, rate limited", FORMATS_LIST, PARAMS_NONE, Some("StationCheck"), true),
+    route!("/{format}/nowplaying/{stationuuid}", "Now playing title of a station and the last titles before it", FORMATS_SINGLE, PARAMS_NONE, Some("StationNowPlaying"), false),
+    route!("/{format}/clicks", "List of station clicks", FORMATS_LIST, PARAMS_CLICKS, Some("StationClick"), true),
+    route!("/{format}/clicks/{stationuuid}", "List of clicks of a station", FORMATS_LIST, PARAMS_CLICKS, Some("StationClick"), true),
+    route!("/{format}/add", "Add a station", FORMATS_SINGLE, PARAMS_ADD, Some("StationAddResult"), false),
+    route!("/{format}/config", "Server config", FORMATS_SINGLE, PARAMS_NONE, Some("ApiConfig"), false),
+    route!("/{format}/vote/{stationuuid}", "Vote for a station", FORMATS_SINGLE, PARAMS_NONE, Some("ResultMessage"), false),
+    route!("/{format}/url/{stationuuid}", "Count a click and return the stream url of a station", FORMATS_URL, PARAMS_NONE, Some("StationCachedInfo"), false),
+    route!("/{format}/stations/topvote", "Stations by votes", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/topvote/{rowcount}", "Stations by votes", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/topclick", "Stations by clicks", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/topclick/{rowcount}", "Stations by clicks", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/trending", "Stations with a rising number of clicks", FORMATS_STATIONS, PARAMS_TRENDING, Some("Station"), true),
+    route!("/{format}/stations/trending/{rowcount}", "Stations with a rising number of clicks", FORMATS_STATIONS, PARAMS_HIDEBROKEN, Some("Station"), true),
+    route!("/{format}/stations/trendingbycountrycode/{countrycode}", "Stations of a country with a rising number of clicks", FORMATS_STATIONS, PARAMS_TRENDING, Some("Station"), true),
+    route!("/{format}/stations/trendingbytag/{tag}", "Stations with a tag and a rising number of clicks", FORMATS_STATIONS, PARAMS_TRENDING, Some("Station"), true),
+    route!("/{format}/stations/lastclick", "Stations by recent click", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/lastclick/{rowcount}", "Stations by recent click", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/lastchange", "Stations by recently changed", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/lastchange/{rowcount}", "Stations by recently changed", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/broken", "Broken stations", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/broken/{rowcount}", "Broken stations", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/improvable", "Stations that need improvements", FORMATS_STATIONS, PARAMS_LIMIT, Some("Station"), true),
+    route!("/{format}/stations/improvable/{rowcount}", "Stations that need improvements", FORMATS_STATIONS, PARAMS_NONE, Some("Station"), true),
+    route!("/{format}/stations/changed", "List of station changes", FORMATS_LIST, PARAMS_CHANGES, Some("StationHistory"), true),
+    route!("/{format}/stations/changed/{stationuuid}", "Old versions of a station", FORMATS_LIST, PARAMS_CHANGES, Some("StationHistory"), true),
+    route!("/{format}/stations/byurl", "Stations with an exact url match", FORMATS_STATIONS, PARAMS_BYURL, Some("Station"), true),
+    route!("/{format}/stations/byuuid", "Stations by uuids", FORMATS_STATIONS, PARAMS_BYUUID, Some("Station"), true),
+    route!("/{format}/stations/search", "Advanced station search", FORMATS_STATIONS, PARAMS_SEARCH, Some("Station"), true),
+    route!("/{format}/stations/byname/{searchterm}", "Stations by name", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bynameexact/{searchterm}", "Stations by exact name", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bycodec/{searchterm}", "Stations by codec", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bycodecexact/{searchterm}", "Stations by exact codec", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bycountry/{searchterm}", "Stations by country", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bycountryexact/{searchterm}", "Stations by exact country", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bycountrycodeexact/{searchterm}", "Stations by exact countrycode", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bystate/{searchterm}", "Stations by state", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bystateexact/{searchterm}", "Stations by exact state", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bytag/{searchterm}", "Stations by tag", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bytagexact/{searchterm}", "Stations by exact tag", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bylanguage/{searchterm}", "Stations by language", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bylanguageexact/{searchterm}", "Stations by exact language", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/bylanguagecode/{searchterm}", "Stations by ISO 639 language code", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/byuuid/{searchterm}", "Station by uuid", FORMATS_STATIONS, PARAMS_STATIONS, Some("Station"), true),
+    route!("/{format}/stations/similar/{stationuuid}", "Stations that are similar to a station, by tags, language, country, codec, bitrate and clicks of the same listeners", FORMATS_STATIONS, PARAMS_SIMILAR, Some("Station"), true),
+    RouteInfo { path: "/v2/{format}/url/{stationuuid}", summary: "Count a click and return the stream url of a station", formats: FORMATS_URL, parameters: PARAMS_NONE, schema: Some("StationCachedInfo"), is_list: false, deprecated: true },
+];
+
+pub const SCHEMAS: &[SchemaInfo] = &[
+    SchemaInfo { name: "Station", properties: &[
+        ("changeuuid", "string", false), ("stationuuid", "string", false), ("name", "string", false), ("url", "string", false),
+        ("url_resolved", "string", false), ("homepage", "string", false), ("favicon", "string", false), ("tags", "string", false),
+        ("country", "string", false), ("countrycode", "string", false), ("state", "string", false), ("language", "string", false),
+        ("languagecodes", "string", false), ("votes", "integer", false), ("lastchangetime", "string", false), ("codec", "string", false), ("bitrate", "integer", false),
+        ("hls", "integer", false), ("lastcheckok", "integer", false), ("lastchecktime", "string", false), ("lastcheckoktime", "string", false),
+        ("lastlocalchecktime", "string", false), ("clicktimestamp", "string", false), ("clickcount", "integer", false), ("clicktrend", "integer", false),
+        ("audio_codec", "string", false), ("audio_bitrate", "integer", false), ("audio_samplerate", "integer", false), ("audio_channels", "integer", false),
+    ]},
+    SchemaInfo { name: "StationCheck", properties: &[
+        ("stationuuid", "string", false), ("checkuuid", "string", false), ("source", "string", false), ("codec", "string", false),
+        ("bitrate", "integer", false), ("hls", "integer", false), ("ok", "integer", false), ("timestamp", "string", false), ("urlcache", "string", false),
+        ("metainfo_overrides_database", "integer", true), ("public", "integer", true), ("name", "string", true), ("description", "string", true),
+        ("tags", "string", true), ("countrycode", "string", true), ("homepage", "string", true), ("favicon", "string", true), ("loadbalancer", "string", true),
+        ("failure_reason", "string", true), ("failure_message", "string", true),
+        ("audio_codec", "string", true), ("audio_bitrate", "integer", true), ("audio_samplerate", "integer", true), ("audio_channels", "integer", true),
+    ]},
+    SchemaInfo { name: "StationClick", properties: &[
+        ("stationuuid", "string", false), ("clickuuid", "string", false), ("clicktimestamp", "string", false),
+    ]},
+    SchemaInfo { name: "StationHistory", properties: &[
+        ("changeuuid", "string", false), ("stationuuid", "string", false), ("name", "string", false), ("url", "string", false),
+        ("homepage", "string", false), ("favicon", "string", false), ("tags", "string", false), ("country", "string", false),
+        ("countrycode", "string", false), ("state", "string", false), ("language", "string", false), ("votes", "integer", false),
+        ("lastchangetime", "string", false),
+    ]},
+    SchemaInfo { name: "StationCachedInfo", properties: &[
+        ("ok", "boolean", false), ("message", "string", false), ("stationuuid", "string", false), ("name", "string", false), ("url", "string", false),
+    ]},
+    SchemaInfo { name: "StationAddResult", properties: &[
+        ("ok", "boolean", false), ("message", "string", false), ("uuid", "string", false),
+    ]},
+    SchemaInfo { name: "ResultMessage", properties: &[
+        ("ok", "boolean", false), ("message", "string", false),
+    ]},
+    SchemaInfo { name: "ErrorMessage", properties: &[
+        ("ok", "boolean", false), ("code", "string", false), ("message", "string", false),
+    ]},
+    SchemaInfo { name: "ExtraInfo", properties: &[
+        ("name", "string", false), ("stationcount", "integer", false),
+    ]},
+    SchemaInfo { name: "State", properties: &[
+        ("name", "string", false), ("country", "string", false), ("stationcount", "integer", false),
+    ]},
+    SchemaInfo { name: "ServerEntry", properties: &[
+        ("ip", "string", false), ("name", "string", false),
+    ]},
+    SchemaInfo { name: "Status", properties: &[
+        ("supported_version", "integer", false), ("software_version", "string", true), ("status", "string", false), ("stations", "integer", false),
+        ("stations_broken", "integer", false), ("tags", "integer", false), ("clicks_last_hour", "integer", false), ("clicks_last_day", "integer", false),
+        ("languages", "integer", false), ("countries", "integer", false),
+    ]},
+    SchemaInfo { name: "StatsHistory", properties: &[
+        ("time", "string", false), ("stations", "integer", false), ("stations_broken", "integer", false), ("tags", "integer", false),
+        ("languages", "integer", false), ("countries", "integer", false), ("clicks_last_hour", "integer", false), ("checks_last_hour", "integer", false),
+        ("countrycodes", "array:StatsHistoryCountry", false),
+    ]},
+    SchemaInfo { name: "StatsHistoryCountry", properties: &[
+        ("countrycode", "string", false), ("stations", "integer", false), ("stations_working", "integer", false),
+    ]},
+    SchemaInfo { name: "StationStats", properties: &[
+        ("stationuuid", "string", false), ("clicks_hourly", "array:StationStatsClicks", false), ("clicks_daily", "array:StationStatsClicks", false),
+        ("checks", "integer", false), ("checks_ok", "integer", false), ("uptime", "number", false),
+        ("stream_changes", "array:StationStatsStream", false), ("sources", "array:StationStatsSource", false),
+    ]},
+    SchemaInfo { name: "StationStatsClicks", properties: &[
+        ("time", "string", false), ("clicks", "integer", false),
+    ]},
+    SchemaInfo { name: "StationStatsStream", properties: &[
+        ("time", "string", false), ("codec", "string", false), ("bitrate", "integer", false), ("hls", "boolean", false),
+    ]},
+    SchemaInfo { name: "StationStatsSource", properties: &[
+        ("source", "string", false), ("checks", "integer", false), ("checks_ok", "integer", false), ("uptime", "number", false),
+        ("lastchecktime", "string", true), ("lastcheckok", "boolean", true),
+    ]},
+    SchemaInfo { name: "StationNowPlaying", properties: &[
+        ("stationuuid", "string", false), ("title", "string", true), ("time", "string", false), ("history", "array:StationNowPlayingTitle", false),
+    ]},
+    SchemaInfo { name: "StationNowPlayingTitle", properties: &[
+        ("title", "string", false), ("time", "string", false),
+    ]},
+    SchemaInfo { name: "ApiConfig", properties: &[
+        ("check_enabled", "boolean", false), ("prometheus_exporter_enabled", "boolean", false), ("pull_servers", "array", false),
+        ("tcp_timeout_seconds", "integer", false), ("broken_stations_never_working_timeout_seconds", "integer", false),
+        ("broken_stations_timeout_seconds", "integer", false), ("checks_timeout_seconds", "integer", false),
+        ("click_valid_timeout_seconds", "integer", false), ("clicks_timeout_seconds", "integer", false),
+        ("mirror_pull_interval_seconds", "integer", false), ("update_caches_interval_seconds", "integer", false),
+        ("server_name", "string", false), ("check_retries", "integer", false), ("check_batchsize", "integer", false),
+        ("check_pause_seconds", "integer", false), ("api_threads", "integer", false), ("cache_type", "string", false), ("cache_ttl", "integer", false), ("add_validate_stream", "boolean", false),
+        ("now_playing_enabled", "boolean", false), ("deep_check_enabled", "boolean", false),
+    ]},
+];
+
+/// Route of a request path that is split at '/', placeholders in curly braces match every segment
+pub fn get_route<S: AsRef<str>>(items: &[S]) -> Option<&'static RouteInfo> {
+    ROUTES.iter().find(|route| {
+        let segments: Vec<&str> = route.path.split('/').collect();
+        segments.len() == items.len() && segments.iter().zip(items.iter()).all(|(segment, item)| {
+            (segment.starts_with('{') && segment.ends_with('}')) || *segment == item.as_ref()
+        })
+    })
+}
+
+fn get_content_type(format: &str) -> &'static str {
+    match format {
+        "json" => "application/json",
+        "xml" => "text/xml",
+        "csv" => "text/csv",
+        "m3u" => "audio/mpegurl",
+        "pls" => "audio/x-scpls",
+        "xspf" => "application/xspf+xml",
+        "ttl" => "text/turtle",
+        "opml" => "text/x-opml",
+        "html" => "text/html",
+        _ => "text/plain",
+    }
+}
+
+fn get_parameter_schema(parameter: &ParameterInfo) -> Value {
+    let mut schema = match parameter.param_type {
+        ParameterType::String => json!({"type": "string"}),
+        ParameterType::Bool => json!({"type": "boolean"}),
+        ParameterType::Number => json!({"type": "integer", "minimum": 0}),
+        ParameterType::List => json!({"type": "array", "items": {"type": "string"}}),
+    };
+    if let Some(default) = parameter.default {
+        schema["default"] = match parameter.param_type {
+            ParameterType::Bool => json!(default == "true"),
+            ParameterType::Number => json!(default.parse::<u64>().unwrap_or(0)),
+            _ => json!(default),
+        };
+    }
+    schema
+}
+
+fn get_path_parameters(route: &RouteInfo) -> Vec<Value> {
+    route.path
+        .split('/')
+        .filter(|item| item.starts_with('{') && item.ends_with('}'))
+        .map(|item| {
+            let name = &item[1..item.len() - 1];
+            let schema = if name == "format" {
+                json!({"type": "string", "enum": route.formats})
+            } else if name == "rowcount" {
+                json!({"type": "integer", "minimum": 0})
+            } else {
+                json!({"type": "string"})
+            };
+            json!({"name": name, "in": "path", "required": true, "schema": schema})
+        })
+        .collect()
+}
+
+fn get_operation(route: &RouteInfo, method: &str) -> Value {
+    let mut parameters = get_path_parameters(route);
+    let parameter_infos: Vec<&ParameterInfo> = route.parameters
+        .iter()
+        .filter_map(|name| PARAMETERS.iter().find(|p| &p.name == name))
+        .collect();
+
+    let mut content = Map::new();
+    for format in route.formats {
+        let media_type = match (route.schema, *format) {
+            (Some(schema), "json") => {
+                let reference = json!({"$ref": format!("#/components/schemas/{}", schema)});
+                if route.is_list {
+                    json!({"schema": {"type": "array", "items": reference}})
+                } else {
+                    json!({"schema": reference})
+                }
+            }
+            _ => json!({}),
+        };
+        content.insert(get_content_type(format).to_string(), media_type);
+    }
+    let mut response = json!({"description": "OK"});
+    if content.len() > 0 {
+        response["content"] = Value::Object(content);
+    }
+
+    let error_response = json!({
+        "description": "Error with a stable code, see ErrorMessage",
+        "content": {
+            "application/json": {"schema": {"$ref": "#/components/schemas/ErrorMessage"}},
+            "text/xml": {},
+        },
+    });
+    let mut operation = json!({
+        "summary": route.summary,
+        "responses": {"200": response, "default": error_response},
+    });
+    if route.deprecated {
+        operation["deprecated"] = json!(true);
+    }
+
+    if method == "get" {
+        for parameter in parameter_infos {
+            parameters.push(json!({
+                "name": parameter.name,
+                "in": "query",
+                "required": false,
+                "description": parameter.description,
+                "schema": get_parameter_schema(parameter),
+            }));
+        }
+    } else if parameter_infos.len() > 0 {
+        let mut properties = Map::new();
+        for parameter in parameter_infos {
+            let mut schema = get_parameter_schema(parameter);
+            schema["description"] = json!(parameter.description);
+            properties.insert(parameter.name.to_string(), schema);
+        }
+        let body_schema = json!({"schema": {"type": "object", "properties": properties}});
+        operation["requestBody"] = json!({
+            "content": {
+                "application/x-www-form-urlencoded": body_schema,
+                "multipart/form-data": body_schema,
+                "application/json": body_schema,
+            }
+        });
+    }
+    if parameters.len() > 0 {
+        operation["parameters"] = Value::Array(parameters);
+    }
+    operation
+}
+
+fn get_schema(schema: &SchemaInfo) -> Value {
+    let mut properties = Map::new();
+    for (name, property_type, nullable) in schema.properties {
+        let mut property = if *property_type == "array" {
+            json!({"type": "array", "items": {"type": "string"}})
+        } else if property_type.starts_with("array:") {
+            json!({"type": "array", "items": {"$ref": format!("#/components/schemas/{}", &property_type["array:".len()..])}})
+        } else {
+            json!({"type": property_type})
+        };
+        if *nullable {
+            property["nullable"] = json!(true);
+        }
+        properties.insert(name.to_string(), property);
+    }
+    json!({"type": "object", "properties": properties})
+}
+
+/// Generate OpenAPI 3 document from the route and parameter tables
+pub fn get_document(server_url: &str) -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let mut item = json!({"get": get_operation(route, "get")});
+        if route.formats.len() > 0 {
+            item["post"] = get_operation(route, "post");
+        }
+        paths.insert(route.path.to_string(), item);
+    }
+
+    let mut schemas = Map::new();
+    for schema in SCHEMAS {
+        schemas.insert(schema.name.to_string(), get_schema(schema));
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "radio-browser.info API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{"url": server_url}],
+        "paths": paths,
+        "components": {"schemas": schemas},
+    })
+}
+
+pub fn render(server_url: &str) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&get_document(server_url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::*;
+    use crate::api::get_all_parameters;
+    use crate::api::parameters::RequestParameters;
+    use crate::api::ServerEntry;
+    use crate::db::models::ExtraInfo;
+    use crate::db::models::State;
+    use crate::db::models::StatsHistoryItem;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::collections::HashSet;
+
+    /// Parses the query of a fake request like handle_cached_connection and returns
+    /// the parameters as json without the url and the errors of the parameters
+    fn parse(query: &str) -> (Value, Result<(), String>, RequestParameters) {
+        let request = crate::api::rouille::Request::fake_http("GET", format!("/json/stations/search{}", query), vec![], vec![]);
+        let ppp = RequestParameters::new(&request);
+        let mut parsed = serde_json::to_value(get_all_parameters(&request, &ppp)).unwrap();
+        parsed.as_object_mut().unwrap().remove("u");
+        let result = ppp.validate(false).map_err(|err| err.to_string());
+        (parsed, result, ppp)
+    }
+
+    #[test]
+    fn parameters_match_request_parsing() {
+        let (_, _, ppp) = parse("");
+        let parsed: HashSet<String> = ppp.get_used();
+        let documented: HashSet<String> = PARAMETERS.iter().map(|p| p.name.to_string()).collect();
+        assert_eq!(parsed, documented);
+    }
+
+    #[test]
+    fn parameter_defaults_match_request_parsing() {
+        let (defaults, _, _) = parse("");
+        for parameter in PARAMETERS {
+            if let Some(default) = parameter.default {
+                let (parsed, result, _) = parse(&format!("?{}={}", parameter.name, default));
+                assert_eq!(result, Ok(()), "default of parameter '{}'", parameter.name);
+                assert_eq!(parsed, defaults, "default of parameter '{}'", parameter.name);
+            }
+        }
+    }
+
+    #[test]
+    fn parameter_types_match_request_parsing() {
+        for parameter in PARAMETERS {
+            let (parsed, result, _) = parse(&format!("?{}=a,b", parameter.name));
+            let values: Vec<&Value> = parsed.as_object().unwrap().values().collect();
+            match parameter.param_type {
+                ParameterType::Number | ParameterType::Bool => assert!(result.is_err(), "type of parameter '{}'", parameter.name),
+                ParameterType::List => assert!(values.contains(&&json!(["a", "b"])), "type of parameter '{}'", parameter.name),
+                ParameterType::String => assert!(values.contains(&&json!("a,b")), "type of parameter '{}'", parameter.name),
+            }
+        }
+    }
+
+    #[test]
+    fn routes_are_found() {
+        for route in ROUTES {
+            let path: Vec<&str> = route.path
+                .split('/')
+                .map(|segment| if segment == "{format}" { route.formats.first().cloned().unwrap_or("json") } else if segment.starts_with('{') { "x" } else { segment })
+                .collect();
+            let found = get_route(&path).expect(&format!("route '{}' not found", route.path));
+            assert_eq!(found.path, route.path);
+            for name in route.parameters {
+                assert!(PARAMETERS.iter().any(|p| &p.name == name), "unknown parameter '{}' in route '{}'", name, route.path);
+            }
+            if let Some(schema) = route.schema {
+                assert!(SCHEMAS.iter().any(|s| s.name == schema), "unknown schema '{}' in route '{}'", schema, route.path);
+            }
+        }
+        assert!(get_route(&["", "json", "unknown"]).is_none());
+        assert!(get_route(&["", "json", "stations", "byname"]).is_none());
+        assert!(get_route(&["", "json", "stations", "byname", "x", "y"]).is_none());
+    }
+
+    fn get_sample(property_type: &str) -> Value {
+        if property_type == "array" {
+            json!(["x"])
+        } else if property_type.starts_with("array:") {
+            json!([get_schema_sample(&property_type["array:".len()..])])
+        } else {
+            match property_type {
+                "string" => json!("x"),
+                "integer" => json!(1),
+                "number" => json!(0.5),
+                "boolean" => json!(true),
+                _ => panic!("unknown type '{}'", property_type),
+            }
+        }
+    }
+
+    /// Object with a value for every property of a schema
+    fn get_schema_sample(name: &str) -> Value {
+        let schema = SCHEMAS.iter().find(|s| s.name == name).expect(&format!("unknown schema '{}'", name));
+        let mut object = Map::new();
+        for (name, property_type, _) in schema.properties {
+            object.insert(name.to_string(), get_sample(property_type));
+        }
+        Value::Object(object)
+    }
+
+    /// The sample of the schema has to survive a round trip through the struct that is sent by the api,
+    /// missing, additional or wrongly typed properties change or break the round trip
+    fn check_schema<T: Serialize + DeserializeOwned>(name: &str) {
+        let schema = SCHEMAS.iter().find(|s| s.name == name).unwrap();
+        let sample = get_schema_sample(name);
+        let item: T = serde_json::from_value(sample.clone()).expect(&format!("schema '{}' does not match", name));
+        assert_eq!(serde_json::to_value(&item).unwrap(), sample, "schema '{}' does not match", name);
+        for (property, _, _) in schema.properties.iter().filter(|(_, _, nullable)| *nullable) {
+            let mut sample = sample.clone();
+            sample[property] = Value::Null;
+            assert!(serde_json::from_value::<T>(sample).is_ok(), "property '{}' of schema '{}' is not nullable", property, name);
+        }
+    }
+
+    #[test]
+    fn schemas_match_structs() {
+        let mut checked = HashSet::new();
+        let mut check = |name: &'static str, f: fn(&str)| {
+            f(name);
+            checked.insert(name);
+        };
+        check("Station", check_schema::<Station>);
+        check("StationCheck", check_schema::<StationCheck>);
+        check("StationClick", check_schema::<StationClick>);
+        check("StationHistory", check_schema::<StationHistoryCurrent>);
+        check("StationCachedInfo", check_schema::<StationCachedInfo>);
+        check("StationAddResult", check_schema::<StationAddResult>);
+        check("ResultMessage", check_schema::<ResultMessage>);
+        check("ErrorMessage", check_schema::<ErrorMessage>);
+        check("ExtraInfo", check_schema::<ExtraInfo>);
+        check("State", check_schema::<State>);
+        check("ServerEntry", check_schema::<ServerEntry>);
+        check("Status", check_schema::<Status>);
+        check("StatsHistory", check_schema::<StatsHistoryItem>);
+        check("StatsHistoryCountry", check_schema::<crate::db::models::StatsHistoryCountryItem>);
+        check("StationStats", check_schema::<StationStats>);
+        check("StationStatsClicks", check_schema::<StationStatsClicks>);
+        check("StationStatsStream", check_schema::<StationStatsStream>);
+        check("StationStatsSource", check_schema::<StationStatsSource>);
+        check("StationNowPlaying", check_schema::<StationNowPlaying>);
+        check("ApiConfig", check_schema::<ApiConfig>);
+        for schema in SCHEMAS {
+            // schemas of arrays are part of the round trip of the schema that contains them
+            let nested = SCHEMAS.iter().any(|parent| checked.contains(parent.name)
+                && parent.properties.iter().any(|(_, property_type, _)| property_type.trim_start_matches("array:") == schema.name));
+            assert!(checked.contains(schema.name) || nested, "schema '{}' is not checked", schema.name);
+        }
+    }
+
+    #[test]
+    fn document_is_valid_json() {
+        let document: Value = serde_json::from_str(&render("http://localhost:8080").unwrap()).unwrap();
+        assert_eq!(document["paths"].as_object().unwrap().len(), ROUTES.len());
+        let parameters = document["paths"]["/{format}/stations/search"]["get"]["parameters"].as_array().unwrap();
+        let bitrate_max = parameters.iter().find(|p| p["name"] == "bitrateMax").unwrap();
+        assert_eq!(bitrate_max["schema"]["default"], json!(1000000));
+    }
+}
@@ -1,23 +1,33 @@
 use crate::api::rouille::Request;
+use crate::api::api_error::ApiError;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io::Read;
 use url::form_urlencoded;
 //use self::serde_json::value::{Map};
 
 pub struct RequestParameters{
-    values: HashMap<String, String>
+    values: HashMap<String, String>,
+    /// names of all parameters that were read
+    used: RefCell<HashSet<String>>,
+    /// problems found while decoding or parsing parameters
+    errors: RefCell<Vec<String>>,
 }
 
 impl RequestParameters {
     pub fn new(req: &Request) -> Self {
         let mut values = HashMap::new();
-        RequestParameters::decode(req, &mut values);
+        let mut errors = vec![];
+        RequestParameters::decode(req, &mut values, &mut errors);
         RequestParameters{
-            values
+            values,
+            used: RefCell::new(HashSet::new()),
+            errors: RefCell::new(errors),
         }
     }
 
-    fn decode(req: &Request, map: &mut HashMap<String,String>) {
+    fn decode(req: &Request, map: &mut HashMap<String,String>, errors: &mut Vec<String>) {
         let content_type_raw: &str = req.header("Content-Type").unwrap_or("nothing");
         let content_type_arr: Vec<&str> = content_type_raw.split(";").collect();
         if content_type_arr.len() == 0{
@@ -34,13 +44,14 @@ impl RequestParameters {
                     RequestParameters::decode_url_encoded(req, map);
                 },
                 "application/json" => {
-                    RequestParameters::decode_json(req, map);
+                    RequestParameters::decode_json(req, map, errors);
                 },
                 "nothing" => {
                     // ignore body
                 },
                 _ =>{
-                    error!("unknown content type: {}", content_type);
+                    debug!("unknown content type: {}", content_type);
+                    errors.push(format!("unknown content type '{}'", content_type));
                 }
             }
         }
@@ -109,13 +120,19 @@ impl RequestParameters {
         };
     }
 
-    fn decode_json(req: &Request, map: &mut HashMap<String, String>) {
+    fn decode_json(req: &Request, map: &mut HashMap<String, String>, errors: &mut Vec<String>) {
         let data = req.data();
         if let Some(mut data) = data {
             let mut buf = Vec::new();
             match data.read_to_end(&mut buf) {
                 Ok(_) => {
-                    let v: HashMap<String, serde_json::Value> = serde_json::from_slice(&buf).unwrap();
+                    let v: HashMap<String, serde_json::Value> = match serde_json::from_slice(&buf) {
+                        Ok(v) => v,
+                        Err(err) => {
+                            errors.push(format!("unable to decode json body: {}", err));
+                            return;
+                        }
+                    };
                     for (key, value) in v {
                         trace!("application/json {} => {}", key, value);
                         if !map.contains_key(&key){
@@ -142,7 +159,7 @@ impl RequestParameters {
                                 map.insert(key, value);
                             }
                             else{
-                                error!("unsupported value type in json");
+                                errors.push(format!("unsupported value type in json for parameter '{}'", key));
                             }
                         }
                     }
@@ -154,8 +171,13 @@ impl RequestParameters {
         }
     }
 
+    fn get_value(&self, name: &str) -> Option<&String> {
+        self.used.borrow_mut().insert(name.to_string());
+        self.values.get(name)
+    }
+
     pub fn get_string(&self, name: &str) -> Option<String> {
-        let v = self.values.get(name);
+        let v = self.get_value(name);
         if let Some(v) = v {
             return Some(String::from(v));
         }
@@ -163,26 +185,113 @@ impl RequestParameters {
     }
 
     pub fn get_bool(&self, name: &str, default: bool) -> bool {
-        let v = self.values.get(name);
+        let v = self.get_value(name);
         if let Some(v) = v {
             let parsed = v.parse::<bool>();
             if let Ok(parsed) = parsed {
                 return parsed;
+            }else{
+                self.errors.borrow_mut().push(format!("parameter '{}' has to be true or false, got '{}'", name, v));
             }
         }
         default
     }
 
+    pub fn get_bool_option(&self, name: &str) -> Option<bool> {
+        let v = self.get_value(name);
+        if let Some(v) = v {
+            let parsed = v.parse::<bool>();
+            if let Ok(parsed) = parsed {
+                return Some(parsed);
+            }else{
+                self.errors.borrow_mut().push(format!("parameter '{}' has to be true or false, got '{}'", name, v));
+            }
+        }
+        None
+    }
+
     pub fn get_number(&self, name: &str, default: u32) -> u32 {
-        let v = self.values.get(name);
+        let v = self.get_value(name);
         if let Some(v) = v {
             let parsed = v.parse::<u32>();
             if let Ok(parsed) = parsed {
                 return parsed;
             }else{
-                error!("could not parse '{}'", v);
+                self.errors.borrow_mut().push(format!("parameter '{}' has to be a positive number, got '{}'", name, v));
             }
         }
         default
     }
-}
\ No newline at end of file
+
+    /// Add a problem found by the caller, e.g. a value that is not in the list of allowed values
+    pub fn add_error(&self, message: String) {
+        self.errors.borrow_mut().push(message);
+    }
+
+    /// Names of the parameters of the request that have never been read, sorted
+    pub fn get_unknown(&self) -> Vec<String> {
+        let used = self.used.borrow();
+        let mut unknown: Vec<String> = self.values.keys().filter(|key| !used.contains(*key)).cloned().collect();
+        unknown.sort();
+        unknown
+    }
+
+    /// Names of all parameters that have been read, no matter if they were in the request
+    #[cfg(test)]
+    pub fn get_used(&self) -> HashSet<String> {
+        self.used.borrow().clone()
+    }
+
+    /// Check for malformed values and for parameters that have never been read.
+    /// In lenient mode all problems are ignored (behaviour of older versions).
+    /// Has to be called after all parameters have been read.
+    pub fn validate(&self, lenient: bool) -> Result<(), ApiError> {
+        if lenient {
+            return Ok(());
+        }
+        let mut errors = self.errors.borrow().clone();
+        for key in self.get_unknown() {
+            errors.push(format!("unknown parameter '{}'", key));
+        }
+        if errors.len() > 0 {
+            Err(ApiError::IllegalParameter(errors.join(", ")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(query: &str) -> RequestParameters {
+        let request = Request::fake_http("GET", format!("/json/stations/search{}", query), vec![], vec![]);
+        RequestParameters::new(&request)
+    }
+
+    #[test]
+    fn validate_rejects_unknown_parameters() {
+        let ppp = parameters("?name=abc&unknownparam=1&other=2");
+        assert_eq!(ppp.get_string("name"), Some(String::from("abc")));
+        assert_eq!(ppp.get_unknown(), vec![String::from("other"), String::from("unknownparam")]);
+        let result = ppp.validate(false).map_err(|err| err.to_string());
+        assert_eq!(result, Err(ApiError::IllegalParameter(String::from("unknown parameter 'other', unknown parameter 'unknownparam'")).to_string()));
+    }
+
+    #[test]
+    fn validate_accepts_known_parameters() {
+        let ppp = parameters("?name=abc&limit=10");
+        assert_eq!(ppp.get_string("name"), Some(String::from("abc")));
+        assert_eq!(ppp.get_number("limit", 0), 10);
+        assert!(ppp.validate(false).is_ok());
+    }
+
+    #[test]
+    fn validate_ignores_problems_when_lenient() {
+        let ppp = parameters("?limit=abc&unknownparam=1");
+        assert_eq!(ppp.get_number("limit", 5), 5);
+        assert!(ppp.validate(false).is_err());
+        assert!(ppp.validate(true).is_ok());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::api::api_response::ApiResponse;
+use crate::check;
 use crate::db::DbConnection;
 use prometheus::{
     Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, Registry, TextEncoder,
@@ -24,9 +25,46 @@ pub struct RegistryLinks {
     pub country_count: IntGauge,
     pub tags_count: IntGauge,
     pub language_count: IntGauge,
+    pub checks: Option<CheckMetrics>,
 }
 
-pub fn create_registry(prefix: &str) -> Result<RegistryLinks, Box<dyn Error>> {
+/// Metrics of the stream checks, they are counted in the process that runs the checks,
+/// so they only exist in processes with the check role
+#[derive(Clone)]
+pub struct CheckMetrics {
+    checks_running: IntGauge,
+    /// mirror of the counter of the check worker, it is set instead of incremented
+    /// so that concurrent scrapes can not count timeouts twice
+    checks_timed_out: IntGauge,
+}
+
+impl CheckMetrics {
+    fn new(registry: &Registry) -> Result<Self, Box<dyn Error>> {
+        let checks_running = IntGauge::new(
+            "checks_running",
+            "Count of station checks that are running right now",
+        )?;
+        let checks_timed_out = IntGauge::new(
+            "checks_timed_out",
+            "Station checks that ran into the timeout since the start of the process",
+        )?;
+        registry.register(Box::new(checks_running.clone()))?;
+        registry.register(Box::new(checks_timed_out.clone()))?;
+        Ok(CheckMetrics {
+            checks_running,
+            checks_timed_out,
+        })
+    }
+
+    fn update(&self) -> Result<(), Box<dyn Error>> {
+        self.checks_running.set(check::get_checks_running().try_into()?);
+        self.checks_timed_out.set(check::get_checks_timed_out().try_into()?);
+        Ok(())
+    }
+}
+
+/// check_role: the checks run in this process and their metrics are exported
+pub fn create_registry(prefix: &str, check_role: bool) -> Result<RegistryLinks, Box<dyn Error>> {
     // Create a Counter.
     let timer = register_histogram_vec!("timer", "Timer for the api", &["method"])?;
     let api_calls = IntCounterVec::new(
@@ -72,6 +110,7 @@ pub fn create_registry(prefix: &str) -> Result<RegistryLinks, Box<dyn Error>> {
     registry.register(Box::new(country_count.clone()))?;
     registry.register(Box::new(tags_count.clone()))?;
     registry.register(Box::new(language_count.clone()))?;
+    let checks = if check_role { Some(CheckMetrics::new(&registry)?) } else { None };
 
     Ok(RegistryLinks {
         registry,
@@ -88,9 +127,30 @@ pub fn create_registry(prefix: &str) -> Result<RegistryLinks, Box<dyn Error>> {
         country_count,
         tags_count,
         language_count,
+        checks,
     })
 }
 
+/// Registry with only the metrics of the checks, for processes with the check role but without the api
+pub fn create_check_registry(prefix: &str) -> Result<(Registry, CheckMetrics), Box<dyn Error>> {
+    let registry = Registry::new_custom(Some(prefix.to_string()), None)?;
+    let checks = CheckMetrics::new(&registry)?;
+    Ok((registry, checks))
+}
+
+pub fn render_check_metrics(registry: &Registry, checks: &CheckMetrics) -> Result<String, Box<dyn Error>> {
+    checks.update()?;
+    encode(registry)
+}
+
+fn encode(registry: &Registry) -> Result<String, Box<dyn Error>> {
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+    let metric_families = registry.gather();
+    encoder.encode(&metric_families, &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
 pub fn render<A>(
     connection_new: &A,
     broken_stations_never_working_timeout: u64,
@@ -124,13 +184,9 @@ where
     registry.country_count.set(country_count.try_into()?);
     registry.tags_count.set(tags_count.try_into()?);
     registry.language_count.set(language_count.try_into()?);
+    if let Some(checks) = &registry.checks {
+        checks.update()?;
+    }
 
-    // Gather the metrics.
-    let mut buffer = vec![];
-    let encoder = TextEncoder::new();
-    let metric_families = registry.registry.gather();
-    encoder.encode(&metric_families, &mut buffer)?;
-
-    // Output to the standard output.
-    Ok(ApiResponse::Text(String::from_utf8(buffer)?))
+    Ok(ApiResponse::Text(encode(&registry.registry)?))
 }
@@ -0,0 +1,408 @@
+//! Verify the audio of a stream by its frame headers instead of trusting the http headers,
+//! the codec, sample rate and channels are read from the frames and the bitrate is
+//! calculated from the size and the duration of the frames.
+//! Supported are MP3 (and MPEG layer 1/2), AAC in ADTS frames, Ogg Vorbis and Ogg Opus,
+//! the frames may also be packed in a MPEG transport stream.
+
+/// Frames in a row with the same format that are needed to accept the data as audio
+const MIN_FRAMES: usize = 3;
+
+const TS_PACKET_SIZE: usize = 188;
+
+const SAMPLERATES_MPEG: [u32; 3] = [44100, 48000, 32000];
+const SAMPLERATES_ADTS: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+const BITRATES_MPEG1_LAYER1: [u32; 15] = [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448];
+const BITRATES_MPEG1_LAYER2: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384];
+const BITRATES_MPEG1_LAYER3: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const BITRATES_MPEG2_LAYER1: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+const BITRATES_MPEG2_LAYER23: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// Audio format as found in the stream data
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioInfo {
+    pub codec: String,
+    pub samplerate: u32,
+    /// 0 if the channels are not in the frame headers
+    pub channels: u8,
+    /// Average bitrate of the analyzed data in kbit/s, 0 if it could not be calculated
+    pub bitrate: u32,
+}
+
+struct Frame {
+    codec: &'static str,
+    samplerate: u32,
+    channels: u8,
+    length: usize,
+    samples: u64,
+}
+
+fn decode_mpeg_header(header: &[u8]) -> Option<Frame> {
+    if header.len() < 4 || header[0] != 0xff || header[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    // version 0 is MPEG 2.5, 2 is MPEG 2 and 3 is MPEG 1, layer 1 is layer III and 3 is layer I
+    let version = (header[1] >> 3) & 0x03;
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = (header[2] >> 4) as usize;
+    let samplerate_index = ((header[2] >> 2) & 0x03) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || samplerate_index == 3 {
+        return None;
+    }
+    let mpeg1 = version == 3;
+    let bitrates = match (mpeg1, layer) {
+        (true, 3) => &BITRATES_MPEG1_LAYER1,
+        (true, 2) => &BITRATES_MPEG1_LAYER2,
+        (true, _) => &BITRATES_MPEG1_LAYER3,
+        (false, 3) => &BITRATES_MPEG2_LAYER1,
+        (false, _) => &BITRATES_MPEG2_LAYER23,
+    };
+    let bitrate = bitrates[bitrate_index] as usize * 1000;
+    let samplerate = SAMPLERATES_MPEG[samplerate_index] >> (3 - version.max(1));
+    let padding = ((header[2] >> 1) & 0x01) as usize;
+    let (codec, length, samples) = match layer {
+        3 => ("MP1", (12 * bitrate / samplerate as usize + padding) * 4, 384),
+        2 => ("MP2", 144 * bitrate / samplerate as usize + padding, 1152),
+        _ if mpeg1 => ("MP3", 144 * bitrate / samplerate as usize + padding, 1152),
+        _ => ("MP3", 72 * bitrate / samplerate as usize + padding, 576),
+    };
+    Some(Frame {
+        codec,
+        samplerate,
+        channels: if header[3] >> 6 == 3 { 1 } else { 2 },
+        length,
+        samples,
+    })
+}
+
+fn decode_adts_header(header: &[u8]) -> Option<Frame> {
+    // 12 bit sync word and layer 0
+    if header.len() < 7 || header[0] != 0xff || header[1] & 0xf6 != 0xf0 {
+        return None;
+    }
+    let samplerate = *SAMPLERATES_ADTS.get(((header[2] >> 2) & 0x0f) as usize)?;
+    let channels = match ((header[2] & 0x01) << 2) | (header[3] >> 6) {
+        7 => 8,
+        channel_config => channel_config,
+    };
+    let length = ((header[3] & 0x03) as usize) << 11 | (header[4] as usize) << 3 | (header[5] >> 5) as usize;
+    if length < 7 {
+        return None;
+    }
+    Some(Frame {
+        codec: "AAC",
+        samplerate,
+        channels,
+        length,
+        samples: 1024 * ((header[6] & 0x03) as u64 + 1),
+    })
+}
+
+fn decode_frame_header(header: &[u8]) -> Option<Frame> {
+    decode_adts_header(header).or_else(|| decode_mpeg_header(header))
+}
+
+fn get_bitrate(bytes: u64, samples: u64, samplerate: u32) -> u32 {
+    if samples == 0 {
+        return 0;
+    }
+    ((bytes * 8 * samplerate as u64 + samples * 500) / (samples * 1000)) as u32
+}
+
+/// Follow the frames that start at the beginning of data, as long as they have the same format
+fn follow_frames(data: &[u8]) -> Option<AudioInfo> {
+    let first = decode_frame_header(data)?;
+    let mut pos = 0;
+    let mut frames = 0;
+    let mut samples = 0;
+    while let Some(frame) = decode_frame_header(&data[pos..]) {
+        if frame.codec != first.codec || frame.samplerate != first.samplerate || frame.channels != first.channels || pos + frame.length > data.len() {
+            break;
+        }
+        frames += 1;
+        samples += frame.samples;
+        pos += frame.length;
+    }
+    if frames < MIN_FRAMES {
+        return None;
+    }
+    Some(AudioInfo {
+        codec: String::from(first.codec),
+        samplerate: first.samplerate,
+        channels: first.channels,
+        bitrate: get_bitrate(pos as u64, samples, first.samplerate),
+    })
+}
+
+/// Find the first position where MIN_FRAMES frames follow each other,
+/// a single sync word is often only a random byte pattern
+fn analyze_frames(data: &[u8]) -> Option<AudioInfo> {
+    (0..data.len())
+        .filter(|&start| data[start] == 0xff)
+        .find_map(|start| follow_frames(&data[start..]))
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Codec, sample rate, channels and nominal bitrate of an Ogg identification header
+fn decode_ogg_codec(packet: &[u8]) -> Option<(&'static str, u32, u8, u32)> {
+    if packet.starts_with(b"\x01vorbis") {
+        Some(("VORBIS", read_u32_le(packet, 12)?, *packet.get(11)?, (read_u32_le(packet, 20)? as i32).max(0) as u32 / 1000))
+    } else if packet.starts_with(b"OpusHead") {
+        // opus always uses 48 kHz, the granule positions count in it
+        Some(("OPUS", 48000, *packet.get(9)?, 0))
+    } else {
+        None
+    }
+}
+
+/// Read the codec from the first page of an Ogg stream and calculate the bitrate
+/// from the size of the audio pages and their granule positions
+fn analyze_ogg(data: &[u8]) -> Option<AudioInfo> {
+    let mut pos = 0;
+    let mut codec = None;
+    let mut serial = 0;
+    let mut first_granule = None;
+    let mut last_granule = 0;
+    let mut audio_bytes = 0;
+    while data.get(pos..pos + 4) == Some(&b"OggS"[..]) && pos + 27 <= data.len() {
+        let segments = data[pos + 26] as usize;
+        let header_length = 27 + segments;
+        let body_length: usize = match data.get(pos + 27..pos + header_length) {
+            Some(segment_table) => segment_table.iter().map(|&size| size as usize).sum(),
+            None => break,
+        };
+        if pos + header_length + body_length > data.len() {
+            break;
+        }
+        let body = &data[pos + header_length..pos + header_length + body_length];
+        let granule = u64::from_le_bytes([
+            data[pos + 6], data[pos + 7], data[pos + 8], data[pos + 9],
+            data[pos + 10], data[pos + 11], data[pos + 12], data[pos + 13],
+        ]);
+        let page_serial = read_u32_le(data, pos + 14)?;
+        match codec {
+            None => {
+                codec = Some(decode_ogg_codec(body)?);
+                serial = page_serial;
+            }
+            Some(_) if page_serial == serial && granule > 0 && granule != u64::MAX => {
+                // the granule position is the sample count at the end of the page,
+                // so the bytes of the first audio page do not count
+                if first_granule.is_none() {
+                    first_granule = Some(granule);
+                } else {
+                    audio_bytes += (header_length + body_length) as u64;
+                    last_granule = granule;
+                }
+            }
+            Some(_) => {}
+        }
+        pos += header_length + body_length;
+    }
+
+    let (codec, samplerate, channels, nominal_bitrate) = codec?;
+    let samples = first_granule.map(|first_granule| last_granule.saturating_sub(first_granule)).unwrap_or(0);
+    let bitrate = match get_bitrate(audio_bytes, samples, samplerate) {
+        0 => nominal_bitrate,
+        bitrate => bitrate,
+    };
+    Some(AudioInfo {
+        codec: String::from(codec),
+        samplerate,
+        channels,
+        bitrate,
+    })
+}
+
+/// Audio elementary stream of a MPEG transport stream, as used by many hls segments
+fn ts_audio(data: &[u8]) -> Option<Vec<u8>> {
+    let start = (0..data.len()).find(|&pos| (0..3).all(|packet| data.get(pos + packet * TS_PACKET_SIZE) == Some(&0x47)))?;
+    let mut audio_pid = None;
+    let mut audio = vec![];
+    for packet in data[start..].chunks_exact(TS_PACKET_SIZE) {
+        if packet[0] != 0x47 {
+            break;
+        }
+        let pid = ((packet[1] & 0x1f) as u16) << 8 | packet[2] as u16;
+        let mut payload = match (packet[3] >> 4) & 0x03 {
+            1 => &packet[4..],
+            3 => match packet.get(5 + packet[4] as usize..) {
+                Some(payload) => payload,
+                None => continue,
+            },
+            _ => continue,
+        };
+        if packet[1] & 0x40 != 0 {
+            // a new PES packet, the stream ids 0xc0 to 0xdf are MPEG audio and AAC
+            if payload.len() < 9 || payload[0..3] != [0, 0, 1] {
+                continue;
+            }
+            if audio_pid.is_none() && (0xc0..=0xdf).contains(&payload[3]) {
+                audio_pid = Some(pid);
+            }
+            if audio_pid != Some(pid) {
+                continue;
+            }
+            payload = match payload.get(9 + payload[8] as usize..) {
+                Some(payload) => payload,
+                None => continue,
+            };
+        } else if audio_pid != Some(pid) {
+            continue;
+        }
+        audio.extend_from_slice(payload);
+    }
+    if audio.is_empty() {
+        None
+    } else {
+        Some(audio)
+    }
+}
+
+/// Analyze the first bytes of a stream, None if no known audio format was found
+pub fn analyze(data: &[u8]) -> Option<AudioInfo> {
+    // a few bytes of 0x47 in the right distance look like a transport stream,
+    // so the other formats are still tried if no audio was found in it
+    if let Some(info) = ts_audio(data).and_then(|audio| analyze_frames(&audio)) {
+        return Some(info);
+    }
+    if let Some(pos) = data.windows(4).position(|window| window == b"OggS") {
+        if let Some(info) = analyze_ogg(&data[pos..]) {
+            return Some(info);
+        }
+    }
+    analyze_frames(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// count frames of length bytes, every frame starts with header and is filled with zeros
+    fn frames(header: &[u8], length: usize, count: usize) -> Vec<u8> {
+        let mut data = vec![];
+        for _ in 0..count {
+            let mut frame = header.to_vec();
+            frame.resize(length, 0);
+            data.extend(frame);
+        }
+        data
+    }
+
+    fn ts_packet(pid: u16, start: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x47, (if start { 0x40 } else { 0 }) | (pid >> 8) as u8, pid as u8, 0x10];
+        packet.extend_from_slice(payload);
+        packet.resize(TS_PACKET_SIZE, 0);
+        packet
+    }
+
+    /// Pack audio into a PES packet of stream id 0xc0 in transport stream packets
+    fn ts_packets(audio: &[u8]) -> Vec<u8> {
+        let mut pes = vec![0, 0, 1, 0xc0, 0, 0, 0x80, 0, 0];
+        pes.extend_from_slice(audio);
+        let mut data = vec![];
+        for (i, chunk) in pes.chunks(TS_PACKET_SIZE - 4).enumerate() {
+            data.extend(ts_packet(0x100, i == 0, chunk));
+        }
+        data
+    }
+
+    fn ogg_page(granule: u64, body: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.extend_from_slice(&[0, 0]);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1234u32.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        page.push(1);
+        page.push(body.len() as u8);
+        page.extend_from_slice(body);
+        page
+    }
+
+    #[test]
+    fn mp3_mpeg1_stereo() {
+        // MPEG 1 layer III, 128 kbit/s, 44100 Hz, joint stereo: 417 bytes per frame
+        let info = analyze(&frames(&[0xff, 0xfb, 0x90, 0x44], 417, 5)).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("MP3"), samplerate: 44100, channels: 2, bitrate: 128 });
+    }
+
+    #[test]
+    fn mp3_mpeg2_mono() {
+        // MPEG 2 layer III, 64 kbit/s, 22050 Hz, mono: 208 bytes per frame
+        let info = analyze(&frames(&[0xff, 0xf3, 0x80, 0xc4], 208, 5)).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("MP3"), samplerate: 22050, channels: 1, bitrate: 64 });
+    }
+
+    #[test]
+    fn mp2() {
+        // MPEG 1 layer II, 192 kbit/s, 48000 Hz, stereo: 576 bytes per frame
+        let info = analyze(&frames(&[0xff, 0xfd, 0xa4, 0x04], 576, 5)).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("MP2"), samplerate: 48000, channels: 2, bitrate: 192 });
+    }
+
+    #[test]
+    fn aac_adts() {
+        // AAC LC, 44100 Hz, 2 channels, frame length 371 bytes
+        let info = analyze(&frames(&[0xff, 0xf1, 0x50, 0x80, 0x2e, 0x7f, 0xfc], 371, 5)).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("AAC"), samplerate: 44100, channels: 2, bitrate: 128 });
+    }
+
+    #[test]
+    fn frames_after_garbage() {
+        let mut data = vec![0xff, 0xfb, 0x12, 0x00, 0x47, 0x11];
+        data.extend(frames(&[0xff, 0xfb, 0x90, 0x44], 417, 5));
+        assert_eq!(analyze(&data).unwrap().codec, "MP3");
+    }
+
+    #[test]
+    fn too_few_frames() {
+        assert_eq!(analyze(&frames(&[0xff, 0xfb, 0x90, 0x44], 417, MIN_FRAMES - 1)), None);
+        assert_eq!(analyze(&[0u8; 2000]), None);
+    }
+
+    #[test]
+    fn aac_in_transport_stream() {
+        let data = ts_packets(&frames(&[0xff, 0xf1, 0x50, 0x80, 0x2e, 0x7f, 0xfc], 371, 5));
+        let info = analyze(&data).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("AAC"), samplerate: 44100, channels: 2, bitrate: 128 });
+    }
+
+    #[test]
+    fn transport_stream_without_audio_falls_through() {
+        // packets that look like a transport stream, but carry no frames, followed by mp3 frames
+        let mut data = ts_packets(&[0; 400]);
+        assert!(ts_audio(&data).is_some());
+        data.extend(frames(&[0xff, 0xfb, 0x90, 0x44], 417, 5));
+        let info = analyze(&data).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("MP3"), samplerate: 44100, channels: 2, bitrate: 128 });
+    }
+
+    #[test]
+    fn ogg_opus() {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2, 0, 0, 0x80, 0xbb, 0, 0, 0, 0, 0]);
+        let mut data = ogg_page(0, &head);
+        // 20 ms per page, 200 bytes of audio per page are 80 kbit/s plus the page headers
+        for page in 1..=5 {
+            data.extend(ogg_page(960 * page, &[0; 200]));
+        }
+        let info = analyze(&data).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("OPUS"), samplerate: 48000, channels: 2, bitrate: 91 });
+    }
+
+    #[test]
+    fn ogg_vorbis_nominal_bitrate() {
+        let mut head = b"\x01vorbis".to_vec();
+        head.extend_from_slice(&[0, 0, 0, 0, 1]);
+        head.extend_from_slice(&44100u32.to_le_bytes());
+        head.extend_from_slice(&0u32.to_le_bytes());
+        head.extend_from_slice(&96000u32.to_le_bytes());
+        head.extend_from_slice(&[0; 6]);
+        let info = analyze(&ogg_page(0, &head)).unwrap();
+        assert_eq!(info, AudioInfo { codec: String::from("VORBIS"), samplerate: 44100, channels: 1, bitrate: 96 });
+    }
+}
@@ -2,20 +2,21 @@ use crate::db::models;
 use crate::db::models::StationItem;
 use crate::db::models::StationCheckItemNew;
 
-use crate::thread;
-use threadpool::ThreadPool;
-
-use av_stream_info_rust;
 use crate::check::favicon;
+use crate::check::failure;
+use crate::check::probe;
+
+use av_stream_info_rust::StreamCheckResult;
 
 use std;
-use std::sync::mpsc::channel;
-use std::sync::mpsc::TryRecvError;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
 
+use crate::config::CheckPriority;
 use crate::db::DbConnection;
-use crate::db::connect;
 
 use colored::*;
 
@@ -82,177 +83,323 @@ fn check_for_change(
 }
 
 fn update_station(
-    conn: &Box<dyn DbConnection>,
+    conn: &dyn DbConnection,
     old: &models::StationItem,
     new_item: StationCheckItemNew,
     new_favicon: &str,
+    title_history: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // output debug
-    let (changed, change_str) = check_for_change(&old, &new_item, new_favicon);
-    if changed {
-        debug!("{}", change_str.red());
-    } else {
-        debug!("{}", change_str.dimmed());
+    update_stations(conn, vec![StationOldNew { old: old.clone(), new: new_item }], &[new_favicon.to_string()], title_history)
+}
+
+/// Store the now playing titles of checks that captured them,
+/// the last title_history titles of every station are kept
+pub fn insert_titles(conn: &dyn DbConnection, list: &[StationCheckItemNew], title_history: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let titles: Vec<(String, String)> = list
+        .iter()
+        .filter_map(|item| item.title.as_ref().map(|title| (item.station_uuid.clone(), title.clone())))
+        .collect();
+    if titles.len() > 0 {
+        conn.insert_station_titles(&titles, title_history)?;
     }
+    Ok(())
+}
 
-    // do real insert
-    let list_new = vec!(new_item);
-    conn.insert_checks(&list_new)?;
-    conn.update_station_with_check_data(&list_new, true)?;
+/// Store the checks and update the stations with them, favicons are the
+/// checked favicons of the stations in the same order
+fn update_stations(
+    conn: &dyn DbConnection,
+    list: Vec<StationOldNew>,
+    favicons: &[String],
+    title_history: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut list_new = vec![];
+    for (oldnew, new_favicon) in list.into_iter().zip(favicons.iter()) {
+        // output debug
+        let (changed, change_str) = check_for_change(&oldnew.old, &oldnew.new, new_favicon);
+        if changed {
+            debug!("{}", change_str.red());
+        } else {
+            debug!("{}", change_str.dimmed());
+        }
+        list_new.push(oldnew.new);
+    }
+
+    // do real insert, in chunks to keep the queries small
+    for chunk in list_new.chunks(UPDATE_CHUNK_SIZE) {
+        let chunk = chunk.to_vec();
+        conn.insert_checks(&chunk)?;
+        conn.update_station_with_check_data(&chunk, true)?;
+        insert_titles(conn, &chunk, title_history)?;
+    }
     Ok(())
 }
 
-fn dbcheck_internal(
-    pool: &ThreadPool,
-    stations: Vec<StationItem>,
+/// Checks that are running right now
+static CHECKS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+/// Checks that did not finish within their timeout since the start of the process
+static CHECKS_TIMED_OUT: AtomicUsize = AtomicUsize::new(0);
+/// Maximum count of checks that are written to the database in one query
+const UPDATE_CHUNK_SIZE: usize = 500;
+
+pub fn get_checks_running() -> usize {
+    CHECKS_RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn get_checks_timed_out() -> usize {
+    CHECKS_TIMED_OUT.load(Ordering::SeqCst)
+}
+
+fn new_item_broken(stationuuid: &str, source: &str, failure_reason: &str, failure_message: String) -> StationCheckItemNew {
+    StationCheckItemNew {
+        checkuuid: None,
+        station_uuid: stationuuid.to_string(),
+        source: source.to_string(),
+        codec: "".to_string(),
+        bitrate: 0,
+        hls: false,
+        check_ok: false,
+        url: "".to_string(),
+        timestamp: None,
+
+        metainfo_overrides_database: false,
+        public: None,
+        name: None,
+        description: None,
+        tags: None,
+        countrycode: None,
+        homepage: None,
+        favicon: None,
+        loadbalancer: None,
+
+        failure_reason: Some(failure_reason.to_string()),
+        failure_message: Some(failure_message),
+        title: None,
+        audio_codec: None,
+        audio_bitrate: None,
+        audio_samplerate: None,
+        audio_channels: None,
+    }
+}
+
+/// Convert the results of a stream check to a check item, the first public stream is used
+fn get_check_item(mut items: Vec<StreamCheckResult>, stationuuid: &str, source: &str) -> StationCheckItemNew {
+    let mut first_error: Option<String> = None;
+    for item in items.drain(..) {
+        match item {
+            Ok(item) => {
+                let public = item.Public.unwrap_or(true);
+                if !public && item.OverrideIndexMetaData {
+                    // ignore non public streams
+                    debug!("Ignore private stream: {} - {}", stationuuid, item.Url);
+                }else{
+                    let mut codec = item.CodecAudio.clone();
+                    if let Some(ref video) = item.CodecVideo {
+                        codec.push_str(",");
+                        codec.push_str(&video);
+                    }
+                    return StationCheckItemNew {
+                        checkuuid: None,
+                        station_uuid: stationuuid.to_string(),
+                        source: source.to_string(),
+                        codec: codec,
+                        bitrate: item.Bitrate as u32,
+                        hls: item.Hls,
+                        check_ok: true,
+                        url: item.Url.clone(),
+                        timestamp: None,
+
+                        metainfo_overrides_database: item.OverrideIndexMetaData,
+                        public: item.Public,
+                        name: item.Name,
+                        description: item.Description,
+                        tags: item.Genre,
+                        countrycode: item.CountryCode,
+                        homepage: item.Homepage,
+                        favicon: item.LogoUrl,
+                        loadbalancer: item.LoadBalancerUrl,
+
+                        failure_reason: None,
+                        failure_message: None,
+                        title: None,
+                        audio_codec: None,
+                        audio_bitrate: None,
+                        audio_samplerate: None,
+                        audio_channels: None,
+                    };
+                }
+            }
+            Err(err) => {
+                if first_error.is_none() {
+                    first_error = Some(err.to_string());
+                }
+            }
+        }
+    }
+    match first_error {
+        Some(message) => new_item_broken(stationuuid, source, failure::get_failure_reason(&message), message),
+        None => new_item_broken(stationuuid, source, failure::FAILURE_NO_RESULT, String::from("no stream found")),
+    }
+}
+
+/// Check a stream url and give up after a timeout that depends on the retries,
+/// failed checks return a broken check item, with capture_title working checks contain the now playing title
+/// and with deep_check the audio format that was found in the frames of the stream
+async fn check_stream_async(url: &str, stationuuid: &str, source: &str, timeout: u64, max_depth: u8, retries: u8, capture_title: bool, deep_check: bool) -> StationCheckItemNew {
+    let max_timeout = (retries.max(1) as u64) * timeout * 2;
+    CHECKS_RUNNING.fetch_add(1, Ordering::SeqCst);
+    let result = tokio::time::timeout(Duration::from_secs(max_timeout), probe::check(url, timeout, max_depth, retries, capture_title, deep_check)).await;
+    CHECKS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    match result {
+        Ok((items, details)) => {
+            let mut item = get_check_item(items, stationuuid, source);
+            if capture_title && item.check_ok {
+                item.title = Some(details.title.unwrap_or_default());
+            }
+            if let Some(audio) = details.audio.filter(|_| item.check_ok) {
+                item.audio_codec = Some(audio.codec);
+                item.audio_bitrate = Some(audio.bitrate);
+                item.audio_samplerate = Some(audio.samplerate);
+                item.audio_channels = Some(audio.channels);
+            }
+            item
+        }
+        Err(_) => {
+            warn!("Check did not finish in {} seconds: {} - {}", max_timeout, stationuuid, url);
+            CHECKS_TIMED_OUT.fetch_add(1, Ordering::SeqCst);
+            new_item_broken(stationuuid, source, failure::FAILURE_TIMEOUT, format!("check did not finish in {} seconds", max_timeout))
+        }
+    }
+}
+
+/// Check a stream url outside of the check worker, failed checks return a broken check item
+pub fn check_stream(url: &str, stationuuid: &str, source: &str, timeout: u64, max_depth: u8, retries: u8, capture_title: bool, deep_check: bool) -> StationCheckItemNew {
+    let runtime = tokio::runtime::Builder::new().basic_scheduler().enable_all().build();
+    match runtime {
+        Ok(mut runtime) => runtime.block_on(check_stream_async(url, stationuuid, source, timeout, max_depth, retries, capture_title, deep_check)),
+        Err(err) => {
+            error!("Unable to create runtime for check: {}", err);
+            new_item_broken(stationuuid, source, failure::FAILURE_UNKNOWN, err.to_string())
+        }
+    }
+}
+
+/// Check the urls of (stationuuid, url) pairs with at most concurrency checks at the same time,
+/// a check only allocates memory when it is started, the results are in the same order
+pub async fn check_urls(
+    list: Vec<(String, String)>,
     source: &str,
+    concurrency: usize,
     timeout: u64,
     max_depth: u8,
     retries: u8,
-    result_sender: Sender<StationOldNew>,
-) -> u32 {
-    let mut checked_count = 0;
-    for station in stations {
-        checked_count = checked_count + 1;
+    capture_title: bool,
+    deep_check: bool,
+) -> Vec<StationCheckItemNew> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut handles = Vec::with_capacity(list.len());
+    for (stationuuid, url) in list {
+        let permit = semaphore.clone().acquire_owned().await;
         let source = String::from(source);
-        let result_sender = result_sender.clone();
-        pool.execute(move || {
-            {
-                let (_, receiver): (Sender<i32>, Receiver<i32>) = channel();
-                let station_name = station.name.clone();
-                let max_timeout = (retries as u64) * timeout * 2;
-                thread::spawn(move || {
-                    for _ in 0..max_timeout {
-                        thread::sleep(Duration::from_secs(1));
-                        let o = receiver.try_recv();
-                        match o {
-                            Ok(_) => {
-                                return;
-                            }
-                            Err(value) => match value {
-                                TryRecvError::Empty => {}
-                                TryRecvError::Disconnected => {
-                                    return;
-                                }
-                            },
-                        }
-                    }
-                    debug!("Still not finished: {}", station_name);
-                    std::process::exit(0x0100);
-                });
+        let task_stationuuid = stationuuid.clone();
+        handles.push((stationuuid, tokio::spawn(async move {
+            let new_item = check_stream_async(&url, &task_stationuuid, &source, timeout, max_depth, retries, capture_title, deep_check).await;
+            drop(permit);
+            new_item
+        })));
+    }
+
+    let mut items = Vec::with_capacity(handles.len());
+    for (stationuuid, handle) in handles {
+        match handle.await {
+            Ok(new_item) => items.push(new_item),
+            Err(err) => {
+                error!("Check task failed: {}", err);
+                items.push(new_item_broken(&stationuuid, source, failure::FAILURE_UNKNOWN, err.to_string()));
             }
+        }
+    }
+    items
+}
 
-            let mut items = av_stream_info_rust::check(&station.url, timeout as u32, max_depth, retries);
-            for item in items.drain(..) {
-                match item {
-                    Ok(item) => {
-                        let public = item.Public.unwrap_or(true);
-                        if !public && item.OverrideIndexMetaData {
-                            // ignore non public streams
-                            debug!("Ignore private stream: {} - {}", station.stationuuid, item.Url);
-                        }else{
-                            let mut codec = item.CodecAudio.clone();
-                            if let Some(ref video) = item.CodecVideo {
-                                codec.push_str(",");
-                                codec.push_str(&video);
-                            }
-                            let new_item_ok = StationCheckItemNew {
-                                checkuuid: None,
-                                station_uuid: station.stationuuid.clone(),
-                                source: source.clone(),
-                                codec: codec,
-                                bitrate: item.Bitrate as u32,
-                                hls: item.Hls,
-                                check_ok: true,
-                                url: item.Url.clone(),
-                                timestamp: None,
+/// Check a single station right now and store the result, used for on demand checks of the api
+pub fn dbcheck_station(
+    conn: &dyn DbConnection,
+    station: &StationItem,
+    source: &str,
+    useragent: &str,
+    timeout: u64,
+    max_depth: u8,
+    retries: u8,
+    favicon_checks: bool,
+    capture_title: bool,
+    deep_check: bool,
+    title_history: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let new_item = check_stream(&station.url, &station.stationuuid, source, timeout, max_depth, retries, capture_title, deep_check);
+    if favicon_checks {
+        let new_favicon = check_favicon(station, useragent, timeout);
+        update_station(conn, station, new_item, &new_favicon, title_history)
+    } else {
+        update_station(conn, station, new_item, &station.favicon, title_history)
+    }
+}
 
-                                metainfo_overrides_database: item.OverrideIndexMetaData,
-                                public: item.Public,
-                                name: item.Name,
-                                description: item.Description,
-                                tags: item.Genre,
-                                countrycode: item.CountryCode,
-                                homepage: item.Homepage,
-                                favicon: item.LogoUrl,
-                                loadbalancer: item.LoadBalancerUrl,
-                            };
-                            let send_result = result_sender.send(StationOldNew {
-                                old: station,
-                                new: new_item_ok,
-                            });
-                            if let Err(send_result) = send_result {
-                                error!("Unable to send positive check result: {}", send_result);
-                            }
-                            return;
-                        }
-                    }
-                    Err(_) => {}
-                }
-            }
-            let new_item_broken: StationCheckItemNew = StationCheckItemNew {
-                checkuuid: None,
-                station_uuid: station.stationuuid.clone(),
-                source: source.clone(),
-                codec: "".to_string(),
-                bitrate: 0,
-                hls: false,
-                check_ok: false,
-                url: "".to_string(),
-                timestamp: None,
-                
-                metainfo_overrides_database: false,
-                public: None,
-                name: None,
-                description: None,
-                tags: None,
-                countrycode: None,
-                homepage: None,
-                favicon: None,
-                loadbalancer: None,
-            };
-            let send_result = result_sender.send(StationOldNew {
-                old: station,
-                new: new_item_broken,
-            });
-            if let Err(send_result) = send_result {
-                error!("Unable to send negative check result: {}", send_result);
-            }
-        });
+/// Search a new favicon of the station, errors only keep the old favicon
+/// so that they never drop the stream checks
+fn check_favicon(station: &StationItem, useragent: &str, timeout: u64) -> String {
+    match favicon::check(&station.homepage, &station.favicon, useragent, timeout as u32) {
+        Ok(favicon) => favicon,
+        Err(err) => {
+            warn!("Favicon check failed: {} - {}: {}", station.stationuuid, station.homepage, err);
+            station.favicon.clone()
+        }
     }
-    checked_count
 }
 
+/// Check the next batch of stations on the runtime of the check worker,
+/// the connection and the runtime are reused for all batches
 pub fn dbcheck(
-    connection_str: String,
+    conn: &mut Box<dyn DbConnection>,
+    runtime: &mut Runtime,
     source: &str,
     concurrency: usize,
     stations_count: u32,
+    check_priority: &CheckPriority,
     useragent: &str,
     timeout: u64,
     max_depth: u8,
     retries: u8,
     favicon_checks: bool,
+    capture_title: bool,
+    deep_check: bool,
+    title_history: u32,
 ) -> Result<u32, Box<dyn std::error::Error>> {
-    let mut conn = connect(connection_str)?;
-    let stations = conn.get_stations_to_check(24, stations_count)?;
-    let useragent = String::from(useragent);
+    let stations = conn.get_stations_to_check(
+        check_priority.interval.as_secs(),
+        check_priority.interval_popular.as_secs(),
+        check_priority.popular_clickcount,
+        check_priority.popular_votes,
+        check_priority.interval_broken.as_secs(),
+        check_priority.broken_recent.as_secs(),
+        check_priority.backoff_step.as_secs(),
+        check_priority.interval_max.as_secs(),
+        stations_count,
+    )?;
+    let checked_count = stations.len() as u32;
 
-    let (result_sender, result_receiver): (Sender<StationOldNew>, Receiver<StationOldNew>) =
-        channel();
-    let pool = ThreadPool::new(concurrency);
-    let checked_count = dbcheck_internal(&pool, stations, source, timeout, max_depth, retries, result_sender);
-    pool.join();
+    let urls = stations.iter().map(|station| (station.stationuuid.clone(), station.url.clone())).collect();
+    let items = runtime.block_on(check_urls(urls, source, concurrency, timeout, max_depth, retries, capture_title, deep_check));
+    let list: Vec<StationOldNew> = stations.into_iter().zip(items).map(|(old, new)| StationOldNew { old, new }).collect();
 
-    for oldnew in result_receiver {
-        let station = oldnew.old;
-        let new_item = oldnew.new;
+    let mut favicons = Vec::with_capacity(list.len());
+    for oldnew in list.iter() {
         if favicon_checks {
-            let new_favicon = favicon::check(&station.homepage, &station.favicon, &useragent, timeout as u32)?;
-            update_station(&mut conn, &station, new_item, &new_favicon)?;
+            favicons.push(check_favicon(&oldnew.old, useragent, timeout));
         } else {
-            update_station(&mut conn, &station, new_item, &station.favicon)?;
+            favicons.push(oldnew.old.favicon.clone());
         }
     }
+    update_stations(conn.as_ref(), list, &favicons, title_history)?;
     Ok(checked_count)
 }
@@ -0,0 +1,90 @@
+/// Failure reasons of stream checks, as stored in StationCheckHistory.FailureReason
+pub const FAILURE_TIMEOUT: &str = "timeout";
+pub const FAILURE_DNS: &str = "dns";
+pub const FAILURE_CONNECT: &str = "connect";
+pub const FAILURE_CONNECT_TIMEOUT: &str = "connect_timeout";
+pub const FAILURE_READ_TIMEOUT: &str = "read_timeout";
+pub const FAILURE_TLS: &str = "tls";
+pub const FAILURE_HTTP_STATUS: &str = "http_status";
+pub const FAILURE_HTTP_PROTOCOL: &str = "http_protocol";
+pub const FAILURE_CONTENT_TYPE: &str = "content_type";
+pub const FAILURE_PLAYLIST: &str = "playlist";
+pub const FAILURE_REDIRECT_LOOP: &str = "redirect_loop";
+pub const FAILURE_INVALID_URL: &str = "invalid_url";
+pub const FAILURE_NO_RESULT: &str = "no_result";
+pub const FAILURE_UNKNOWN: &str = "unknown";
+
+/// Texts of the errors and their failure reasons, the first match wins,
+/// so more specific texts have to be in front of more general ones
+const FAILURE_TEXTS: &[(&str, &[&str])] = &[
+    (FAILURE_REDIRECT_LOOP, &["max depth reached"]),
+    (FAILURE_DNS, &["failed to lookup address", "name or service not known", "no address associated", "nodename nor servname"]),
+    (FAILURE_TLS, &["handshake", "ssl", "tls", "certificate"]),
+    (FAILURE_HTTP_STATUS, &["illegal http status code"]),
+    (FAILURE_CONTENT_TYPE, &["content type", "content-type"]),
+    (FAILURE_HTTP_PROTOCOL, &["http status line", "http header"]),
+    (FAILURE_PLAYLIST, &["playlist"]),
+    (FAILURE_INVALID_URL, &["unknown scheme", "illegal host name", "port unknown", "empty host", "relative url", "invalid"]),
+    (FAILURE_CONNECT_TIMEOUT, &["connect timed out", "connection timed out"]),
+    (FAILURE_CONNECT, &["connection was not possible", "connection refused", "connection reset", "network is unreachable", "no route to host"]),
+    (FAILURE_READ_TIMEOUT, &["read timed out", "resource temporarily unavailable", "would block", "timed out"]),
+];
+
+/// av_stream_info_rust and the probe only return error messages, so the reason
+/// has to be found by the texts of the libraries and of the errors they pass through
+pub fn get_failure_reason(message: &str) -> &'static str {
+    let message = message.to_lowercase();
+    FAILURE_TEXTS
+        .iter()
+        .find(|(_, texts)| texts.iter().any(|text| message.contains(text)))
+        .map(|(reason, _)| *reason)
+        .unwrap_or(FAILURE_UNKNOWN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_errors() {
+        assert_eq!(get_failure_reason("max depth reached"), FAILURE_REDIRECT_LOOP);
+        assert_eq!(get_failure_reason("illegal http status code 404"), FAILURE_HTTP_STATUS);
+        assert_eq!(get_failure_reason("unknown content type text/html; charset=UTF-8"), FAILURE_CONTENT_TYPE);
+        assert_eq!(get_failure_reason("Missing content-type in http header"), FAILURE_CONTENT_TYPE);
+        assert_eq!(get_failure_reason("HTTP status line too short"), FAILURE_HTTP_PROTOCOL);
+        assert_eq!(get_failure_reason("HTTP header missing"), FAILURE_HTTP_PROTOCOL);
+        assert_eq!(get_failure_reason("Empty playlist"), FAILURE_PLAYLIST);
+        assert_eq!(get_failure_reason("unknown scheme"), FAILURE_INVALID_URL);
+        assert_eq!(get_failure_reason("illegal host name"), FAILURE_INVALID_URL);
+        assert_eq!(get_failure_reason("relative URL without a base"), FAILURE_INVALID_URL);
+        assert_eq!(get_failure_reason("invalid port number"), FAILURE_INVALID_URL);
+        assert_eq!(get_failure_reason("stream ended"), FAILURE_UNKNOWN);
+    }
+
+    #[test]
+    fn network_errors() {
+        assert_eq!(get_failure_reason("failed to lookup address information: Name or service not known"), FAILURE_DNS);
+        assert_eq!(get_failure_reason("failed to lookup address information: nodename nor servname provided, or not known"), FAILURE_DNS);
+        assert_eq!(get_failure_reason("connection was not possible"), FAILURE_CONNECT);
+        assert_eq!(get_failure_reason("connection was not possible: Connection refused (os error 111)"), FAILURE_CONNECT);
+        assert_eq!(get_failure_reason("connection was not possible: No route to host (os error 113)"), FAILURE_CONNECT);
+        assert_eq!(get_failure_reason("Connection reset by peer (os error 104)"), FAILURE_CONNECT);
+    }
+
+    #[test]
+    fn timeouts() {
+        assert_eq!(get_failure_reason("connection was not possible: connect timed out"), FAILURE_CONNECT_TIMEOUT);
+        assert_eq!(get_failure_reason("connection was not possible: Connection timed out (os error 110)"), FAILURE_CONNECT_TIMEOUT);
+        assert_eq!(get_failure_reason("read timed out"), FAILURE_READ_TIMEOUT);
+        assert_eq!(get_failure_reason("Resource temporarily unavailable (os error 11)"), FAILURE_READ_TIMEOUT);
+        assert_eq!(get_failure_reason("operation would block"), FAILURE_READ_TIMEOUT);
+    }
+
+    #[test]
+    fn tls_errors_before_timeouts() {
+        assert_eq!(get_failure_reason("tls handshake did not finish in time"), FAILURE_TLS);
+        assert_eq!(get_failure_reason("the handshake failed: error:1416F086:SSL routines:tls_process_server_certificate:certificate verify failed"), FAILURE_TLS);
+        assert_eq!(get_failure_reason("TLS read timed out"), FAILURE_TLS);
+        assert_eq!(get_failure_reason("invalid certificate: UnknownIssuer"), FAILURE_TLS);
+    }
+}
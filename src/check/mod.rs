@@ -1,27 +1,138 @@
+mod audio;
 mod check;
+mod failure;
 mod favicon;
+mod probe;
+mod worker;
 
+pub use self::check::check_stream;
+pub use self::check::dbcheck_station;
+pub use self::check::get_checks_running;
+pub use self::check::get_checks_timed_out;
+pub use self::check::insert_titles;
+
+use crate::config::CheckPriority;
+use crate::db::connect;
+use crate::db::DbConnection;
+use crate::health;
+use reqwest::blocking::Client;
 use std::thread;
 use std::time::Duration;
 
+/// Time of a batch of checks including the pause, if every check runs into its timeout,
+/// the checks of a batch run in rounds of concurrency checks and the favicons are checked one after another
+fn get_check_interval(pause_seconds: u64, tcp_timeout: u64, retries: u8, check_stations: u32, concurrency: usize, favicon: bool) -> u64 {
+    let concurrency = concurrency.max(1) as u64;
+    let rounds = (check_stations as u64 + concurrency - 1) / concurrency;
+    let check_timeout = (retries.max(1) as u64) * tcp_timeout * 2;
+    // a favicon check asks the old favicon and the homepage
+    let favicon_timeout = if favicon { check_stations as u64 * tcp_timeout * 2 } else { 0 };
+    pause_seconds + rounds.max(1) * check_timeout + favicon_timeout
+}
+
 pub fn start(
     database_url: String,
     source: String,
     concurrency: usize,
     check_stations: u32,
+    check_priority: CheckPriority,
     useragent: String,
     tcp_timeout: u64,
     max_depth: u8,
     retries: u8,
     favicon: bool,
+    capture_title: bool,
+    deep_check: bool,
+    title_history: u32,
     enable_check: bool,
     pause_seconds: u64,
 ) {
     if enable_check {
-        thread::spawn(move || loop {
-            trace!("Check started.. (concurrency: {}, chunksize: {})", concurrency, check_stations);
-            let result = check::dbcheck(
-                database_url.clone(),
+        health::register("check", get_check_interval(pause_seconds, tcp_timeout, retries, check_stations, concurrency, favicon));
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new().threaded_scheduler().enable_all().build();
+            let mut runtime = match runtime {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    error!("Check worker could not create runtime: {}", err);
+                    return;
+                }
+            };
+            // the connection is kept between the batches and only replaced after errors
+            let mut conn: Option<Box<dyn DbConnection>> = None;
+            loop {
+                trace!("Check started.. (concurrency: {}, chunksize: {})", concurrency, check_stations);
+                if conn.is_none() {
+                    match connect(database_url.clone()) {
+                        Ok(new_conn) => conn = Some(new_conn),
+                        Err(err) => error!("Check worker connection error: {}", err),
+                    }
+                }
+                if let Some(ref mut current_conn) = conn {
+                    let result = check::dbcheck(
+                        current_conn,
+                        &mut runtime,
+                        &source,
+                        concurrency,
+                        check_stations,
+                        &check_priority,
+                        &useragent,
+                        tcp_timeout,
+                        max_depth,
+                        retries,
+                        favicon,
+                        capture_title,
+                        deep_check,
+                        title_history,
+                    );
+                    health::report("check", &result);
+                    match result {
+                        Ok(_)=>{},
+                        Err(err)=>{
+                            error!("Check worker error: {}", err);
+                            conn = None;
+                        }
+                    }
+                }
+                thread::sleep(Duration::from_secs(pause_seconds));
+            }
+        });
+    }
+}
+
+/// Check stations of a coordinator instead of the own database
+pub fn start_worker(
+    coordinator: String,
+    token: String,
+    source: String,
+    concurrency: usize,
+    check_stations: u32,
+    useragent: String,
+    tcp_timeout: u64,
+    max_depth: u8,
+    retries: u8,
+    capture_title: bool,
+    deep_check: bool,
+    pause_seconds: u64,
+) {
+    health::register("check", get_check_interval(pause_seconds, tcp_timeout, retries, check_stations, concurrency, false));
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new().threaded_scheduler().enable_all().build();
+        let mut runtime = match runtime {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                error!("Check worker could not create runtime: {}", err);
+                return;
+            }
+        };
+        let client = Client::new();
+        loop {
+            trace!("Check worker started.. (coordinator: {}, concurrency: {}, chunksize: {})", coordinator, concurrency, check_stations);
+            let result = worker::check_lease(
+                &client,
+                &mut runtime,
+                &coordinator,
+                &token,
                 &source,
                 concurrency,
                 check_stations,
@@ -29,15 +140,15 @@ pub fn start(
                 tcp_timeout,
                 max_depth,
                 retries,
-                favicon,
+                capture_title,
+                deep_check,
             );
+            health::report("check", &result);
             match result {
-                Ok(_)=>{},
-                Err(err)=>{
-                    error!("Check worker error: {}", err);
-                }
+                Ok(count) => debug!("Check worker checked {} stations", count),
+                Err(err) => error!("Check worker error: {}", err),
             }
             thread::sleep(Duration::from_secs(pause_seconds));
-        });
-    }
+        }
+    });
 }
@@ -0,0 +1,946 @@
+//! Async version of the stream check of av_stream_info_rust, so that thousands of streams
+//! can be checked at the same time without a thread for each of them.
+//! Results and error messages are the same as the ones of the library.
+use crate::check::audio::{self, AudioInfo};
+use av_stream_info_rust::{StreamCheckError, StreamCheckResult, StreamInfo};
+use hls_m3u8::MasterPlaylist;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use url::Url;
+
+type ProbeResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+/// Streams that were found and the details of the first working stream
+type CheckOnceResult = (Vec<StreamCheckResult>, StreamDetails);
+
+const USER_AGENT: &str = "StreamCheckBot/0.1.0";
+/// Maximum size of playlists and http headers
+const MAX_CONTENT_SIZE: usize = 10000;
+/// Bytes of the stream that are used to find the content type
+const DEEP_SCAN_SIZE: usize = 50;
+/// Maximum entries of a playlist that are checked
+const MAX_PLAYLIST_URLS: usize = 9;
+/// Maximum icy-metaint of streams, larger values are not read to find the title
+const MAX_ICY_METAINT: usize = 65536;
+/// Maximum size of hls media playlists and segments that are searched for the title and audio frames
+const MAX_SEGMENT_SIZE: usize = 65536;
+/// Bytes of the stream that are analyzed by the deep check, a few seconds of audio at usual bitrates
+const DEEP_CHECK_SIZE: usize = 65536;
+
+/// Information that is only read from the stream data if it was requested
+#[derive(Debug, Default)]
+pub struct StreamDetails {
+    /// Now playing title
+    pub title: Option<String>,
+    /// Audio format of the frames of the deep check
+    pub audio: Option<AudioInfo>,
+}
+
+#[derive(Debug)]
+struct ProbeError {
+    details: String,
+}
+
+impl ProbeError {
+    fn new(msg: &str) -> ProbeError {
+        ProbeError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for ProbeError {}
+
+fn new_error(url: &str, msg: &str) -> StreamCheckResult {
+    Err(StreamCheckError {
+        Url: url.to_string(),
+        Msg: msg.to_string(),
+    })
+}
+
+struct Response {
+    code: u32,
+    headers: HashMap<String, String>,
+    readable: Box<dyn AsyncRead + Unpin + Send>,
+    timeout: Duration,
+}
+
+async fn read_with_timeout(readable: &mut (dyn AsyncRead + Unpin + Send), buffer: &mut [u8], timeout: Duration) -> io::Result<usize> {
+    match tokio::time::timeout(timeout, readable.read(buffer)).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "read timed out")),
+    }
+}
+
+impl Response {
+    /// Read up to max_size bytes of the body, less if the stream ends before
+    async fn read_up_to(&mut self, max_size: usize) -> io::Result<Vec<u8>> {
+        let mut content = vec![];
+        let mut buffer = vec![0; MAX_CONTENT_SIZE];
+        while content.len() < max_size {
+            let bytes = read_with_timeout(self.readable.as_mut(), &mut buffer, self.timeout).await?;
+            if bytes == 0 {
+                break;
+            }
+            content.extend_from_slice(&buffer[0..bytes]);
+        }
+        Ok(content)
+    }
+
+    /// Read more of the body until content has at least size bytes
+    async fn read_to_size(&mut self, content: &mut Vec<u8>, size: usize) -> io::Result<()> {
+        let mut buffer = vec![0; MAX_CONTENT_SIZE];
+        while content.len() < size {
+            let bytes = read_with_timeout(self.readable.as_mut(), &mut buffer, self.timeout).await?;
+            if bytes == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended"));
+            }
+            content.extend_from_slice(&buffer[0..bytes]);
+        }
+        Ok(())
+    }
+
+    fn content_length(&self) -> Option<usize> {
+        self.headers.get("content-length").and_then(|value| value.parse().ok())
+    }
+}
+
+async fn read_until(readable: &mut (dyn AsyncRead + Unpin + Send), condition: &[u8], timeout: Duration) -> io::Result<String> {
+    let mut buffer = [0; 1];
+    let mut bytes = Vec::new();
+    while bytes.len() <= MAX_CONTENT_SIZE {
+        if read_with_timeout(readable, &mut buffer, timeout).await? == 0 {
+            break;
+        }
+        bytes.push(buffer[0]);
+        if bytes.ends_with(condition) {
+            break;
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Status code of "HTTP/1.1 200 OK" or "ICY 200 OK", the line may contain any bytes the server sent
+fn decode_status_line(line: &str) -> ProbeResult<u32> {
+    if !line.starts_with("HTTP/") && !line.starts_with("ICY") {
+        return Err(Box::new(ProbeError::new("HTTP header missing")));
+    }
+    match line.split_whitespace().nth(1) {
+        Some(code) if code.len() == 3 => Ok(code.parse()?),
+        _ => Err(Box::new(ProbeError::new("HTTP status line too short"))),
+    }
+}
+
+async fn connect(host: &str, port: u16, timeout: Duration) -> ProbeResult<TcpStream> {
+    let addrs = tokio::net::lookup_host((host, port)).await?;
+    // the error of the last address is returned, it tells connect timeouts from refused connections
+    let mut error = ProbeError::new("connection was not possible");
+    for addr in addrs {
+        match tokio::time::timeout(timeout, TcpStream::connect(&addr)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => error = ProbeError::new(&format!("connection was not possible: {}", err)),
+            Err(_) => error = ProbeError::new("connection was not possible: connect timed out"),
+        }
+    }
+    Err(Box::new(error))
+}
+
+async fn request(url_str: &str, timeout: Duration, icy_metadata: bool) -> ProbeResult<Response> {
+    let url = Url::parse(url_str)?;
+    let host = url.host_str().ok_or(ProbeError::new("illegal host name"))?;
+    let port = url.port_or_known_default().ok_or(ProbeError::new("port unknown"))?;
+    let default_port = match url.scheme() {
+        "http" => 80,
+        "https" => 443,
+        _ => return Err(Box::new(ProbeError::new("unknown scheme"))),
+    };
+    let host_header = if port == default_port { String::from(host) } else { format!("{}:{}", host, port) };
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => String::from(url.path()),
+    };
+    let request_str = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: */*\r\nUser-Agent: {}\r\n{}Connection: close\r\n\r\n",
+        path, host_header, USER_AGENT, if icy_metadata { "Icy-MetaData: 1\r\n" } else { "" }
+    );
+
+    let stream = connect(host, port, timeout).await?;
+    let readable: Box<dyn AsyncRead + Unpin + Send> = if url.scheme() == "https" {
+        let connector = tokio_tls::TlsConnector::from(native_tls::TlsConnector::builder().build()?);
+        let mut stream = tokio::time::timeout(timeout, connector.connect(host, stream)).await
+            .map_err(|_| ProbeError::new("tls handshake did not finish in time"))??;
+        stream.write_all(request_str.as_bytes()).await?;
+        stream.flush().await?;
+        Box::new(BufReader::new(stream))
+    } else {
+        let mut stream = stream;
+        stream.write_all(request_str.as_bytes()).await?;
+        stream.flush().await?;
+        Box::new(BufReader::new(stream))
+    };
+
+    read_response(readable, timeout).await
+}
+
+/// Read the status line and the headers, the body is left in readable
+async fn read_response(mut readable: Box<dyn AsyncRead + Unpin + Send>, timeout: Duration) -> ProbeResult<Response> {
+    let status_line = read_until(readable.as_mut(), b"\r\n", timeout).await?;
+    let code = decode_status_line(&status_line)?;
+    let mut headers = HashMap::new();
+    for line in read_until(readable.as_mut(), b"\r\n\r\n", timeout).await?.lines() {
+        if let Some(index) = line.find(':') {
+            let (key, value) = line.split_at(index);
+            headers.insert(key.to_lowercase(), String::from(value[1..].trim()));
+        }
+    }
+    Ok(Response { code, headers, readable, timeout })
+}
+
+fn type_is_playlist(content_type: &str) -> bool {
+    let content_type = content_type.split(';').next().unwrap_or("");
+    match content_type {
+        "application/mpegurl" | "application/x-mpegurl" | "audio/mpegurl" | "audio/x-mpegurl"
+        | "application/vnd.apple.mpegurl" | "application/vnd.apple.mpegurl.audio" => true,
+        "audio/x-scpls" | "application/x-scpls" | "application/pls+xml" => true,
+        "video/x-ms-asx" | "video/x-ms-asf" => true,
+        "application/xspf+xml" => true,
+        _ => false,
+    }
+}
+
+fn type_is_stream(content_type: &str) -> Option<&'static str> {
+    match content_type {
+        "audio/mpeg" | "audio/x-mpeg" | "audio/mp3" => Some("MP3"),
+        "audio/aac" | "audio/x-aac" => Some("AAC"),
+        "audio/aacp" => Some("AAC+"),
+        "audio/ogg" | "application/ogg" | "video/ogg" => Some("OGG"),
+        "audio/flac" => Some("FLAC"),
+        "application/flv" => Some("FLV"),
+        "application/octet-stream" => Some("UNKNOWN"),
+        _ => None,
+    }
+}
+
+fn decode_hls_codecs(codecs_raw: &str) -> (String, Option<String>) {
+    let mut codec_audio = String::from("UNKNOWN");
+    if codecs_raw.contains("mp4a.40.2") {
+        codec_audio = String::from("AAC");
+    }
+    if codecs_raw.contains("mp4a.40.5") {
+        codec_audio = String::from("AAC+");
+    }
+    if codecs_raw.contains("mp4a.40.34") {
+        codec_audio = String::from("MP3");
+    }
+    let h264 = ["avc1.42001e", "avc1.66.30", "avc1.42001f", "avc1.4d001e", "avc1.77.30", "avc1.4d001f", "avc1.4d0028", "avc1.64001f", "avc1.640028", "avc1.640029"];
+    let codec_video = if h264.iter().any(|codec| codecs_raw.contains(codec)) { Some(String::from("H.264")) } else { None };
+    (codec_audio, codec_video)
+}
+
+fn new_stream_info(url: &str, codec_audio: String, codec_video: Option<String>, bitrate: u32, hls: bool) -> StreamInfo {
+    StreamInfo {
+        Public: None,
+        AudioInfo: None,
+        Name: None,
+        Description: None,
+        Type: String::from(""),
+        Url: String::from(url),
+        Homepage: None,
+        Genre: None,
+        Bitrate: bitrate,
+        Sampling: 0,
+        CodecAudio: codec_audio,
+        CodecVideo: codec_video,
+        Hls: hls,
+        LogoUrl: None,
+        LoadBalancerUrl: None,
+        IcyVersion: 1,
+        OverrideIndexMetaData: false,
+        CountryCode: None,
+    }
+}
+
+fn decode_hls(url: &str, content: &str) -> StreamCheckResult {
+    match content.parse::<MasterPlaylist>() {
+        Ok(playlist) => {
+            if let Some(stream_inf) = playlist.stream_inf_tags().iter().next() {
+                let (audio, video) = match stream_inf.codecs() {
+                    Some(codecs) => decode_hls_codecs(&codecs.to_string()),
+                    None => (String::from("UNKNOWN"), None),
+                };
+                Ok(new_stream_info(url, audio, video, (stream_inf.bandwidth() as u32) / 1000, true))
+            } else {
+                Ok(new_stream_info(url, String::from("UNKNOWN"), None, 0, true))
+            }
+        }
+        Err(_) => Ok(new_stream_info(url, String::from("UNKNOWN"), None, 0, true)),
+    }
+}
+
+/// Remove padding and placeholders, that some streams send instead of an empty title
+fn clean_title(title: &str) -> Option<String> {
+    let title = title.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if title.is_empty() || title == "-" {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+/// Text in utf8, older servers send latin1
+fn decode_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| if little_endian { u16::from_le_bytes([pair[0], pair[1]]) } else { u16::from_be_bytes([pair[0], pair[1]]) })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Title of an icy metadata block like "StreamTitle='Artist - Title';StreamUrl='';"
+fn decode_icy_title(metadata: &[u8]) -> Option<String> {
+    let metadata = decode_text(metadata);
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &metadata[start..];
+    let end = rest.find("';").or_else(|| rest.rfind('\'')).unwrap_or(rest.len());
+    clean_title(&rest[..end])
+}
+
+/// Read the first icy metadata block of the stream, it follows the first icy-metaint bytes of audio data
+async fn read_icy_title(response: &mut Response, content: &mut Vec<u8>) -> Option<String> {
+    let metaint = icy_metaint(response);
+    if metaint == 0 || metaint > MAX_ICY_METAINT {
+        return None;
+    }
+    response.read_to_size(content, metaint + 1).await.ok()?;
+    let metadata_end = metaint + 1 + (content[metaint] as usize) * 16;
+    response.read_to_size(content, metadata_end).await.ok()?;
+    decode_icy_title(&content[metaint + 1..metadata_end])
+}
+
+/// Bytes of audio data between the icy metadata blocks, 0 if the stream has no metadata
+fn icy_metaint(response: &Response) -> usize {
+    response.headers.get("icy-metaint").and_then(|value| value.parse().ok()).unwrap_or(0)
+}
+
+/// Remove the icy metadata blocks, so that only the audio data is left
+fn strip_icy_metadata(content: &[u8], metaint: usize) -> Vec<u8> {
+    if metaint == 0 {
+        return content.to_vec();
+    }
+    let mut audio = Vec::with_capacity(content.len());
+    let mut pos = 0;
+    while pos < content.len() {
+        let audio_end = (pos + metaint).min(content.len());
+        audio.extend_from_slice(&content[pos..audio_end]);
+        pos = match content.get(audio_end) {
+            Some(&length) => audio_end + 1 + (length as usize) * 16,
+            None => break,
+        };
+    }
+    audio
+}
+
+fn decode_id3_size(bytes: &[u8], syncsafe: bool) -> usize {
+    if syncsafe {
+        bytes.iter().fold(0, |size, &byte| (size << 7) | (byte & 0x7f) as usize)
+    } else {
+        bytes.iter().fold(0, |size, &byte| (size << 8) | byte as usize)
+    }
+}
+
+/// Text of an ID3 text frame, only the first of multiple values is used
+fn decode_id3_text(body: &[u8]) -> Option<String> {
+    let (encoding, text) = body.split_first()?;
+    let text = match encoding {
+        0 => text.iter().map(|&byte| byte as char).collect(),
+        1 if text.starts_with(&[0xff, 0xfe]) => decode_utf16(&text[2..], true),
+        1 if text.starts_with(&[0xfe, 0xff]) => decode_utf16(&text[2..], false),
+        1 | 2 => decode_utf16(text, false),
+        3 => String::from_utf8_lossy(text).to_string(),
+        _ => return None,
+    };
+    clean_title(text.split('\0').next().unwrap_or(""))
+}
+
+/// Title of an ID3v2.3 or ID3v2.4 tag from the frames TPE1 (artist) and TIT2 (title)
+fn decode_id3_tag(tag: &[u8]) -> Option<String> {
+    if tag.len() < 10 {
+        return None;
+    }
+    let version = tag[3];
+    if version != 3 && version != 4 {
+        return None;
+    }
+    let syncsafe = version == 4;
+    let end = (10 + decode_id3_size(&tag[6..10], true)).min(tag.len());
+    let mut pos = 10;
+    if tag[5] & 0x40 != 0 {
+        // skip extended header
+        if tag.len() < 14 {
+            return None;
+        }
+        pos += if syncsafe { decode_id3_size(&tag[10..14], true) } else { decode_id3_size(&tag[10..14], false) + 4 };
+    }
+
+    let mut title = None;
+    let mut artist = None;
+    while pos + 10 <= end && tag[pos] != 0 {
+        let body_start = pos + 10;
+        let body_end = body_start + decode_id3_size(&tag[pos + 4..pos + 8], syncsafe);
+        if body_end > end {
+            break;
+        }
+        match &tag[pos..pos + 4] {
+            b"TIT2" => title = decode_id3_text(&tag[body_start..body_end]),
+            b"TPE1" => artist = decode_id3_text(&tag[body_start..body_end]),
+            _ => {}
+        }
+        pos = body_end;
+    }
+    match (artist, title) {
+        (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+        (None, Some(title)) => Some(title),
+        (Some(artist), None) => Some(artist),
+        (None, None) => None,
+    }
+}
+
+/// Title of the first ID3 tag in the data, the tags are at the start of packed audio
+/// segments and in the elementary streams of transport stream segments
+fn decode_id3_title(data: &[u8]) -> Option<String> {
+    let mut start = 0;
+    while let Some(pos) = data[start..].windows(3).position(|window| window == b"ID3") {
+        if let Some(title) = decode_id3_tag(&data[start + pos..]) {
+            return Some(title);
+        }
+        start += pos + 3;
+    }
+    None
+}
+
+fn playlist_uris(content: &str) -> impl Iterator<Item = &str> {
+    content.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+async fn read_hls_file(url: &str, timeout: Duration) -> Option<Vec<u8>> {
+    let mut response = request(url, timeout, false).await.ok()?;
+    if response.code < 200 || response.code >= 300 {
+        return None;
+    }
+    response.read_up_to(MAX_SEGMENT_SIZE).await.ok()
+}
+
+/// Read the newest segment of a hls stream, master playlists are followed to their first variant
+async fn hls_segment(url: &str, content: &str, timeout: Duration) -> Option<Vec<u8>> {
+    let mut media_url = Url::parse(url).ok()?;
+    let mut media = content.to_string();
+    if content.contains("#EXT-X-STREAM-INF") {
+        media_url = media_url.join(playlist_uris(content).next()?).ok()?;
+        media = String::from_utf8_lossy(&read_hls_file(media_url.as_str(), timeout).await?).to_string();
+        if media.len() >= MAX_SEGMENT_SIZE {
+            // the last line may have been cut
+            media.truncate(media.rfind('\n')?);
+        }
+    }
+    let segment_url = media_url.join(playlist_uris(&media).last()?).ok()?;
+    read_hls_file(segment_url.as_str(), timeout).await
+}
+
+/// Find the title in the ID3 tags and the audio format in the frames of the newest segment
+async fn hls_details(url: &str, content: &str, timeout: Duration, capture_title: bool, deep_check: bool) -> StreamDetails {
+    if !capture_title && !deep_check {
+        return StreamDetails::default();
+    }
+    match hls_segment(url, content, timeout).await {
+        Some(segment) => StreamDetails {
+            title: if capture_title { decode_id3_title(&segment) } else { None },
+            audio: if deep_check { audio::analyze(&segment) } else { None },
+        },
+        None => StreamDetails::default(),
+    }
+}
+
+async fn handle_playlist(mut response: Response, url: String, timeout: Duration, max_depth: u8, capture_title: bool, deep_check: bool) -> CheckOnceResult {
+    let max_size = response.content_length().unwrap_or(MAX_CONTENT_SIZE).min(MAX_CONTENT_SIZE);
+    let content = match response.read_up_to(max_size).await {
+        Ok(content) => String::from_utf8_lossy(&content).to_string(),
+        Err(err) => return (vec![new_error(&url, &err.to_string())], StreamDetails::default()),
+    };
+    if playlist_decoder::is_content_hls(&content) {
+        let details = hls_details(&url, &content, timeout, capture_title, deep_check).await;
+        return (vec![decode_hls(&url, &content)], details);
+    }
+
+    let base_url = match Url::parse(&url) {
+        Ok(base_url) => base_url,
+        Err(err) => return (vec![new_error(&url, &err.to_string())], StreamDetails::default()),
+    };
+    let mut list = vec![];
+    let mut details = StreamDetails::default();
+    for entry in playlist_decoder::decode(&content).iter().take(MAX_PLAYLIST_URLS) {
+        if entry.trim() == "" {
+            continue;
+        }
+        match base_url.join(entry) {
+            Ok(abs_url) => {
+                let (result, entry_details) = check_once(abs_url.to_string(), timeout, max_depth - 1, capture_title, deep_check).await;
+                let found = result.iter().any(|item| item.is_ok());
+                list.extend(result);
+                if found {
+                    details = entry_details;
+                    break;
+                }
+            }
+            Err(err) => list.push(new_error(&url, &err.to_string())),
+        }
+    }
+    if list.len() == 0 {
+        list.push(new_error(&url, "Empty playlist"));
+    }
+    (list, details)
+}
+
+async fn handle_stream(mut response: Response, content_type: String, url: String, mut codec: String, capture_title: bool, deep_check: bool) -> (StreamInfo, StreamDetails) {
+    // the content type of the header is often wrong or too generic, the first bytes tell better
+    let mut content = vec![];
+    if let Ok(bytes) = response.read_up_to(DEEP_SCAN_SIZE).await {
+        let mime = tree_magic::from_u8(&bytes);
+        if mime != "application/octet-stream" {
+            if let Some(codec_scanned) = type_is_stream(&mime) {
+                debug!("url={}, override stream_type with deep scan: {}", url, codec_scanned);
+                codec = String::from(codec_scanned);
+            }
+        }
+        content = bytes;
+    }
+    let title = if capture_title { read_icy_title(&mut response, &mut content).await } else { None };
+    let audio = if deep_check {
+        // streams that end early are analyzed with the data that was read
+        let _ = response.read_to_size(&mut content, DEEP_CHECK_SIZE).await;
+        audio::analyze(&strip_icy_metadata(&content, icy_metaint(&response)))
+    } else {
+        None
+    };
+
+    let mut headers = response.headers;
+    let header_number = |headers: &HashMap<String, String>, name: &str, default: u32| -> u32 {
+        headers.get(name).and_then(|value| value.parse().ok()).unwrap_or(default)
+    };
+    let info = StreamInfo {
+        Public: headers.get("icy-pub").and_then(|value| value.parse::<u32>().ok()).map(|value| value == 1),
+        Bitrate: header_number(&headers, "icy-br", 0),
+        Sampling: header_number(&headers, "icy-sr", 0),
+        IcyVersion: header_number(&headers, "icy-version", 1),
+        OverrideIndexMetaData: header_number(&headers, "icy-index-metadata", 0) == 1,
+        AudioInfo: headers.remove("icy-audio-info"),
+        Name: headers.remove("icy-name"),
+        Description: headers.remove("icy-description"),
+        Homepage: headers.remove("icy-url"),
+        Genre: headers.remove("icy-genre"),
+        LogoUrl: headers.remove("icy-logo"),
+        LoadBalancerUrl: headers.remove("icy-loadbalancer"),
+        CountryCode: headers.remove("icy-countrycode"),
+        Type: content_type,
+        Url: url,
+        CodecAudio: codec,
+        CodecVideo: None,
+        Hls: false,
+    };
+    (info, StreamDetails { title, audio })
+}
+
+/// Follow redirects and playlists up to max_depth and return the streams that were found
+fn check_once(url: String, timeout: Duration, max_depth: u8, capture_title: bool, deep_check: bool) -> Pin<Box<dyn Future<Output = CheckOnceResult> + Send>> {
+    Box::pin(async move {
+        debug!("check(url={})", url);
+        if max_depth == 0 {
+            return (vec![new_error(&url, "max depth reached")], StreamDetails::default());
+        }
+        let mut response = match request(&url, timeout, capture_title).await {
+            Ok(response) => response,
+            Err(err) => return (vec![new_error(&url, &err.to_string())], StreamDetails::default()),
+        };
+        if response.code >= 200 && response.code < 300 {
+            let content_type = match response.headers.remove("content-type") {
+                Some(content_type) => content_type,
+                None => return (vec![new_error(&url, "Missing content-type in http header")], StreamDetails::default()),
+            };
+            let content_type_lower = content_type.to_lowercase();
+            if type_is_playlist(&content_type_lower) || response.content_length().is_some() {
+                handle_playlist(response, url, timeout, max_depth, capture_title, deep_check).await
+            } else if let Some(codec) = type_is_stream(&content_type_lower) {
+                let (info, details) = handle_stream(response, content_type, url, String::from(codec), capture_title, deep_check).await;
+                (vec![Ok(info)], details)
+            } else {
+                (vec![new_error(&url, &format!("unknown content type {}", content_type))], StreamDetails::default())
+            }
+        } else if response.code >= 300 && response.code < 400 {
+            match response.headers.get("location") {
+                Some(location) => {
+                    // relative redirects are resolved against the current url
+                    let location = Url::parse(&url).and_then(|base| base.join(location)).map(|url| url.to_string()).unwrap_or(location.clone());
+                    check_once(location, timeout, max_depth - 1, capture_title, deep_check).await
+                }
+                None => (vec![], StreamDetails::default()),
+            }
+        } else {
+            (vec![new_error(&url, &format!("illegal http status code {}", response.code))], StreamDetails::default())
+        }
+    })
+}
+
+/// Check url for audio streams, retry until at least one working stream was found,
+/// with capture_title the now playing title is read from the icy metadata or the ID3 tags of hls streams,
+/// with deep_check the audio format is read from the frames of the first seconds of the stream
+pub async fn check(url: &str, timeout: u64, max_depth: u8, retries: u8, capture_title: bool, deep_check: bool) -> CheckOnceResult {
+    let timeout = Duration::from_secs(timeout);
+    let mut result = (vec![], StreamDetails::default());
+    for retry in 0..retries {
+        if retry > 0 {
+            tokio::time::delay_for(Duration::from_secs(1)).await;
+        }
+        result = check_once(String::from(url), timeout, max_depth, capture_title, deep_check).await;
+        if result.0.iter().any(|item| item.is_ok()) {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const MP3_FRAME_HEADER: [u8; 4] = [0xff, 0xfb, 0x90, 0x44];
+
+    fn run<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new().basic_scheduler().enable_all().build().unwrap().block_on(future)
+    }
+
+    fn canned(bytes: &[u8]) -> Box<dyn AsyncRead + Unpin + Send> {
+        Box::new(io::Cursor::new(bytes.to_vec()))
+    }
+
+    /// Answer every connection with the canned response of the requested path, 404 for unknown paths
+    fn serve(responses: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(bytes) => request.extend_from_slice(&buffer[..bytes]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+                let response = responses.iter()
+                    .find(|(response_path, _)| *response_path == path)
+                    .map(|(_, response)| response.clone())
+                    .unwrap_or(b"HTTP/1.0 404 Not Found\r\n\r\n".to_vec());
+                let _ = stream.write_all(&response);
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    fn http_response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("{}\r\n", status);
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frames = vec![];
+        for _ in 0..count {
+            frames.extend_from_slice(&MP3_FRAME_HEADER);
+            frames.extend(std::iter::repeat(0).take(413));
+        }
+        frames
+    }
+
+    fn icy_stream(metaint: usize, title: &str) -> Vec<u8> {
+        let mut metadata = format!("StreamTitle='{}';", title).into_bytes();
+        metadata.resize((metadata.len() + 15) / 16 * 16, 0);
+        let audio = mp3_frames(40);
+        let mut stream = vec![];
+        for block in audio.chunks(metaint) {
+            stream.extend_from_slice(block);
+            stream.push((metadata.len() / 16) as u8);
+            stream.extend_from_slice(&metadata);
+        }
+        stream
+    }
+
+    fn id3_tag(frames: &[(&[u8; 4], &str)]) -> Vec<u8> {
+        let mut body = vec![];
+        for (id, text) in frames {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&((text.len() + 1) as u32).to_be_bytes());
+            body.extend_from_slice(&[0, 0, 3]);
+            body.extend_from_slice(text.as_bytes());
+        }
+        let size = body.len();
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend_from_slice(&[(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        tag.extend(body);
+        tag
+    }
+
+    fn errors(result: &CheckOnceResult) -> Vec<String> {
+        result.0.iter().filter_map(|item| item.as_ref().err()).map(|err| err.Msg.clone()).collect()
+    }
+
+    fn streams(result: &CheckOnceResult) -> Vec<&StreamInfo> {
+        result.0.iter().filter_map(|item| item.as_ref().ok()).collect()
+    }
+
+    #[test]
+    fn status_line() {
+        assert_eq!(decode_status_line("HTTP/1.1 200 OK\r\n").unwrap(), 200);
+        assert_eq!(decode_status_line("HTTP/1.0 302 Found\r\n").unwrap(), 302);
+        assert_eq!(decode_status_line("HTTP/1.1 404\r\n").unwrap(), 404);
+        assert_eq!(decode_status_line("ICY 200 OK\r\n").unwrap(), 200);
+        assert!(decode_status_line("HTTP/1.1\r\n").is_err());
+        assert!(decode_status_line("HTTP/1.1 2000 OK\r\n").is_err());
+        assert!(decode_status_line("HTTP/1.1 ÄÖÜ OK\r\n").is_err());
+        assert!(decode_status_line("HTTP/1.1 2\u{fffd}0 OK\r\n").is_err());
+        assert!(decode_status_line("ICYü").is_err());
+        assert!(decode_status_line("<html>").is_err());
+        assert!(decode_status_line("").is_err());
+    }
+
+    #[test]
+    fn response_headers() {
+        let canned_response = http_response("ICY 200 OK", &[("Content-Type", "audio/mpeg"), ("icy-name", " Test Radio "), ("ICY-BR", "128"), ("icy-metaint", "16000")], b"body");
+        let mut response = run(read_response(canned(&canned_response), Duration::from_secs(1))).unwrap();
+        assert_eq!(response.code, 200);
+        assert_eq!(response.headers.get("content-type").unwrap(), "audio/mpeg");
+        assert_eq!(response.headers.get("icy-name").unwrap(), "Test Radio");
+        assert_eq!(response.headers.get("icy-br").unwrap(), "128");
+        assert_eq!(icy_metaint(&response), 16000);
+        assert_eq!(response.content_length(), None);
+        assert_eq!(run(response.read_up_to(100)).unwrap(), b"body");
+
+        assert!(run(read_response(canned(b"SOURCE / ICE/1.0\r\n\r\n"), Duration::from_secs(1))).is_err());
+    }
+
+    #[test]
+    fn icy_title() {
+        assert_eq!(decode_icy_title(b"StreamTitle='Artist - Song';StreamUrl='';\0\0\0"), Some(String::from("Artist - Song")));
+        assert_eq!(decode_icy_title(b"StreamTitle='It's me';\0"), Some(String::from("It's me")));
+        assert_eq!(decode_icy_title(b"StreamTitle='K\xfcnstler - Lied';"), Some(String::from("Künstler - Lied")));
+        assert_eq!(decode_icy_title("StreamTitle='Künstler - Lied';".as_bytes()), Some(String::from("Künstler - Lied")));
+        assert_eq!(decode_icy_title(b"StreamTitle=' - ';"), None);
+        assert_eq!(decode_icy_title(b"StreamTitle='';"), None);
+        assert_eq!(decode_icy_title(b"StreamUrl='http://example.com';"), None);
+
+        let canned_response = http_response("ICY 200 OK", &[("icy-metaint", "16")], &icy_stream(16, "Artist - Song"));
+        let mut response = run(read_response(canned(&canned_response), Duration::from_secs(1))).unwrap();
+        let mut content = vec![];
+        assert_eq!(run(read_icy_title(&mut response, &mut content)), Some(String::from("Artist - Song")));
+
+        let canned_response = http_response("ICY 200 OK", &[("icy-metaint", "100000")], &icy_stream(16, "Artist - Song"));
+        let mut response = run(read_response(canned(&canned_response), Duration::from_secs(1))).unwrap();
+        assert_eq!(run(read_icy_title(&mut response, &mut vec![])), None);
+    }
+
+    #[test]
+    fn icy_metadata_is_stripped() {
+        // the metadata follows every 2 frames, the stream may end inside of a block
+        let stream = icy_stream(834, "Artist - Song");
+        assert_eq!(strip_icy_metadata(&stream, 834), mp3_frames(40));
+        assert_eq!(strip_icy_metadata(&stream[..2500], 834), &mp3_frames(40)[..2500 - 2 * (1 + 32)]);
+        assert_eq!(strip_icy_metadata(&stream, 0), stream);
+    }
+
+    #[test]
+    fn id3_title() {
+        let tag = id3_tag(&[(b"TPE1", "Artist"), (b"TIT2", "Title")]);
+        assert_eq!(decode_id3_tag(&tag), Some(String::from("Artist - Title")));
+        assert_eq!(decode_id3_tag(&id3_tag(&[(b"TIT2", "Title")])), Some(String::from("Title")));
+        assert_eq!(decode_id3_tag(&id3_tag(&[(b"TALB", "Album")])), None);
+
+        let mut segment = vec![0x47; 100];
+        segment.extend(tag);
+        segment.extend(mp3_frames(2));
+        assert_eq!(decode_id3_title(&segment), Some(String::from("Artist - Title")));
+        assert_eq!(decode_id3_title(b"ID3 but no tag"), None);
+    }
+
+    #[test]
+    fn content_types() {
+        assert!(type_is_playlist("audio/x-mpegurl"));
+        assert!(type_is_playlist("audio/x-scpls; charset=utf-8"));
+        assert!(!type_is_playlist("audio/mpeg"));
+        assert_eq!(type_is_stream("audio/mpeg"), Some("MP3"));
+        assert_eq!(type_is_stream("audio/aacp"), Some("AAC+"));
+        assert_eq!(type_is_stream("text/html"), None);
+    }
+
+    #[test]
+    fn hls_master_playlist() {
+        let content = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"\nmedia.m3u8\n";
+        let info = decode_hls("http://example.com/master.m3u8", content).unwrap();
+        assert_eq!(info.CodecAudio, "AAC");
+        assert_eq!(info.CodecVideo, None);
+        assert_eq!(info.Bitrate, 128);
+        assert!(info.Hls);
+
+        let (audio, video) = decode_hls_codecs("avc1.640028,mp4a.40.5");
+        assert_eq!(audio, "AAC+");
+        assert_eq!(video, Some(String::from("H.264")));
+    }
+
+    #[test]
+    fn stream() {
+        let server = serve(vec![
+            ("/stream", http_response("ICY 200 OK", &[("Content-Type", "audio/mpeg"), ("icy-name", "Test Radio"), ("icy-br", "128"), ("icy-sr", "44100"), ("icy-pub", "1"), ("icy-genre", "pop,rock"), ("icy-metaint", "1000")], &icy_stream(1000, "Artist - Song"))),
+            ("/html", http_response("HTTP/1.1 200 OK", &[("Content-Type", "text/html")], b"<html></html>")),
+            ("/nocontenttype", http_response("HTTP/1.1 200 OK", &[], b"")),
+            ("/error", http_response("HTTP/1.1 500 Internal Server Error", &[], b"")),
+        ]);
+
+        let result = run(check(&format!("{}/stream", server), 2, 5, 1, true, true));
+        let found = streams(&result);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].CodecAudio, "MP3");
+        assert_eq!(found[0].Bitrate, 128);
+        assert_eq!(found[0].Sampling, 44100);
+        assert_eq!(found[0].Public, Some(true));
+        assert_eq!(found[0].Name, Some(String::from("Test Radio")));
+        assert_eq!(found[0].Genre, Some(String::from("pop,rock")));
+        assert!(!found[0].Hls);
+        assert_eq!(result.1.title, Some(String::from("Artist - Song")));
+        let audio = result.1.audio.unwrap();
+        assert_eq!((audio.codec.as_str(), audio.samplerate, audio.channels, audio.bitrate), ("MP3", 44100, 2, 128));
+
+        let result = run(check(&format!("{}/stream", server), 2, 5, 1, false, false));
+        assert_eq!(streams(&result).len(), 1);
+        assert_eq!(result.1.title, None);
+        assert_eq!(result.1.audio, None);
+
+        assert_eq!(errors(&run(check(&format!("{}/html", server), 2, 5, 1, false, false))), vec!["unknown content type text/html"]);
+        assert_eq!(errors(&run(check(&format!("{}/nocontenttype", server), 2, 5, 1, false, false))), vec!["Missing content-type in http header"]);
+        assert_eq!(errors(&run(check(&format!("{}/error", server), 2, 5, 1, false, false))), vec!["illegal http status code 500"]);
+        assert_eq!(errors(&run(check(&format!("{}/missing", server), 2, 5, 1, false, false))), vec!["illegal http status code 404"]);
+        assert_eq!(errors(&run(check("ftp://127.0.0.1/stream", 2, 5, 1, false, false))), vec!["unknown scheme"]);
+    }
+
+    #[test]
+    fn redirects() {
+        let server = serve(vec![
+            ("/stream", http_response("ICY 200 OK", &[("Content-Type", "audio/aac")], &[0; 100])),
+            ("/relative", http_response("HTTP/1.1 302 Found", &[("Location", "/stream")], b"")),
+            ("/first", http_response("HTTP/1.1 301 Moved Permanently", &[("Location", "relative")], b"")),
+            ("/loop", http_response("HTTP/1.1 302 Found", &[("Location", "/loop")], b"")),
+            ("/nolocation", http_response("HTTP/1.1 302 Found", &[], b"")),
+        ]);
+
+        let result = run(check(&format!("{}/first", server), 2, 5, 1, false, false));
+        let found = streams(&result);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].Url, format!("{}/stream", server));
+        assert_eq!(found[0].CodecAudio, "AAC");
+
+        // the stream is at depth 3
+        assert_eq!(streams(&run(check(&format!("{}/first", server), 2, 3, 1, false, false))).len(), 1);
+        assert_eq!(errors(&run(check(&format!("{}/first", server), 2, 2, 1, false, false))), vec!["max depth reached"]);
+        assert_eq!(errors(&run(check(&format!("{}/loop", server), 2, 5, 1, false, false))), vec!["max depth reached"]);
+        assert!(run(check(&format!("{}/nolocation", server), 2, 5, 1, false, false)).0.is_empty());
+    }
+
+    #[test]
+    fn playlists() {
+        let server = serve(vec![
+            ("/stream", http_response("ICY 200 OK", &[("Content-Type", "audio/mpeg")], &mp3_frames(2))),
+            ("/list.m3u", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/x-mpegurl")], b"#EXTM3U\n#EXTINF:-1,Broken\n/missing\n#EXTINF:-1,Working\nstream\n")),
+            ("/list.pls", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/x-scpls"), ("Content-Length", "60")], b"[playlist]\nNumberOfEntries=1\nFile1=/stream\nTitle1=Radio\n")),
+            ("/broken.m3u", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/x-mpegurl")], b"/missing\n/missing2\n")),
+            ("/empty.m3u", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/x-mpegurl")], b"#EXTM3U\n")),
+            ("/nested.m3u", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/x-mpegurl")], b"/list.pls\n")),
+        ]);
+
+        // playlist_decoder does not keep the order of the entries, the broken one may be checked first
+        let result = run(check(&format!("{}/list.m3u", server), 2, 5, 1, false, false));
+        assert!(errors(&result).iter().all(|err| err == "illegal http status code 404"));
+        assert_eq!(streams(&result).len(), 1);
+        assert_eq!(streams(&result)[0].Url, format!("{}/stream", server));
+
+        let result = run(check(&format!("{}/broken.m3u", server), 2, 5, 1, false, false));
+        assert_eq!(errors(&result), vec!["illegal http status code 404", "illegal http status code 404"]);
+
+        let result = run(check(&format!("{}/list.pls", server), 2, 5, 1, false, false));
+        assert_eq!(streams(&result).len(), 1);
+
+        let result = run(check(&format!("{}/nested.m3u", server), 2, 5, 1, false, false));
+        assert_eq!(streams(&result).len(), 1);
+        assert_eq!(errors(&run(check(&format!("{}/nested.m3u", server), 2, 2, 1, false, false))), vec!["max depth reached"]);
+
+        assert_eq!(errors(&run(check(&format!("{}/empty.m3u", server), 2, 5, 1, false, false))), vec!["Empty playlist"]);
+    }
+
+    #[test]
+    fn hls() {
+        let mut segment = id3_tag(&[(b"TPE1", "Artist"), (b"TIT2", "Title")]);
+        for _ in 0..10 {
+            segment.extend_from_slice(&[0xff, 0xf1, 0x50, 0x80, 0x2e, 0x7f, 0xfc]);
+            segment.extend(std::iter::repeat(0).take(364));
+        }
+        let master = b"#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.2\"\nmedia.m3u8\n";
+        let media = b"#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nold.aac\n#EXTINF:10,\nnew.aac\n";
+        let server = serve(vec![
+            ("/master.m3u8", http_response("HTTP/1.1 200 OK", &[("Content-Type", "application/vnd.apple.mpegurl"), ("Content-Length", &master.len().to_string())], master)),
+            ("/media.m3u8", http_response("HTTP/1.1 200 OK", &[("Content-Type", "application/vnd.apple.mpegurl"), ("Content-Length", &media.len().to_string())], media)),
+            ("/new.aac", http_response("HTTP/1.1 200 OK", &[("Content-Type", "audio/aac"), ("Content-Length", &segment.len().to_string())], &segment)),
+        ]);
+
+        let result = run(check(&format!("{}/master.m3u8", server), 2, 5, 1, true, true));
+        let found = streams(&result);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].Hls);
+        assert_eq!(found[0].CodecAudio, "AAC");
+        assert_eq!(found[0].Bitrate, 64);
+        assert_eq!(result.1.title, Some(String::from("Artist - Title")));
+        let audio = result.1.audio.unwrap();
+        assert_eq!((audio.codec.as_str(), audio.samplerate, audio.channels, audio.bitrate), ("AAC", 44100, 2, 128));
+
+        let result = run(check(&format!("{}/master.m3u8", server), 2, 5, 1, false, false));
+        assert_eq!(streams(&result).len(), 1);
+        assert_eq!(result.1.title, None);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::api::data::CheckLease;
+use crate::api::data::CheckLeaseRequest;
+use crate::api::data::CheckLeaseResult;
+use crate::check::check;
+
+use reqwest::blocking::Client;
+use reqwest::header::AUTHORIZATION;
+use reqwest::header::USER_AGENT;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+use tokio::runtime::Runtime;
+
+fn post<T, R>(client: &Client, url: &str, token: &str, useragent: &str, body: &T) -> Result<R, Box<dyn Error>>
+where
+    T: Serialize,
+    R: DeserializeOwned,
+{
+    trace!("{}", url);
+    let response = client
+        .post(url)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header(USER_AGENT, useragent)
+        .json(body)
+        .send()?
+        .error_for_status()?;
+    Ok(response.json()?)
+}
+
+/// Lease the next batch of stations from the coordinator, check them and post the results back,
+/// results that arrive after the lease expired are ignored by the coordinator
+pub fn check_lease(
+    client: &Client,
+    runtime: &mut Runtime,
+    coordinator: &str,
+    token: &str,
+    source: &str,
+    concurrency: usize,
+    stations_count: u32,
+    useragent: &str,
+    timeout: u64,
+    max_depth: u8,
+    retries: u8,
+    capture_title: bool,
+    deep_check: bool,
+) -> Result<u32, Box<dyn Error>> {
+    let coordinator = coordinator.trim_end_matches('/');
+    let lease_request = CheckLeaseRequest {
+        source: source.to_string(),
+        limit: stations_count,
+    };
+    let lease: CheckLease = post(client, &format!("{}/checkworker/lease", coordinator), token, useragent, &lease_request)?;
+    let checked_count = lease.stations.len() as u32;
+    if checked_count == 0 {
+        return Ok(0);
+    }
+    debug!("Leased {} stations for {} seconds (lease {})", checked_count, lease.timeout_seconds, lease.leaseuuid);
+
+    let urls = lease.stations.into_iter().map(|station| (station.stationuuid, station.url)).collect();
+    let checks = runtime.block_on(check::check_urls(urls, source, concurrency, timeout, max_depth, retries, capture_title, deep_check));
+    let result = CheckLeaseResult {
+        leaseuuid: lease.leaseuuid,
+        source: source.to_string(),
+        checks,
+    };
+    let _: serde_json::Value = post(client, &format!("{}/checkworker/results", coordinator), token, useragent, &result)?;
+    Ok(checked_count)
+}
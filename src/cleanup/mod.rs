@@ -1,15 +1,38 @@
 use crate::db::connect;
+use crate::db::DbConnection;
+use crate::health;
+use crate::normalize::TagNormalizer;
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
+/// Rewrite tags of all stations to their canonical form
+fn canonicalize_tags(conn: &mut Box<dyn DbConnection>, tag_normalizer: &TagNormalizer) -> Result<(), Box<dyn std::error::Error>> {
+    let list: Vec<(String, String)> = conn.get_distinct_tags()?
+        .drain(..)
+        .map(|tags| {
+            let tags_new = tag_normalizer.canonical_list(&tags);
+            (tags, tags_new)
+        })
+        .filter(|(tags, tags_new)| tags != tags_new)
+        .collect();
+    if list.len() > 0 {
+        debug!("Canonicalize tags of {} distinct tag lists", list.len());
+        conn.replace_tags(list)?;
+    }
+    Ok(())
+}
+
 fn do_cleanup(
     delete: bool,
     database_url: String,
+    tag_normalizer: &TagNormalizer,
     click_valid_timeout: u64,
     broken_stations_never_working_timeout: u64,
     broken_stations_timeout: u64,
     checks_timeout: u64,
     clicks_timeout: u64,
+    stats_history_timeout: u64,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut conn_new_style = connect(database_url)?;
 
@@ -27,6 +50,7 @@ fn do_cleanup(
         conn_new_style.delete_were_working(broken_stations_timeout)?;
         conn_new_style.delete_old_checks(checks_timeout)?;
         conn_new_style.delete_old_clicks(clicks_timeout)?;
+        conn_new_style.delete_old_stats_history(stats_history_timeout)?;
         conn_new_style.delete_removed_from_history()?;
     }
 
@@ -34,6 +58,17 @@ fn do_cleanup(
     conn_new_style.remove_unused_ip_infos_from_stationclicks(click_valid_timeout)?;
     conn_new_style.remove_illegal_icon_links()?;
     conn_new_style.calc_country_field()?;
+    canonicalize_tags(&mut conn_new_style, tag_normalizer)?;
+
+    conn_new_style.insert_stats_history(
+        stations_working,
+        stations_broken,
+        conn_new_style.get_tag_count()?,
+        conn_new_style.get_language_count()?,
+        conn_new_style.get_country_count()?,
+        conn_new_style.get_click_count_last_hour()?,
+        conn_new_style.get_check_count_last_hour()?,
+    )?;
 
     info!("STATS: {} Checks/Hour, {} Checks/Day, {} Working stations, {} Broken stations, {} to do, deletable {} + {}", checks_hour, checks_day, stations_working, stations_broken, stations_todo, stations_deletable_never_worked, stations_deletable_were_working);
     Ok(())
@@ -42,23 +77,30 @@ fn do_cleanup(
 pub fn start(
     database_url: String,
     delete: bool,
+    tag_synonyms: HashMap<String, Vec<String>>,
     pause_seconds: u64,
     clicks_valid_timeout: u64,
     broken_stations_never_working_timeout: u64,
     broken_stations_timeout: u64,
     checks_timeout: u64,
     clicks_timeout: u64,
+    stats_history_timeout: u64,
 ) {
+    let tag_normalizer = TagNormalizer::new(&tag_synonyms);
+    health::register("cleanup", pause_seconds);
     thread::spawn(move || loop {
         let result = do_cleanup(
             delete,
             database_url.clone(),
+            &tag_normalizer,
             clicks_valid_timeout,
             broken_stations_never_working_timeout,
             broken_stations_timeout,
             checks_timeout,
             clicks_timeout,
+            stats_history_timeout,
         );
+        health::report("cleanup", &result);
         if let Err(error) = result {
             error!("Error: {}", error);
         }
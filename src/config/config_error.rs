@@ -6,12 +6,14 @@ use std::fmt::Result;
 #[derive(Debug, Clone)]
 pub enum ConfigError {
     TypeError(String, String),
+    RoleError(String, String),
 }
 
 impl Display for ConfigError {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match *self {
             ConfigError::TypeError(ref field_name, ref field_value) => write!(f, "Value {} for field {} has wrong type", field_name, field_value),
+            ConfigError::RoleError(ref role, ref message) => write!(f, "Config of role {} is invalid: {}", role, message),
         }
     }
 }
@@ -1,6 +1,8 @@
 mod config_error;
 
 use clap::{App, Arg};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::time::Duration;
 use std::error::Error;
@@ -9,6 +11,40 @@ use humantime;
 
 pub use config_error::ConfigError;
 
+/// Config value that is not shown in the debug output of the config, which gets logged on start
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Compare in constant time, so that the time of the comparison does not tell how many bytes matched
+    pub fn matches(&self, value: &str) -> bool {
+        let secret = self.0.as_bytes();
+        let value = value.as_bytes();
+        if secret.len() != value.len() {
+            return false;
+        }
+        secret.iter().zip(value).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
 #[derive(Debug,Clone)]
 pub enum CacheType {
     None,
@@ -28,21 +64,71 @@ impl From<CacheType> for String {
     }
 }
 
+/// Part of the server that runs in a process, one process may run several roles
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Role {
+    Api,
+    Check,
+    Pull,
+    Cleanup,
+    Refresh,
+}
+
+impl From<Role> for String {
+    fn from(r: Role) -> Self {
+        match r {
+            Role::Api => String::from("api"),
+            Role::Check => String::from("check"),
+            Role::Pull => String::from("pull"),
+            Role::Cleanup => String::from("cleanup"),
+            Role::Refresh => String::from("refresh"),
+        }
+    }
+}
+
+/// How often stations are rechecked, depending on their popularity and state
+#[derive(Debug,Clone)]
+pub struct CheckPriority {
+    /// Recheck interval of working stations
+    pub interval: Duration,
+    /// Recheck interval of working stations with at least popular_clickcount clicks or popular_votes votes
+    pub interval_popular: Duration,
+    pub popular_clickcount: u32,
+    pub popular_votes: u32,
+    /// Recheck interval of stations that were working within broken_recent
+    pub interval_broken: Duration,
+    pub broken_recent: Duration,
+    /// The interval of stations that are broken for longer doubles with every backoff_step
+    /// they are broken, up to interval_max
+    pub backoff_step: Duration,
+    pub interval_max: Duration,
+}
+
 #[derive(Debug,Clone)]
 pub struct Config {
+    pub add_validate_stream: bool,
+    pub add_validate_timeout: Duration,
     pub allow_database_downgrade: bool,
     pub broken_stations_never_working_timeout: Duration,
     pub broken_stations_timeout: Duration,
+    pub check_coordinator: String,
+    pub check_coordinator_token: Secret,
+    pub check_lease_timeout: Duration,
+    pub check_on_demand_interval: Duration,
+    pub check_on_demand_per_hour: u32,
+    pub check_priority: CheckPriority,
     pub check_stations: u32,
     pub checks_timeout: Duration,
     pub click_valid_timeout: Duration,
     pub clicks_timeout: Duration,
     pub concurrency: usize,
-    pub connection_string: String,
+    pub connection_string: Secret,
+    pub deep_check: bool,
     pub delete: bool,
     pub enable_check: bool,
     pub favicon: bool,
     pub ignore_migration_errors: bool,
+    pub lenient_parameters: bool,
     pub listen_host: String,
     pub listen_port: i32,
     pub log_dir: String,
@@ -50,17 +136,26 @@ pub struct Config {
     pub log_json: bool,
     pub max_depth: u8,
     pub mirror_pull_interval: Duration,
+    pub now_playing: bool,
+    pub now_playing_cache: Duration,
+    pub now_playing_history: u32,
     pub pause: Duration,
     pub prometheus_exporter_prefix: String,
     pub prometheus_exporter: bool,
     pub retries: u8,
+    pub roles: Vec<Role>,
     pub server_url: String,
     pub servers_pull: Vec<String>,
+    pub similar_stations_interval: Duration,
+    /// canonical tag => list of aliases
+    pub tag_synonyms: HashMap<String, Vec<String>>,
     pub source: String,
     pub static_files_dir: String,
+    pub stats_history_timeout: Duration,
     pub tcp_timeout: Duration,
     pub threads: usize,
     pub update_caches_interval: Duration,
+    pub update_caches_full_interval: Duration,
     pub useragent: String,
     pub cache_type: CacheType,
     pub cache_url: String,
@@ -197,6 +292,131 @@ fn get_hosts_from_config(config: &toml::Value) -> Result<Vec<String>, Box<dyn Er
     Ok(list)
 }
 
+fn get_tag_synonyms_from_config(config: &toml::Value) -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
+    let mut map = HashMap::new();
+    let setting = config.get("tag-synonyms");
+    if let Some(setting) = setting {
+        let setting_decoded = setting.as_table().ok_or(Box::new(ConfigError::TypeError("tag-synonyms".into(), setting.to_string())))?;
+        for (canonical, aliases) in setting_decoded {
+            let aliases_decoded = aliases.as_array().ok_or(Box::new(ConfigError::TypeError(canonical.clone(), aliases.to_string())))?;
+            let mut list = vec![];
+            for alias in aliases_decoded {
+                let alias_str = alias.as_str().ok_or(Box::new(ConfigError::TypeError(canonical.clone(), alias.to_string())))?;
+                list.push(alias_str.to_string());
+            }
+            map.insert(canonical.clone(), list);
+        }
+    }
+    Ok(map)
+}
+
+fn get_check_priority_from_config(config: &toml::Value) -> Result<CheckPriority, Box<dyn Error>> {
+    let empty = toml::Value::Table(toml::value::Table::new());
+    let setting = config.get("check-priority").unwrap_or(&empty);
+    if !setting.is_table() {
+        return Err(Box::new(ConfigError::TypeError("check-priority".into(), setting.to_string())));
+    }
+    let get_duration = |name: &str, default_value: &str| -> Result<Duration, Box<dyn Error>> {
+        match setting.get(name) {
+            Some(value) => {
+                let value_str = value.as_str().ok_or(Box::new(ConfigError::TypeError(name.into(), value.to_string())))?;
+                Ok(value_str.parse::<humantime::Duration>()?.into())
+            }
+            None => Ok(default_value.parse::<humantime::Duration>()?.into()),
+        }
+    };
+    let get_number = |name: &str, default_value: u32| -> Result<u32, Box<dyn Error>> {
+        match setting.get(name) {
+            Some(value) => {
+                let value_int = value.as_integer().ok_or(Box::new(ConfigError::TypeError(name.into(), value.to_string())))?;
+                Ok(value_int as u32)
+            }
+            None => Ok(default_value),
+        }
+    };
+    Ok(CheckPriority {
+        interval: get_duration("interval", "24hours")?,
+        interval_popular: get_duration("interval-popular", "6hours")?,
+        popular_clickcount: get_number("popular-clickcount", 100)?,
+        popular_votes: get_number("popular-votes", 100)?,
+        interval_broken: get_duration("interval-broken", "2hours")?,
+        broken_recent: get_duration("broken-recent", "2days")?,
+        backoff_step: get_duration("backoff-step", "3days")?,
+        interval_max: get_duration("interval-max", "7days")?,
+    })
+}
+
+/// Decode comma separated roles, "all" starts every role like before roles existed,
+/// the check only if it is enabled with enable-check
+fn get_roles(role_str: &str, enable_check: bool) -> Result<Vec<Role>, Box<dyn Error>> {
+    let mut roles = vec![];
+    for name in role_str.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+        let mut new_roles = match name {
+            "api" => vec![Role::Api],
+            "check" => vec![Role::Check],
+            "pull" => vec![Role::Pull],
+            "cleanup" => vec![Role::Cleanup],
+            "refresh" => vec![Role::Refresh],
+            "all" if enable_check => vec![Role::Api, Role::Check, Role::Pull, Role::Cleanup, Role::Refresh],
+            "all" => vec![Role::Api, Role::Pull, Role::Cleanup, Role::Refresh],
+            _ => return Err(Box::new(ConfigError::TypeError("role".into(), "possible values are api,check,pull,cleanup,refresh,all".into()))),
+        };
+        new_roles.retain(|role| !roles.contains(role));
+        roles.append(&mut new_roles);
+    }
+    if roles.is_empty() {
+        return Err(Box::new(ConfigError::TypeError("role".into(), role_str.into())));
+    }
+    Ok(roles)
+}
+
+/// Every role checks the settings it needs, so a process with a single role
+/// does not start without doing anything
+fn validate_roles(config: &Config, role_str: &str) -> Result<(), Box<dyn Error>> {
+    // with "all" the optional roles may do nothing, as before roles existed
+    let explicit = !role_str.split(',').any(|name| name.trim() == "all");
+    let error = |role: Role, message: &str| Err(Box::new(ConfigError::RoleError(role.into(), message.into())) as Box<dyn Error>);
+    for role in config.roles.iter() {
+        match role {
+            Role::Api => {
+                if config.threads == 0 {
+                    return error(Role::Api, "threads has to be greater than 0");
+                }
+                if config.listen_port <= 0 || config.listen_port > 65535 {
+                    return error(Role::Api, "listen-port has to be between 1 and 65535");
+                }
+            }
+            Role::Check => {
+                if config.concurrency == 0 || config.check_stations == 0 {
+                    return error(Role::Check, "concurrency and stations have to be greater than 0");
+                }
+                if !config.check_coordinator.is_empty() && config.roles.len() > 1 {
+                    return error(Role::Check, "check-coordinator can only be used if check is the only role");
+                }
+            }
+            Role::Pull => {
+                if explicit && config.servers_pull.is_empty() {
+                    return error(Role::Pull, "no servers to pull from");
+                }
+            }
+            Role::Cleanup => {
+                if config.checks_timeout.as_secs() == 0 || config.clicks_timeout.as_secs() == 0 || config.stats_history_timeout.as_secs() == 0 {
+                    return error(Role::Cleanup, "checks-timeout, clicks-timeout and stats-history-timeout have to be greater than 0");
+                }
+            }
+            Role::Refresh => {
+                if explicit && config.update_caches_interval.as_secs() == 0 {
+                    return error(Role::Refresh, "update-caches-interval has to be greater than 0");
+                }
+            }
+        }
+    }
+    if !config.check_coordinator.is_empty() && !config.roles.contains(&Role::Check) {
+        return error(Role::Check, "check-coordinator is set, but the role check is not selected");
+    }
+    Ok(())
+}
+
 pub fn load_config() -> Result<Config, Box<dyn Error>> {
     let hostname_str: String = hostname::get().map(|os_string| os_string.to_string_lossy().into_owned()).unwrap_or("".to_string());
 
@@ -213,6 +433,13 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
                 .env("CONFIG_FILE")
                 .default_value("/etc/radiobrowser.toml")
                 .takes_value(true),
+        ).arg(
+            Arg::with_name("role")
+                .long("role")
+                .value_name("ROLE")
+                .help("comma separated roles of this process: api, check, pull, cleanup, refresh or all")
+                .env("ROLE")
+                .takes_value(true),
         ).arg(
             Arg::with_name("log-dir")
                 .short("l")
@@ -281,6 +508,90 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
                 .help("concurrent threads used by socket")
                 .env("THREADS")
                 .takes_value(true),
+        ).arg(
+            Arg::with_name("lenient-parameters")
+                .long("lenient-parameters")
+                .value_name("LENIENT_PARAMETERS")
+                .help("ignore unknown and malformed request parameters instead of answering with an error")
+                .env("LENIENT_PARAMETERS")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("add-validate-stream")
+                .long("add-validate-stream")
+                .value_name("ADD_VALIDATE_STREAM")
+                .help("check the stream of new stations before adding them and reject broken ones")
+                .env("ADD_VALIDATE_STREAM")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("add-validate-timeout")
+                .long("add-validate-timeout")
+                .value_name("ADD_VALIDATE_TIMEOUT")
+                .help("tcp timeout for the stream check of new stations")
+                .env("ADD_VALIDATE_TIMEOUT")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("check-on-demand-interval")
+                .long("check-on-demand-interval")
+                .value_name("CHECK_ON_DEMAND_INTERVAL")
+                .help("minimum time between two on demand checks of the same station")
+                .env("CHECK_ON_DEMAND_INTERVAL")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("check-on-demand-per-hour")
+                .long("check-on-demand-per-hour")
+                .value_name("CHECK_ON_DEMAND_PER_HOUR")
+                .help("on demand checks per hour allowed for one ip, 0 to disable")
+                .env("CHECK_ON_DEMAND_PER_HOUR")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("check-coordinator")
+                .long("check-coordinator")
+                .value_name("CHECK_COORDINATOR")
+                .help("url of a coordinator api server, if set this process only checks stations leased from it")
+                .env("CHECK_COORDINATOR")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("check-coordinator-token")
+                .long("check-coordinator-token")
+                .value_name("CHECK_COORDINATOR_TOKEN")
+                .help("shared secret of the coordinator and its check workers, empty disables the coordinator endpoints")
+                .env("CHECK_COORDINATOR_TOKEN")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("check-lease-timeout")
+                .long("check-lease-timeout")
+                .value_name("CHECK_LEASE_TIMEOUT")
+                .help("time a check worker has to post the results of leased stations")
+                .env("CHECK_LEASE_TIMEOUT")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("now-playing")
+                .long("now-playing")
+                .value_name("NOW_PLAYING")
+                .help("capture the now playing titles of streams on checks and enable the nowplaying endpoint")
+                .env("NOW_PLAYING")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("now-playing-cache")
+                .long("now-playing-cache")
+                .value_name("NOW_PLAYING_CACHE")
+                .help("time a now playing title is used before the stream is asked again")
+                .env("NOW_PLAYING_CACHE")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("now-playing-history")
+                .long("now-playing-history")
+                .value_name("NOW_PLAYING_HISTORY")
+                .help("count of the last titles that are stored for each station")
+                .env("NOW_PLAYING_HISTORY")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("deep-check")
+                .long("deep-check")
+                .value_name("DEEP_CHECK")
+                .help("read the first seconds of audio on checks to verify codec, sample rate, channels and bitrate")
+                .env("DEEP_CHECK")
+                .takes_value(true),
         ).arg(
             Arg::with_name("mirror")
                 .short("m")
@@ -297,6 +608,20 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
                 .help("update caches at an interval")
                 .env("UPDATE_CACHES_INTERVAL")
                 .takes_value(true),
+        ).arg(
+            Arg::with_name("update-caches-full-interval")
+                .long("update-caches-full-interval")
+                .value_name("UPDATE_CACHES_FULL_INTERVAL")
+                .help("rebuild caches completely at an interval, in between only changed items are updated")
+                .env("UPDATE_CACHES_FULL_INTERVAL")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("similar-stations-interval")
+                .long("similar-stations-interval")
+                .value_name("SIMILAR_STATIONS_INTERVAL")
+                .help("calculate similar stations at an interval, 0 to disable")
+                .env("SIMILAR_STATIONS_INTERVAL")
+                .takes_value(true),
         ).arg(
             Arg::with_name("mirror-pull-interval")
                 .short("q")
@@ -424,6 +749,14 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
                 .env("CLICKS_TIMEOUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("stats-history-timeout")
+                .long("stats_history_timeout")
+                .value_name("STATS_HISTORY_TIMEOUT")
+                .help("Hourly stats history is removed after this timespan.")
+                .env("STATS_HISTORY_TIMEOUT")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("max-depth")
                 .long("max_depth")
@@ -514,6 +847,7 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
         get_option_string(&matches, &config, "listen-host", String::from("127.0.0.1"))?;
     let listen_port: i32 = get_option_number(&matches, &config, "listen-port", 8080)? as i32;
 
+    let lenient_parameters: bool = get_option_bool(&matches, &config, "lenient-parameters", false)?;
     let prometheus_exporter: bool = get_option_bool(&matches, &config, "prometheus-exporter", true)?;
     let prometheus_exporter_prefix: String = get_option_string(&matches, &config, "prometheus-exporter-prefix", String::from("radio_browser_"))?;
 
@@ -526,6 +860,10 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
     let threads: usize = get_option_number(&matches, &config, "threads", 1)? as usize;
     let update_caches_interval =
         get_option_duration(&matches, &config, "update-caches-interval", String::from("2mins"))?;
+    let update_caches_full_interval =
+        get_option_duration(&matches, &config, "update-caches-full-interval", String::from("1hour"))?;
+    let similar_stations_interval =
+        get_option_duration(&matches, &config, "similar-stations-interval", String::from("6hours"))?;
     let mirror_pull_interval =
         get_option_duration(&matches, &config, "mirror-pull-interval", String::from("5mins"))?;
     let ignore_migration_errors: bool = get_option_bool(&matches, &config, "ignore-migration-errors", false)?;
@@ -536,6 +874,19 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
     let concurrency: usize = get_option_number(&matches, &config, "concurrency", 1)? as usize;
     let check_stations: u32 = get_option_number(&matches, &config, "stations", 10)? as u32;
     let enable_check: bool = get_option_bool(&matches, &config, "enable-check", false)?;
+    let add_validate_stream: bool = get_option_bool(&matches, &config, "add-validate-stream", false)?;
+    let add_validate_timeout = get_option_duration(&matches, &config, "add-validate-timeout", String::from("5secs"))?;
+    let check_on_demand_interval = get_option_duration(&matches, &config, "check-on-demand-interval", String::from("10mins"))?;
+    let check_on_demand_per_hour: u32 = get_option_number(&matches, &config, "check-on-demand-per-hour", 0)? as u32;
+    let check_coordinator: String = get_option_string(&matches, &config, "check-coordinator", String::from(""))?;
+    let check_coordinator_token: String = get_option_string(&matches, &config, "check-coordinator-token", String::from(""))?;
+    let check_lease_timeout = get_option_duration(&matches, &config, "check-lease-timeout", String::from("10mins"))?;
+    let now_playing: bool = get_option_bool(&matches, &config, "now-playing", false)?;
+    let now_playing_cache = get_option_duration(&matches, &config, "now-playing-cache", String::from("30secs"))?;
+    let now_playing_history: u32 = get_option_number(&matches, &config, "now-playing-history", 10)? as u32;
+    let deep_check: bool = get_option_bool(&matches, &config, "deep-check", false)?;
+    let role_str: String = get_option_string(&matches, &config, "role", String::from("all"))?;
+    let roles = get_roles(&role_str, enable_check)?;
     let delete: bool = get_option_bool(&matches, &config, "delete", false)?;
     let favicon: bool = get_option_bool(&matches, &config, "favicon", false)?;
     let pause = get_option_duration(&matches, &config, "pause", String::from("10secs"))?;
@@ -549,6 +900,7 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
     let broken_stations_timeout = get_option_duration(&matches, &config, "broken-stations-timeout", String::from("30days"))?;
     let checks_timeout = get_option_duration(&matches, &config, "checks-timeout", String::from("30days"))?;
     let clicks_timeout = get_option_duration(&matches, &config, "clicks-timeout", String::from("30days"))?;
+    let stats_history_timeout = get_option_duration(&matches, &config, "stats-history-timeout", String::from("1year"))?;
 
     let cache_type_str: String = get_option_string(&matches, &config, "cache-type", String::from("none"))?;
     let cache_url: String = get_option_string(&matches, &config, "cache-url", String::from(""))?;
@@ -572,20 +924,32 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
 
     let mut servers = get_hosts_from_config(&config)?;
     servers_pull.append(&mut servers);
-    Ok(Config {
+    let tag_synonyms = get_tag_synonyms_from_config(&config)?;
+    let check_priority = get_check_priority_from_config(&config)?;
+    let result = Config {
+        add_validate_stream,
+        add_validate_timeout,
         allow_database_downgrade,
         broken_stations_never_working_timeout,
         broken_stations_timeout,
+        check_coordinator,
+        check_coordinator_token: Secret::new(check_coordinator_token),
+        check_lease_timeout,
+        check_on_demand_interval,
+        check_on_demand_per_hour,
+        check_priority,
         check_stations,
         checks_timeout,
         click_valid_timeout,
         clicks_timeout,
         concurrency,
-        connection_string,
+        connection_string: Secret::new(connection_string),
+        deep_check,
         delete,
         enable_check,
         favicon,
         ignore_migration_errors,
+        lenient_parameters,
         listen_host,
         listen_port,
         log_dir,
@@ -593,20 +957,30 @@ pub fn load_config() -> Result<Config, Box<dyn Error>> {
         log_json,
         max_depth,
         mirror_pull_interval,
+        now_playing,
+        now_playing_cache,
+        now_playing_history,
         pause,
         prometheus_exporter_prefix,
         prometheus_exporter,
         retries,
+        roles,
         server_url,
         servers_pull,
+        similar_stations_interval,
+        tag_synonyms,
         source,
         static_files_dir,
+        stats_history_timeout,
         tcp_timeout,
         threads,
         update_caches_interval,
+        update_caches_full_interval,
         useragent,
         cache_type,
         cache_url,
         cache_ttl,
-    })
+    };
+    validate_roles(&result, &role_str)?;
+    Ok(result)
 }
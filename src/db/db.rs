@@ -7,6 +7,8 @@ use crate::db::models::StationCheckItem;
 use crate::db::models::StationCheckItemNew;
 use crate::db::models::StationChangeItemNew;
 use crate::db::models::StationHistoryItem;
+use crate::db::models::StatsHistoryItem;
+use crate::db::models::StationTitleItem;
 use crate::db::models::StationClickItem;
 use crate::db::MysqlConnection;
 use crate::db::DbError;
@@ -24,19 +26,33 @@ pub trait DbConnection {
     fn get_country_count(&self) -> Result<u64, Box<dyn Error>>;
     fn get_language_count(&self) -> Result<u64, Box<dyn Error>>;
     fn get_click_count_last_hour(&self) -> Result<u64, Box<dyn Error>>;
+    fn get_check_count_last_hour(&self) -> Result<u64, Box<dyn Error>>;
     fn get_click_count_last_day(&self) -> Result<u64, Box<dyn Error>>;
-    fn get_stations_to_check(&mut self, hours: u32, itemcount: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
+    fn get_stations_to_check(
+        &mut self,
+        interval: u64,
+        interval_popular: u64,
+        popular_clickcount: u32,
+        popular_votes: u32,
+        interval_broken: u64,
+        broken_recent: u64,
+        backoff_step: u64,
+        interval_max: u64,
+        itemcount: u32,
+    ) -> Result<Vec<StationItem>, Box<dyn Error>>;
     fn get_station_by_uuid(&self, id_str: &str) -> Result<Vec<StationItem>,Box<dyn Error>>;
     fn get_stations_by_uuid(&self, uuids: Vec<String>) -> Result<Vec<StationItem>,Box<dyn Error>>;
     fn get_stations_by_column_multiple(&self,column_name: &str,search: Option<String>,exact: bool,order: &str,reverse: bool,hidebroken: bool,offset: u32,limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
     fn get_stations_by_all(&self,order: &str,reverse: bool,hidebroken: bool,offset: u32,limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
     fn get_stations_advanced(
-        &self,name: Option<String>,name_exact: bool,country: Option<String>,country_exact: bool,countrycode: Option<String>,
-        state: Option<String>,state_exact: bool,language: Option<String>,
-        language_exact: bool,tag: Option<String>,tag_exact: bool,tag_list: Vec<String>,
-        codec: Option<String>,
-        bitrate_min: u32,bitrate_max: u32,order: &str,reverse: bool,hidebroken: bool,offset: u32,limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
-    fn get_changes(&self, stationuuid: Option<String>, changeuuid: Option<String>) -> Result<Vec<StationHistoryItem>, Box<dyn Error>>;
+        &self,name: Option<String>,name_exact: bool,country: Option<String>,country_exact: bool,countrycode_list: Vec<String>,
+        state_list: Vec<String>,state_exact: bool,language_list: Vec<String>,
+        language_exact: bool,languagecode_list: Vec<String>,tag: Option<String>,tag_exact: bool,tag_list: Vec<String>,tag_match_all: bool,tag_exclude: Vec<String>,
+        codec_list: Vec<String>,codec_exclude: Vec<String>,
+        bitrate_min: u32,bitrate_max: u32,
+        hls: Option<bool>,lastcheckok: Option<bool>,votes_min: u32,clickcount_min: u32,has_favicon: Option<bool>,lastcheck_age_max: u32,
+        order: &str,reverse: bool,hidebroken: bool,offset: u32,limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
+    fn get_changes(&self, stationuuid: Option<String>, changeuuid: Option<String>, newest_first: bool, limit: u32) -> Result<Vec<StationHistoryItem>, Box<dyn Error>>;
     
     fn add_station_opt(&self, name: Option<String>, url: Option<String>, homepage: Option<String>, favicon: Option<String>,
         countrycode: Option<String>, state: Option<String>, language: Option<String>, tags: Option<String>) -> Result<String, Box<dyn Error>>;
@@ -47,6 +63,15 @@ pub trait DbConnection {
     fn get_stations_topclick(&self, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
     fn get_stations_lastclick(&self, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
     fn get_stations_lastchange(&self, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
+    fn insert_stats_history(&self, stations: u64, stations_broken: u64, tags: u64, languages: u64, countries: u64, clicks_last_hour: u64, checks_last_hour: u64) -> Result<(), Box<dyn Error>>;
+    fn get_stats_history(&self, from: Option<String>, to: Option<String>, resolution: &str, countrycodes: Vec<String>) -> Result<Vec<StatsHistoryItem>, Box<dyn Error>>;
+    fn get_stations_trending(&self, countrycode: Option<String>, tag: Option<String>, hidebroken: bool, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
+    fn get_stations_similar(&self, stationuuid: &str, hidebroken: bool, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
+    fn get_station_clicks_hourly(&self, stationuuid: &str, hours: u32) -> Result<Vec<(String, u32)>, Box<dyn Error>>;
+    fn get_station_clicks_daily(&self, stationuuid: &str, days: u32) -> Result<Vec<(String, u32)>, Box<dyn Error>>;
+    fn get_station_check_sources(&self, stationuuid: &str) -> Result<Vec<(String, u32, u32)>, Box<dyn Error>>;
+    /// Working checks of the last days, oldest first, at most the latest limit checks
+    fn get_station_stream_history(&self, stationuuid: &str, days: u32, limit: u32) -> Result<Vec<(String, String, u32, bool)>, Box<dyn Error>>;
     fn get_stations_by_column(&self,column_name: &str,search: String,exact: bool,order: &str,reverse: bool,hidebroken: bool,offset: u32,limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>>;
 
     fn get_pull_server_lastid(&self, server: &str) -> Result<Option<String>, Box<dyn Error>>;
@@ -58,11 +83,12 @@ pub trait DbConnection {
 
     fn insert_station_by_change(&self, list_station_changes: &Vec<StationChangeItemNew>) -> Result<Vec<String>,Box<dyn std::error::Error>>;
 
-    fn get_extra(&self, table_name: &str, column_name: &str, search: Option<String>, order: String, reverse: bool, hidebroken: bool) -> Result<Vec<ExtraInfo>, Box<dyn Error>>;
-    fn get_1_n(&self, column: &str, search: Option<String>, order: String, reverse: bool, hidebroken: bool) -> Result<Vec<ExtraInfo>, Box<dyn Error>>;
+    fn get_extra(&self, table_name: &str, column_name: &str, search: Option<String>, order: String, reverse: bool, hidebroken: bool, offset: u32, limit: u32) -> Result<Vec<ExtraInfo>, Box<dyn Error>>;
+    /// Items of a cache table whose name is exactly one of names, in no particular order
+    fn get_extra_by_names(&self, table_name: &str, column_name: &str, names: &Vec<String>, hidebroken: bool) -> Result<Vec<ExtraInfo>, Box<dyn Error>>;
     fn get_states(&self, country: Option<String>, search: Option<String>, order: String, reverse: bool, hidebroken: bool) -> Result<Vec<State>, Box<dyn Error>>;
-    fn get_checks(&self, stationuuid: Option<String>, checkuuid: Option<String>, seconds: u32, include_history: bool) -> Result<Vec<StationCheckItem>, Box<dyn Error>>;
-    fn get_clicks(&self, stationuuid: Option<String>, clickuuid: Option<String>, seconds: u32) -> Result<Vec<StationClickItem>, Box<dyn Error>>;
+    fn get_checks(&self, stationuuid: Option<String>, checkuuid: Option<String>, seconds: u32, include_history: bool, limit: u32) -> Result<Vec<StationCheckItem>, Box<dyn Error>>;
+    fn get_clicks(&self, stationuuid: Option<String>, clickuuid: Option<String>, seconds: u32, limit: u32) -> Result<Vec<StationClickItem>, Box<dyn Error>>;
 
     fn insert_checks(&self, list: &Vec<StationCheckItemNew>) -> Result<HashSet<String>, Box<dyn std::error::Error>>;
     fn update_station_with_check_data(&self, list: &Vec<StationCheckItemNew>, local: bool) -> Result<(), Box<dyn Error>>;
@@ -73,20 +99,47 @@ pub trait DbConnection {
     fn delete_were_working(&mut self, seconds: u64) -> Result<(), Box<dyn Error>>;
     fn delete_old_checks(&mut self, seconds: u64) -> Result<(), Box<dyn Error>>;
     fn delete_old_clicks(&mut self, seconds: u64) -> Result<(), Box<dyn Error>>;
+    fn delete_old_stats_history(&mut self, seconds: u64) -> Result<(), Box<dyn Error>>;
     fn delete_removed_from_history(&mut self) -> Result<(), Box<dyn Error>>;
     fn remove_unused_ip_infos_from_stationclicks(&mut self, seconds: u64) -> Result<(), Box<dyn Error>>;
     fn remove_illegal_icon_links(&mut self) -> Result<(), Box<dyn Error>>;
     fn calc_country_field(&mut self) -> Result<(), Box<dyn Error>>;
+    fn get_distinct_tags(&self) -> Result<Vec<String>, Box<dyn Error>>;
+    fn replace_tags(&mut self, list: Vec<(String, String)>) -> Result<(), Box<dyn Error>>;
+    fn get_distinct_languages(&self) -> Result<Vec<(String, String)>, Box<dyn Error>>;
+    fn update_language_codes(&self, list: Vec<(String, String)>) -> Result<(), Box<dyn Error>>;
     
     fn update_stations_clickcount(&self) -> Result<(), Box<dyn Error>>;
+    /// Latest clicks with known ip of the last seconds as (ip, stationuuid, unix timestamp), at most limit clicks
+    fn get_clicks_with_ip(&self, seconds: u64, limit: u32) -> Result<Vec<(String, String, i64)>, Box<dyn Error>>;
+    fn set_similar_stations(&self, list: Vec<(String, String, f64)>) -> Result<(), Box<dyn Error>>;
 
-    fn get_stations_multi_items(&self, column_name: &str) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>>;
+    /// Counts of the lower case values of a column with comma separated values, only of the given values if they are given
+    fn get_stations_multi_items(&self, column_name: &str, values: Option<&Vec<&String>>) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>>;
+    /// Counts of the values of a column, only of the values that equal value in lower case if it is given
+    fn get_stations_single_items(&self, column_name: &str, value: Option<&str>) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>>;
+    /// Counts of the pairs of state and country, only of the states that equal state in lower case if it is given
+    fn get_stations_state_items(&self, state: Option<&str>) -> Result<HashMap<(String, String), (u32,u32)>, Box<dyn Error>>;
     fn get_cached_items(&self, table_name: &str, column_name: &str) -> Result<HashMap<String, (u32, u32)>, Box<dyn Error>>;
     fn update_cache_item(&self, tag: &String, count: u32, count_working: u32, table_name: &str, column_name: &str) -> Result<(), Box<dyn Error>>;
     fn insert_to_cache(&self, tags: HashMap<&String, (u32,u32)>, table_name: &str, column_name: &str) -> Result<(), Box<dyn Error>>;
     fn remove_from_cache(&self, tags: Vec<&String>, table_name: &str, column_name: &str) -> Result<(), Box<dyn Error>>;
+    /// Queued (column name, value, version) items of the caches
+    fn get_cache_queue(&self) -> Result<Vec<(String, String, u32)>, Box<dyn Error>>;
+    fn remove_from_cache_queue(&self, items: &[(String, String, u32)]) -> Result<(), Box<dyn Error>>;
+    fn get_cached_states(&self) -> Result<HashMap<(String, String), (u32, u32)>, Box<dyn Error>>;
+    fn update_cache_state(&self, state: &(String, String), count: u32, count_working: u32) -> Result<(), Box<dyn Error>>;
+    fn insert_states_to_cache(&self, states: HashMap<&(String, String), (u32,u32)>) -> Result<(), Box<dyn Error>>;
+    fn remove_states_from_cache(&self, states: Vec<&(String, String)>) -> Result<(), Box<dyn Error>>;
 
     fn vote_for_station(&self, ip: &str, station: Option<StationItem>) -> Result<String, Box<dyn Error>>;
+    fn insert_check_request(&self, ip: &str, stationuuid: &str, request_type: &str, station_seconds: u64, ip_per_hour: u32) -> Result<(), Box<dyn Error>>;
+    fn insert_check_lease(&self, leaseuuid: &str, source: &str, stationuuids: &[String], seconds: u64) -> Result<(), Box<dyn Error>>;
+    fn get_check_lease_stations(&self, leaseuuid: &str, source: &str) -> Result<Vec<String>, Box<dyn Error>>;
+    fn delete_check_lease(&self, leaseuuid: &str) -> Result<(), Box<dyn Error>>;
+    fn insert_station_titles(&self, list: &[(String, String)], keep: u32) -> Result<(), Box<dyn Error>>;
+    fn get_station_now_playing(&self, stationuuid: &str) -> Result<Option<(StationTitleItem, u64)>, Box<dyn Error>>;
+    fn get_station_titles(&self, stationuuid: &str) -> Result<Vec<StationTitleItem>, Box<dyn Error>>;
     fn increase_clicks(&self, ip: &str, station: &StationItem, seconds: u64) -> Result<bool,Box<dyn Error>>;
     fn sync_votes(&self, list: Vec<Station>) -> Result<(), Box<dyn Error>>;
 }
@@ -9,6 +9,8 @@ pub enum DbError {
     VoteError(String),
     AddStationError(String),
     IllegalOrderError(String),
+    StationNotFoundError(String),
+    RateLimitError(String),
 }
 
 impl Display for DbError {
@@ -18,6 +20,8 @@ impl Display for DbError {
             DbError::VoteError(ref v) => write!(f, "VoteError '{}'", v),
             DbError::AddStationError(ref v) => write!(f, "AddStationError '{}'", v),
             DbError::IllegalOrderError(ref v) => write!(f, "IllegalOrderError '{}'", v),
+            DbError::StationNotFoundError(ref v) => write!(f, "StationNotFoundError '{}'", v),
+            DbError::RateLimitError(ref v) => write!(f, "RateLimitError '{}'", v),
         }
     }
 }
@@ -27,6 +27,12 @@ impl From<Row> for StationCheckItem {
             homepage:       row.take_opt("Homepage").transpose().unwrap_or(None),
             favicon:        row.take_opt("Favicon").transpose().unwrap_or(None),
             loadbalancer:   row.take_opt("Loadbalancer").transpose().unwrap_or(None),
+            failure_reason: row.take_opt("FailureReason").transpose().unwrap_or(None),
+            failure_message: row.take_opt("FailureMessage").transpose().unwrap_or(None),
+            audio_codec:    row.take_opt("AudioCodec").transpose().unwrap_or(None),
+            audio_bitrate:  row.take_opt("AudioBitrate").transpose().unwrap_or(None),
+            audio_samplerate: row.take_opt("AudioSampleRate").transpose().unwrap_or(None),
+            audio_channels: row.take_opt("AudioChannels").transpose().unwrap_or(None),
         }
     }
 }
@@ -50,6 +56,7 @@ impl From<Row> for StationItem {
             countrycode:        row.take_opt("CountryCode").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
             state:              row.take_opt("Subcountry").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
             language:           row.take_opt("Language").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
+            languagecodes:      row.take_opt("LanguageCodes").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
             votes:              row.take_opt("Votes").unwrap_or(Ok(0)).unwrap_or(0),
             lastchangetime:     row.take_opt("CreationFormated").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
             homepage:           row.take_opt("Homepage").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
@@ -59,6 +66,10 @@ impl From<Row> for StationItem {
             clicktimestamp:     row.take_opt("ClickTimestampFormated").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
             clickcount:         row.take_opt("clickcount").unwrap_or(Ok(0)).unwrap_or(0),
             clicktrend:         row.take_opt("ClickTrend").unwrap_or(Ok(0)).unwrap_or(0),
+            audio_codec:        row.take_opt("AudioCodec").unwrap_or(Ok("".to_string())).unwrap_or("".to_string()),
+            audio_bitrate:      row.take_opt("AudioBitrate").unwrap_or(Ok(0)).unwrap_or(0),
+            audio_samplerate:   row.take_opt("AudioSampleRate").unwrap_or(Ok(0)).unwrap_or(0),
+            audio_channels:     row.take_opt("AudioChannels").unwrap_or(Ok(0)).unwrap_or(0),
         }
     }
 }
@@ -394,5 +394,189 @@ r#"ALTER TABLE Station DROP COLUMN CountrySubdivisionCode;"#);
 r#"ALTER TABLE StationCheckHistory ADD COLUMN CountrySubdivisionCode VARCHAR(3) NULL;"#,
 r#"ALTER TABLE StationCheckHistory DROP COLUMN CountrySubdivisionCode;"#);
 
+    migrations.add_migration("20201205_120000_Add_Station_LanguageCodes",
+r#"ALTER TABLE Station ADD COLUMN LanguageCodes VARCHAR(50) NOT NULL DEFAULT '';"#,
+r#"ALTER TABLE Station DROP COLUMN LanguageCodes;"#);
+
+    migrations.add_migration("20201205_120500_CreateLanguageCodeCache",
+r#"CREATE TABLE `LanguageCodeCache` (
+`LanguageCode` varchar(150) NOT NULL,
+`StationCount` int(11) DEFAULT '0',
+`StationCountWorking` int(11) DEFAULT '0',
+PRIMARY KEY (`LanguageCode`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE LanguageCodeCache");
+
+    migrations.add_migration("20201206_100000_CreateCountryCache",
+r#"CREATE TABLE `CountryCache` (
+`CountryName` varchar(150) NOT NULL COLLATE utf8mb4_unicode_ci,
+`StationCount` int(11) DEFAULT '0',
+`StationCountWorking` int(11) DEFAULT '0',
+PRIMARY KEY (`CountryName`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE CountryCache");
+
+    migrations.add_migration("20201206_100100_CreateCountryCodeCache",
+r#"CREATE TABLE `CountryCodeCache` (
+`CountryCode` varchar(150) NOT NULL,
+`StationCount` int(11) DEFAULT '0',
+`StationCountWorking` int(11) DEFAULT '0',
+PRIMARY KEY (`CountryCode`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE CountryCodeCache");
+
+    migrations.add_migration("20201206_100200_CreateCodecCache",
+r#"CREATE TABLE `CodecCache` (
+`CodecName` varchar(150) NOT NULL,
+`StationCount` int(11) DEFAULT '0',
+`StationCountWorking` int(11) DEFAULT '0',
+PRIMARY KEY (`CodecName`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE CodecCache");
+
+    migrations.add_migration("20201206_100300_CreateStateCache",
+r#"CREATE TABLE `StateCache` (
+`StateName` varchar(150) NOT NULL COLLATE utf8mb4_unicode_ci,
+`CountryName` varchar(150) NOT NULL COLLATE utf8mb4_unicode_ci,
+`StationCount` int(11) DEFAULT '0',
+`StationCountWorking` int(11) DEFAULT '0',
+PRIMARY KEY (`StateName`, `CountryName`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StateCache");
+
+    migrations.add_migration("20201210_090000_CreateCacheQueue",
+r#"CREATE TABLE `CacheQueue` (
+`ColumnName` varchar(30) NOT NULL,
+`Value` varchar(150) NOT NULL,
+PRIMARY KEY (`ColumnName`, `Value`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE CacheQueue");
+
+    migrations.add_migration("20201214_200000_CreateStationSimilar",
+r#"CREATE TABLE `StationSimilar` (
+`StationUuid` char(36) NOT NULL,
+`SimilarStationUuid` char(36) NOT NULL,
+`Score` double NOT NULL DEFAULT '0',
+PRIMARY KEY (`StationUuid`, `SimilarStationUuid`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationSimilar");
+
+    migrations.add_migration("20201214_200500_Add_Index_StationClick_IP",
+r#"ALTER TABLE StationClick ADD INDEX IN_StationClick_IP(IP);"#,
+r#"ALTER TABLE StationClick DROP INDEX IN_StationClick_IP;"#);
+
+    migrations.add_migration("20201218_100000_CreateStationClickHourly",
+r#"CREATE TABLE `StationClickHourly` (
+`StationUuid` char(36) NOT NULL,
+`Hour` datetime NOT NULL,
+`Clicks` int(11) NOT NULL DEFAULT '0',
+PRIMARY KEY (`StationUuid`, `Hour`),
+KEY `IN_StationClickHourly_Hour` (`Hour`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationClickHourly");
+
+    migrations.add_migration("20201218_100100_Fill_StationClickHourly",
+r#"INSERT INTO StationClickHourly(StationUuid, Hour, Clicks)
+SELECT StationUuid, DATE_FORMAT(ClickTimestamp, '%Y-%m-%d %H:00:00'), COUNT(*) FROM StationClick
+GROUP BY StationUuid, DATE_FORMAT(ClickTimestamp, '%Y-%m-%d %H:00:00');"#,
+r#"DELETE FROM StationClickHourly;"#);
+
+    migrations.add_migration("20201218_100200_Add_Station_TrendScore",
+r#"ALTER TABLE Station ADD COLUMN TrendScore DOUBLE NOT NULL DEFAULT '0', ADD INDEX IN_Station_TrendScore(TrendScore);"#,
+r#"ALTER TABLE Station DROP INDEX IN_Station_TrendScore, DROP COLUMN TrendScore;"#);
+
+    migrations.add_migration("20201220_120000_CreateStatsHistory",
+r#"CREATE TABLE `StatsHistory` (
+`Time` datetime NOT NULL,
+`Stations` int(11) NOT NULL DEFAULT '0',
+`StationsBroken` int(11) NOT NULL DEFAULT '0',
+`Tags` int(11) NOT NULL DEFAULT '0',
+`Languages` int(11) NOT NULL DEFAULT '0',
+`Countries` int(11) NOT NULL DEFAULT '0',
+`ClicksLastHour` int(11) NOT NULL DEFAULT '0',
+`ChecksLastHour` int(11) NOT NULL DEFAULT '0',
+PRIMARY KEY (`Time`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StatsHistory");
+
+    migrations.add_migration("20201220_120100_CreateStatsHistoryCountry",
+r#"CREATE TABLE `StatsHistoryCountry` (
+`Time` datetime NOT NULL,
+`CountryCode` varchar(150) NOT NULL,
+`Stations` int(11) NOT NULL DEFAULT '0',
+`StationsWorking` int(11) NOT NULL DEFAULT '0',
+PRIMARY KEY (`Time`, `CountryCode`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StatsHistoryCountry");
+
+    migrations.add_migration("20201222_090000_Add_StationCheckHistory_FailureReason",
+r#"ALTER TABLE StationCheckHistory ADD COLUMN FailureReason varchar(50) DEFAULT NULL;"#,
+r#"ALTER TABLE StationCheckHistory DROP COLUMN FailureReason;"#);
+
+    migrations.add_migration("20201222_090100_Update_View_StationCheck_FailureReason",
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime,FailureReason FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#,
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#);
+
+    migrations.add_migration("20201223_100000_Add_StationCheckHistory_FailureMessage",
+r#"ALTER TABLE StationCheckHistory ADD COLUMN FailureMessage TEXT DEFAULT NULL;"#,
+r#"ALTER TABLE StationCheckHistory DROP COLUMN FailureMessage;"#);
+
+    migrations.add_migration("20201223_100100_Update_View_StationCheck_FailureMessage",
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime,FailureReason,FailureMessage FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#,
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime,FailureReason FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#);
+
+    migrations.add_migration("20201224_100000_CreateStationCheckRequest",
+r#"CREATE TABLE `StationCheckRequest` (
+`IP` varchar(50) NOT NULL,
+`StationUuid` char(36) NOT NULL,
+`RequestTime` datetime NOT NULL,
+KEY `IP` (`IP`),
+KEY `StationUuid` (`StationUuid`),
+KEY `RequestTime` (`RequestTime`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationCheckRequest");
+
+    migrations.add_migration("20201226_100000_CreateStationCheckLease",
+r#"CREATE TABLE `StationCheckLease` (
+`StationUuid` char(36) NOT NULL,
+`LeaseUuid` char(36) NOT NULL,
+`Source` varchar(100) NOT NULL,
+`ExpireTime` datetime NOT NULL,
+PRIMARY KEY (`StationUuid`),
+KEY `LeaseUuid` (`LeaseUuid`),
+KEY `ExpireTime` (`ExpireTime`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationCheckLease");
+
+    migrations.add_migration("20201227_100000_CreateStationNowPlaying",
+r#"CREATE TABLE `StationNowPlaying` (
+`StationUuid` char(36) NOT NULL,
+`Title` varchar(255) NOT NULL,
+`UpdateTime` datetime NOT NULL,
+PRIMARY KEY (`StationUuid`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationNowPlaying");
+
+    migrations.add_migration("20201227_100100_CreateStationTitleHistory",
+r#"CREATE TABLE `StationTitleHistory` (
+`TitleID` int(11) NOT NULL AUTO_INCREMENT,
+`StationUuid` char(36) NOT NULL,
+`Title` varchar(255) NOT NULL,
+`TitleTime` datetime NOT NULL,
+PRIMARY KEY (`TitleID`),
+KEY `StationUuid` (`StationUuid`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"#,"DROP TABLE StationTitleHistory");
+
+    migrations.add_migration("20201228_100000_Add_StationCheckHistory_Audio",
+r#"ALTER TABLE StationCheckHistory ADD COLUMN AudioCodec varchar(50) DEFAULT NULL, ADD COLUMN AudioBitrate int(11) DEFAULT NULL, ADD COLUMN AudioSampleRate int(11) DEFAULT NULL, ADD COLUMN AudioChannels int(11) DEFAULT NULL;"#,
+r#"ALTER TABLE StationCheckHistory DROP COLUMN AudioCodec, DROP COLUMN AudioBitrate, DROP COLUMN AudioSampleRate, DROP COLUMN AudioChannels;"#);
+
+    migrations.add_migration("20201228_100100_Update_View_StationCheck_Audio",
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime,FailureReason,FailureMessage,AudioCodec,AudioBitrate,AudioSampleRate,AudioChannels FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#,
+r#"CREATE OR REPLACE VIEW StationCheck AS SELECT CheckID,CheckUuid,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,CheckTime,UrlCache,MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime,FailureReason,FailureMessage FROM StationCheckHistory WHERE CheckID IN (select max(CheckID) FROM StationCheckHistory Group By StationUuid,Source);"#);
+
+    migrations.add_migration("20201228_100200_Add_Station_Audio",
+r#"ALTER TABLE Station ADD COLUMN AudioCodec varchar(50) NOT NULL DEFAULT '', ADD COLUMN AudioBitrate int(11) NOT NULL DEFAULT 0, ADD COLUMN AudioSampleRate int(11) NOT NULL DEFAULT 0, ADD COLUMN AudioChannels int(11) NOT NULL DEFAULT 0;"#,
+r#"ALTER TABLE Station DROP COLUMN AudioCodec, DROP COLUMN AudioBitrate, DROP COLUMN AudioSampleRate, DROP COLUMN AudioChannels;"#);
+
+    migrations.add_migration("20201229_100000_Add_CacheQueue_Version",
+r#"ALTER TABLE CacheQueue ADD COLUMN Version int(11) NOT NULL DEFAULT 0;"#,
+r#"ALTER TABLE CacheQueue DROP COLUMN Version;"#);
+
+    migrations.add_migration("20201230_100000_Add_StationCheckRequest_RequestType",
+r#"ALTER TABLE StationCheckRequest ADD COLUMN RequestType varchar(20) NOT NULL DEFAULT 'check';"#,
+r#"ALTER TABLE StationCheckRequest DROP COLUMN RequestType;"#);
+
+    migrations.add_migration("20201231_100000_Widen_Station_LanguageCodes",
+r#"ALTER TABLE Station MODIFY COLUMN LanguageCodes VARCHAR(255) NOT NULL DEFAULT '';"#,
+r#"ALTER TABLE Station MODIFY COLUMN LanguageCodes VARCHAR(50) NOT NULL DEFAULT '';"#);
+
     Ok(migrations)
 }
\ No newline at end of file
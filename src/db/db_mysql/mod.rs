@@ -8,8 +8,11 @@ use crate::db::db_error::DbError;
 
 use std;
 use std::collections::HashMap;
+use std::hash::Hash;
 
 use celes::Country;
+use crate::normalize::normalize_text;
+use crate::normalize::LANGUAGE_NORMALIZER;
 use crate::uuid::Uuid;
 use crate::db::models::State;
 use crate::db::models::ExtraInfo;
@@ -20,6 +23,9 @@ use crate::db::models::StationChangeItemNew;
 use crate::db::models::StationClickItem;
 use crate::db::models::StationClickItemNew;
 use crate::db::models::StationHistoryItem;
+use crate::db::models::StatsHistoryItem;
+use crate::db::models::StatsHistoryCountryItem;
+use crate::db::models::StationTitleItem;
 use crate::api::data::Station;
 use std::error::Error;
 use crate::db::DbConnection;
@@ -38,7 +44,7 @@ pub struct MysqlConnection {
 impl MysqlConnection {
     const COLUMNS: &'static str =
         "StationID,ChangeUuid,StationUuid,Name,Url,Homepage,Favicon,UrlCache,
-    Tags,Country,CountryCode,Subcountry,Language,Votes,
+    Tags,Country,CountryCode,Subcountry,Language,LanguageCodes,Votes,
     Date_Format(Creation,'%Y-%m-%d %H:%i:%s') AS CreationFormated,
     Codec,Bitrate,Hls,LastCheckOK,
     LastCheckTime,
@@ -48,7 +54,8 @@ impl MysqlConnection {
     Date_Format(LastLocalCheckTime,'%Y-%m-%d %H:%i:%s') AS LastLocalCheckTimeFormated,
     ClickTimestamp,
     Date_Format(ClickTimestamp,'%Y-%m-%d %H:%i:%s') AS ClickTimestampFormated,
-    clickcount,ClickTrend";
+    clickcount,ClickTrend,
+    AudioCodec,AudioBitrate,AudioSampleRate,AudioChannels";
 
     const COLUMNS_CHECK: &'static str =
         "CheckID, StationUuid, CheckUuid, Source, Codec, Bitrate, Hls, CheckOK,
@@ -57,7 +64,9 @@ impl MysqlConnection {
     UrlCache,
     MetainfoOverridesDatabase,Public,Name,
     Description,Tags,CountryCode,
-    Homepage,Favicon,Loadbalancer";
+    Homepage,Favicon,Loadbalancer,
+    FailureReason,FailureMessage,
+    AudioCodec,AudioBitrate,AudioSampleRate,AudioChannels";
 
     const COLUMNS_CLICK: &'static str =
         "ClickID, StationUuid, ClickUuid, IP,
@@ -112,6 +121,24 @@ impl MysqlConnection {
         return Ok(0);
     }
 
+    /// Requests of all types count to the limit per ip, the limit per station is separate for every type
+    fn insert_check_request_locked(conn: &mut mysql::PooledConn, ip: &str, stationuuid: &str, request_type: &str, station_seconds: u64, ip_per_hour: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let keep_seconds = station_seconds.max(3600);
+        conn.exec_drop("DELETE FROM StationCheckRequest WHERE RequestType=:request_type AND RequestTime < UTC_TIMESTAMP() - INTERVAL :keep_seconds SECOND", params!(request_type, keep_seconds))?;
+
+        let station_count: Option<u64> = conn.exec_first("SELECT COUNT(*) FROM StationCheckRequest WHERE StationUuid=:stationuuid AND RequestType=:request_type AND RequestTime > UTC_TIMESTAMP() - INTERVAL :station_seconds SECOND", params!(stationuuid, request_type, station_seconds))?;
+        if station_count.unwrap_or(0) > 0 {
+            return Err(Box::new(DbError::RateLimitError(format!("station was requested in the last {} seconds", station_seconds))));
+        }
+        let ip_count: Option<u64> = conn.exec_first("SELECT COUNT(*) FROM StationCheckRequest WHERE IP=:ip AND RequestTime > UTC_TIMESTAMP() - INTERVAL 1 HOUR", params!(ip))?;
+        if ip_count.unwrap_or(0) >= ip_per_hour as u64 {
+            return Err(Box::new(DbError::RateLimitError(format!("only {} on demand checks per hour are allowed", ip_per_hour))));
+        }
+
+        conn.exec_drop("INSERT INTO StationCheckRequest(IP,StationUuid,RequestType,RequestTime) VALUES(:ip,:stationuuid,:request_type,UTC_TIMESTAMP())", params!(ip, stationuuid, request_type))?;
+        Ok(())
+    }
+
     fn backup_stations_by_uuid(transaction: &mut mysql::Transaction<'_>, stationuuids: &Vec<String>) -> Result<(),Box<dyn std::error::Error>>{
         if stationuuids.len() > 0{
             let mut insert_params: Vec<Value> = vec![];
@@ -144,6 +171,75 @@ impl MysqlConnection {
         Ok(list_result)
     }
 
+    /// Half life of clicks in the trend score
+    const TREND_HALF_LIFE_HOURS: u32 = 6;
+    /// Timespan of clicks that the usual click rate of a station is calculated from
+    const TREND_BASELINE_HOURS: u32 = 7 * 24;
+
+    /// Columns of Station that have cache tables with comma separated values,
+    /// their values get queued for the refresh worker if stations change
+    const QUEUED_CACHE_COLUMNS: [&'static str; 3] = ["Tags", "Language", "LanguageCodes"];
+    /// Columns of Station that have cache tables with single values, queued like QUEUED_CACHE_COLUMNS
+    const QUEUED_SINGLE_CACHE_COLUMNS: [&'static str; 4] = ["Country", "CountryCode", "Codec", "Subcountry"];
+
+    /// Select the cached values of stations by uuid together with their LastCheckOK state
+    fn get_cache_items_by_uuid(transaction: &mut mysql::Transaction<'_>, stationuuids: &Vec<String>) -> Result<HashMap<String, (bool, HashSet<(&'static str, String)>)>,Box<dyn std::error::Error>>{
+        let mut stations = HashMap::new();
+        if stationuuids.len() > 0 {
+            let select_query: Vec<&str> = stationuuids.iter().map(|_| "?").collect();
+            let query = format!("SELECT StationUuid,LastCheckOK,{},{} FROM Station WHERE StationUuid IN ({})", MysqlConnection::QUEUED_CACHE_COLUMNS.join(","), MysqlConnection::QUEUED_SINGLE_CACHE_COLUMNS.join(","), select_query.join(","));
+            let result: Vec<Row> = transaction.exec(query, stationuuids)?;
+            for mut row in result {
+                let stationuuid: String = row.take_opt(0).unwrap_or(Ok(String::from(""))).unwrap_or(String::from(""));
+                let ok: bool = row.take_opt(1).unwrap_or(Ok(false)).unwrap_or(false);
+                let mut items: HashSet<(&str, String)> = HashSet::new();
+                for (i, column_name) in MysqlConnection::QUEUED_CACHE_COLUMNS.iter().enumerate() {
+                    let value: String = row.take_opt(2 + i).unwrap_or(Ok(String::from(""))).unwrap_or(String::from(""));
+                    for single_value in value.split(',') {
+                        items.insert((column_name, single_value.trim().to_lowercase()));
+                    }
+                }
+                for (i, column_name) in MysqlConnection::QUEUED_SINGLE_CACHE_COLUMNS.iter().enumerate() {
+                    let value: String = row.take_opt(2 + MysqlConnection::QUEUED_CACHE_COLUMNS.len() + i).unwrap_or(Ok(String::from(""))).unwrap_or(String::from(""));
+                    items.insert((column_name, value.trim().to_lowercase()));
+                }
+                stations.insert(stationuuid, (ok, items));
+            }
+        }
+        Ok(stations)
+    }
+
+    /// Queue the cached values of stations for an update of their counts
+    fn queue_cache_items_by_uuid(transaction: &mut mysql::Transaction<'_>, stationuuids: &Vec<String>) -> Result<(),Box<dyn std::error::Error>>{
+        let stations = MysqlConnection::get_cache_items_by_uuid(transaction, stationuuids)?;
+        if stations.len() > 0 {
+            let items: HashSet<(&str, String)> = stations.into_iter().flat_map(|(_, (_, items))| items).collect();
+            MysqlConnection::queue_cache_items(transaction, items)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the stations that match the condition and queue their cached values
+    fn delete_stations_where(&self, condition: &str, seconds: u64) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        let stationuuids: Vec<String> = transaction.exec(format!("SELECT StationUuid FROM Station WHERE {} FOR UPDATE", condition), params!(seconds))?;
+        if stationuuids.len() > 0 {
+            MysqlConnection::queue_cache_items_by_uuid(&mut transaction, &stationuuids)?;
+            let delete_query: Vec<&str> = stationuuids.iter().map(|_| "?").collect();
+            transaction.exec_drop(format!("DELETE FROM Station WHERE StationUuid IN ({})", delete_query.join(",")), &stationuuids)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Queue values for the refresh worker, values that are queued again while the worker
+    /// updates them get a new version, so that the worker does not remove them from the queue
+    fn queue_cache_items(transaction: &mut mysql::Transaction<'_>, items: HashSet<(&str, String)>) -> Result<(),Box<dyn std::error::Error>>{
+        let query = "INSERT INTO CacheQueue(ColumnName,Value) VALUES(?,?) ON DUPLICATE KEY UPDATE Version=Version+1";
+        transaction.exec_batch(query, items.iter().filter(|item| item.1 != "" && item.1.len() <= 150).map(|item| (item.0, &item.1)))?;
+        Ok(())
+    }
+
     fn insert_station_by_change_internal(transaction: &mut mysql::Transaction<'_>, stationchanges: &Vec<StationChangeItemNew>) -> Result<Vec<String>,Box<dyn std::error::Error>> {
         // filter out changes that already exist in the database
         let changeuuids: Vec<String> = stationchanges.iter().map(|item|item.changeuuid.clone()).collect();
@@ -163,7 +259,7 @@ impl MysqlConnection {
             let mut insert_query = vec![];
             let mut insert_params: Vec<Value> = vec![];
             for change in list {
-                insert_query.push("(?,?,?,?,?,?,?,?,?,?,?,'',UTC_TIMESTAMP())");
+                insert_query.push("(?,?,?,?,?,?,?,?,?,?,?,?,'',UTC_TIMESTAMP())");
                 insert_params.push(change.name.clone().into());
                 insert_params.push(change.url.clone().into());
                 insert_params.push(change.homepage.clone().into());
@@ -172,14 +268,16 @@ impl MysqlConnection {
                 insert_params.push(change.countrycode.clone().into());
                 insert_params.push(change.state.clone().into());
                 insert_params.push(fix_multi_field(&change.language).into());
+                insert_params.push(LANGUAGE_NORMALIZER.codes(&change.language).into());
                 insert_params.push(fix_multi_field(&change.tags).into());
                 insert_params.push(change.changeuuid.clone().into());
                 insert_params.push(change.stationuuid.clone().into());
                 list_ids.push(change.stationuuid.clone());
             }
-            let query = format!("INSERT INTO Station(Name,Url,Homepage,Favicon,Country,CountryCode,Subcountry,Language,Tags,ChangeUuid,StationUuid, UrlCache, Creation) 
+            let query = format!("INSERT INTO Station(Name,Url,Homepage,Favicon,Country,CountryCode,Subcountry,Language,LanguageCodes,Tags,ChangeUuid,StationUuid, UrlCache, Creation) 
                                     VALUES{}", insert_query.join(","));
             transaction.exec_drop(query, insert_params)?;
+            MysqlConnection::queue_cache_items_by_uuid(transaction, &list_ids)?;
         }
         Ok(list_ids)
     }
@@ -232,6 +330,43 @@ impl DbConnection for MysqlConnection {
         Ok(())
     }
 
+    fn get_distinct_tags(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<String> = conn.query("SELECT DISTINCT(Tags) FROM Station")?;
+        Ok(result)
+    }
+
+    fn replace_tags(&mut self, list: Vec<(String, String)>) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        // the rewrite does not create a station change, every mirror canonicalizes its own tags
+        // and mirrors with different synonyms would otherwise rewrite each others changes forever
+        let query_update = "UPDATE Station SET Tags=:tags_new WHERE Tags=:tags_old";
+        transaction.exec_batch(query_update, list.iter().map(|(tags_old, tags_new)| params!(tags_old, tags_new)))?;
+        let mut items: HashSet<(&str, String)> = HashSet::new();
+        for (tags_old, tags_new) in list.iter() {
+            for tag in tags_old.split(',').chain(tags_new.split(',')) {
+                items.insert(("Tags", tag.trim().to_lowercase()));
+            }
+        }
+        MysqlConnection::queue_cache_items(&mut transaction, items)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_distinct_languages(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, String)> = conn.query("SELECT DISTINCT Language, LanguageCodes FROM Station WHERE Language IS NOT NULL")?;
+        Ok(result)
+    }
+
+    fn update_language_codes(&self, list: Vec<(String, String)>) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        let query_update = "UPDATE Station SET LanguageCodes=:languagecodes WHERE Language=:language";
+        transaction.exec_batch(query_update, list.iter().map(|(language, languagecodes)| params!(language, languagecodes)))?;
+        transaction.commit()?;
+        Ok(())
+    }
+
     fn delete_old_checks(&mut self, seconds: u64) -> Result<(), Box<dyn Error>> {
         let delete_old_checks_history_query = "DELETE FROM StationCheckHistory WHERE CheckTime < UTC_TIMESTAMP() - INTERVAL :seconds SECOND";
         let mut conn = self.pool.get_conn()?;
@@ -243,6 +378,17 @@ impl DbConnection for MysqlConnection {
         let delete_old_clicks_query = "DELETE FROM StationClick WHERE ClickTimestamp < UTC_TIMESTAMP() - INTERVAL :seconds SECOND";
         let mut conn = self.pool.get_conn()?;
         conn.exec_drop(delete_old_clicks_query, params!(seconds))?;
+        // only delete buckets that contain no click newer than the timeout
+        let delete_old_buckets_query = "DELETE FROM StationClickHourly WHERE Hour < UTC_TIMESTAMP() - INTERVAL :seconds SECOND - INTERVAL 1 HOUR";
+        conn.exec_drop(delete_old_buckets_query, params!(seconds))?;
+        Ok(())
+    }
+
+    fn delete_old_stats_history(&mut self, seconds: u64) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        transaction.exec_drop("DELETE FROM StatsHistory WHERE Time < UTC_TIMESTAMP() - INTERVAL :seconds SECOND", params!(seconds))?;
+        transaction.exec_drop("DELETE FROM StatsHistoryCountry WHERE Time < UTC_TIMESTAMP() - INTERVAL :seconds SECOND", params!(seconds))?;
+        transaction.commit()?;
         Ok(())
     }
 
@@ -254,17 +400,11 @@ impl DbConnection for MysqlConnection {
     }
 
     fn delete_never_working(&mut self, seconds: u64) -> Result<(), Box<dyn Error>> {
-        let delete_never_working_query = "DELETE FROM Station WHERE LastCheckOkTime IS NULL AND Creation < UTC_TIMESTAMP() - INTERVAL :seconds SECOND";
-        let mut conn = self.pool.get_conn()?;
-        conn.exec_drop(delete_never_working_query, params!(seconds))?;
-        Ok(())
+        self.delete_stations_where("LastCheckOkTime IS NULL AND Creation < UTC_TIMESTAMP() - INTERVAL :seconds SECOND", seconds)
     }
 
     fn delete_were_working(&mut self, seconds: u64) -> Result<(), Box<dyn Error>> {
-        let delete_were_working_query = "DELETE FROM Station WHERE LastCheckOK=0 AND LastCheckOkTime IS NOT NULL AND LastCheckOkTime < UTC_TIMESTAMP() - INTERVAL :seconds SECOND";
-        let mut conn = self.pool.get_conn()?;
-        conn.exec_drop(delete_were_working_query, params!(seconds))?;
-        Ok(())
+        self.delete_stations_where("LastCheckOK=0 AND LastCheckOkTime IS NOT NULL AND LastCheckOkTime < UTC_TIMESTAMP() - INTERVAL :seconds SECOND", seconds)
     }
 
     fn remove_unused_ip_infos_from_stationclicks(&mut self, seconds: u64) -> Result<(), Box<dyn Error>> {
@@ -283,19 +423,48 @@ impl DbConnection for MysqlConnection {
 
     fn update_stations_clickcount(&self) -> Result<(), Box<dyn Error>> {
         trace!("update_stations_clickcount() 1");
-        let query = "UPDATE Station st SET 
-        clickcount=IFNULL((SELECT COUNT(*) FROM StationClick sc WHERE st.StationUuid=sc.StationUuid),0),
-        ClickTrend=
-        (
-            (select count(*) from StationClick sc1 where sc1.StationUuid=st.StationUuid AND ClickTimestamp>DATE_SUB(UTC_TIMESTAMP(),INTERVAL 1 DAY) AND ClickTimestamp<=DATE_SUB(UTC_TIMESTAMP(),INTERVAL 0 DAY)) - 
-            (select count(*) from StationClick sc2 where sc2.StationUuid=st.StationUuid AND ClickTimestamp>DATE_SUB(UTC_TIMESTAMP(),INTERVAL 2 DAY) AND ClickTimestamp<=DATE_SUB(UTC_TIMESTAMP(),INTERVAL 1 DAY))
-        ),
-        ClickTimestamp=(SELECT Max(ClickTimestamp) FROM StationClick sc WHERE sc.StationUuid=st.StationUuid);";
-        self.pool.get_conn()?.query_drop(query)?;
+        // TrendScore compares the exponentially decayed clicks of the last hours with the
+        // value that would be expected from the average click rate of the baseline window
+        let query = "UPDATE Station st LEFT JOIN (
+            SELECT StationUuid,
+            SUM(Clicks) AS ClicksAll,
+            SUM(IF(Hour>UTC_TIMESTAMP() - INTERVAL 1 DAY, Clicks, 0)) AS ClicksDay,
+            SUM(IF(Hour>UTC_TIMESTAMP() - INTERVAL 2 DAY AND Hour<=UTC_TIMESTAMP() - INTERVAL 1 DAY, Clicks, 0)) AS ClicksDayBefore,
+            SUM(IF(Hour>UTC_TIMESTAMP() - INTERVAL :baseline_hours HOUR, Clicks * POW(0.5, TIMESTAMPDIFF(HOUR, Hour, UTC_TIMESTAMP()) / :half_life_hours), 0)) AS ClicksDecayed,
+            SUM(IF(Hour>UTC_TIMESTAMP() - INTERVAL :baseline_hours HOUR, Clicks, 0)) / :baseline_hours * :half_life_hours / LN(2) AS ClicksExpected
+            FROM StationClickHourly GROUP BY StationUuid
+        ) h ON h.StationUuid=st.StationUuid SET
+        st.clickcount=IFNULL(h.ClicksAll,0),
+        st.ClickTrend=IFNULL(h.ClicksDay,0) - IFNULL(h.ClicksDayBefore,0),
+        st.TrendScore=IFNULL((h.ClicksDecayed - h.ClicksExpected) / SQRT(h.ClicksExpected + 1),0);";
+        self.pool.get_conn()?.exec_drop(query, params!{
+            "baseline_hours" => MysqlConnection::TREND_BASELINE_HOURS,
+            "half_life_hours" => MysqlConnection::TREND_HALF_LIFE_HOURS,
+        })?;
         trace!("update_stations_clickcount() 2");
         Ok(())
     }
 
+    /// Count pairs of stations that were clicked by the same ip within seconds,
+    /// ips are only compared inside of the database and are never returned
+    fn get_clicks_with_ip(&self, seconds: u64, limit: u32) -> Result<Vec<(String, String, i64)>, Box<dyn Error>> {
+        let query = "SELECT IP, StationUuid, TIMESTAMPDIFF(SECOND, '1970-01-01', ClickTimestamp) FROM StationClick
+            WHERE IP IS NOT NULL AND StationUuid IS NOT NULL AND ClickTimestamp > UTC_TIMESTAMP() - INTERVAL :seconds SECOND
+            ORDER BY ClickTimestamp DESC LIMIT :limit";
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, String, i64)> = conn.exec(query, params!(seconds, limit))?;
+        Ok(result)
+    }
+
+    fn set_similar_stations(&self, list: Vec<(String, String, f64)>) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        transaction.query_drop("DELETE FROM StationSimilar")?;
+        let query = "INSERT INTO StationSimilar(StationUuid, SimilarStationUuid, Score) VALUES(?,?,?)";
+        transaction.exec_batch(query, list.iter().map(|(stationuuid, similar_stationuuid, score)| (stationuuid, similar_stationuuid, score)))?;
+        transaction.commit()?;
+        Ok(())
+    }
+
     fn get_station_count_broken(&self) -> Result<u64, Box<dyn Error>> {
         self.get_single_column_number("SELECT COUNT(*) AS Items FROM radio.Station WHERE LastCheckOK=0 OR LastCheckOK IS NULL")
     }
@@ -309,7 +478,7 @@ impl DbConnection for MysqlConnection {
     }
 
     fn get_country_count(&self) -> Result<u64, Box<dyn Error>> {
-        self.get_single_column_number(r#"SELECT COUNT(DISTINCT(Country)) AS StationCount FROM Station"#)
+        self.get_single_column_number(r#"SELECT COUNT(*) AS StationCount FROM CountryCache"#)
     }
 
     fn get_language_count(&self) -> Result<u64, Box<dyn Error>> {
@@ -320,6 +489,10 @@ impl DbConnection for MysqlConnection {
         self.get_single_column_number(r#"SELECT COUNT(*) FROM StationClick WHERE TIMESTAMPDIFF(MINUTE,ClickTimestamp,UTC_TIMESTAMP())<=60;"#)
     }
 
+    fn get_check_count_last_hour(&self) -> Result<u64, Box<dyn Error>> {
+        self.get_single_column_number(r#"SELECT COUNT(*) FROM StationCheckHistory WHERE CheckTime>UTC_TIMESTAMP() - INTERVAL 1 HOUR;"#)
+    }
+
     fn get_click_count_last_day(&self) -> Result<u64, Box<dyn Error>> {
         self.get_single_column_number(r#"SELECT COUNT(*) FROM StationClick WHERE TIMESTAMPDIFF(HOUR,ClickTimestamp,UTC_TIMESTAMP())<=24;"#)
     }
@@ -331,10 +504,43 @@ impl DbConnection for MysqlConnection {
         self.get_single_column_number_params("SELECT COUNT(*) AS Items FROM Station WHERE LastLocalCheckTime IS NULL OR LastLocalCheckTime < UTC_TIMESTAMP() - INTERVAL :hours HOUR", params!(hours))
     }
 
-    fn get_stations_to_check(&mut self, hours: u32, itemcount: u32) -> Result<Vec<StationItem>, Box<dyn Error>> {
-        let query = format!("SELECT {columns} FROM Station WHERE LastLocalCheckTime IS NULL OR LastLocalCheckTime < UTC_TIMESTAMP() - INTERVAL {interval} HOUR ORDER BY RAND() LIMIT {limit}", columns = MysqlConnection::COLUMNS, interval = hours, limit = itemcount);
+    fn get_stations_to_check(
+        &mut self,
+        interval: u64,
+        interval_popular: u64,
+        popular_clickcount: u32,
+        popular_votes: u32,
+        interval_broken: u64,
+        broken_recent: u64,
+        backoff_step: u64,
+        interval_max: u64,
+        itemcount: u32,
+    ) -> Result<Vec<StationItem>, Box<dyn Error>> {
+        // new stations first, then the stations that are the most overdue relative to their interval,
+        // stations that are leased to a check worker are skipped
+        let query = format!("SELECT {columns} FROM (
+            SELECT *,CASE
+                WHEN LastCheckOK=1 AND (clickcount>=:popular_clickcount OR Votes>=:popular_votes) THEN :interval_popular
+                WHEN LastCheckOK=1 THEN :interval
+                WHEN COALESCE(LastCheckOkTime,Creation) > UTC_TIMESTAMP() - INTERVAL :broken_recent SECOND THEN :interval_broken
+                ELSE LEAST(:interval_max, :interval * POW(2, LEAST(30, FLOOR(TIMESTAMPDIFF(SECOND,COALESCE(LastCheckOkTime,Creation),UTC_TIMESTAMP()) / :backoff_step))))
+            END AS CheckInterval FROM Station) st
+            WHERE (LastLocalCheckTime IS NULL OR LastLocalCheckTime <= UTC_TIMESTAMP() - INTERVAL CheckInterval SECOND)
+            AND StationUuid NOT IN (SELECT StationUuid FROM StationCheckLease WHERE ExpireTime > UTC_TIMESTAMP())
+            ORDER BY LastLocalCheckTime IS NULL DESC, TIMESTAMPDIFF(SECOND,LastLocalCheckTime,UTC_TIMESTAMP()) / CheckInterval DESC
+            LIMIT :limit", columns = MysqlConnection::COLUMNS);
         let mut conn = self.pool.get_conn()?;
-        let results = conn.exec_iter(query, ())?;
+        let results = conn.exec_iter(query, params!{
+            "interval" => interval,
+            "interval_popular" => interval_popular,
+            "popular_clickcount" => popular_clickcount,
+            "popular_votes" => popular_votes,
+            "interval_broken" => interval_broken,
+            "broken_recent" => broken_recent,
+            "backoff_step" => backoff_step.max(1),
+            "interval_max" => interval_max.max(1),
+            "limit" => itemcount,
+        })?;
         self.get_list_from_query_result(results)
     }
 
@@ -422,6 +628,150 @@ impl DbConnection for MysqlConnection {
         self.get_stations_query(query)
     }
 
+    fn insert_stats_history(&self, stations: u64, stations_broken: u64, tags: u64, languages: u64, countries: u64, clicks_last_hour: u64, checks_last_hour: u64) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        let time: Option<String> = transaction.query_first("SELECT Date_Format(UTC_TIMESTAMP(),'%Y-%m-%d %H:%i:%s')")?;
+        transaction.exec_drop("INSERT INTO StatsHistory(Time,Stations,StationsBroken,Tags,Languages,Countries,ClicksLastHour,ChecksLastHour)
+            VALUES(:time,:stations,:stations_broken,:tags,:languages,:countries,:clicks_last_hour,:checks_last_hour)", params!{
+            "time" => &time,
+            stations,
+            stations_broken,
+            tags,
+            languages,
+            countries,
+            clicks_last_hour,
+            checks_last_hour,
+        })?;
+        transaction.exec_drop("INSERT INTO StatsHistoryCountry(Time,CountryCode,Stations,StationsWorking)
+            SELECT :time,CountryCode,StationCount,StationCountWorking FROM CountryCodeCache", params!{"time" => &time})?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_stats_history(&self, from: Option<String>, to: Option<String>, resolution: &str, countrycodes: Vec<String>) -> Result<Vec<StatsHistoryItem>, Box<dyn Error>> {
+        let period = match resolution {
+            "hour" => "Date_Format(Time,'%Y-%m-%d %H:00:00')",
+            "week" => "Date_Format(Time - INTERVAL WEEKDAY(Time) DAY,'%Y-%m-%d 00:00:00')",
+            "month" => "Date_Format(Time,'%Y-%m-01 00:00:00')",
+            _ => "Date_Format(Time,'%Y-%m-%d 00:00:00')",
+        };
+        let time_filter = "Time>=IFNULL(:from,UTC_TIMESTAMP() - INTERVAL 30 DAY) AND Time<=IFNULL(:to,UTC_TIMESTAMP())";
+        let mut conn = self.pool.get_conn()?;
+
+        let query = format!("SELECT {period} AS Period,
+            CAST(ROUND(AVG(Stations)) AS UNSIGNED),CAST(ROUND(AVG(StationsBroken)) AS UNSIGNED),CAST(ROUND(AVG(Tags)) AS UNSIGNED),CAST(ROUND(AVG(Languages)) AS UNSIGNED),
+            CAST(ROUND(AVG(Countries)) AS UNSIGNED),CAST(ROUND(AVG(ClicksLastHour)) AS UNSIGNED),CAST(ROUND(AVG(ChecksLastHour)) AS UNSIGNED)
+            FROM StatsHistory WHERE {time_filter} GROUP BY Period ORDER BY Period", period = period, time_filter = time_filter);
+        let result: Vec<(String, u64, u64, u64, u64, u64, u64, u64)> = conn.exec(query, params!{"from" => &from, "to" => &to})?;
+
+        let mut country_params: Vec<Value> = vec![from.into(), to.into()];
+        let mut country_filter = String::new();
+        if countrycodes.len() > 0 {
+            let mut country_query = vec![];
+            for countrycode in countrycodes {
+                country_query.push("?");
+                country_params.push(countrycode.to_uppercase().into());
+            }
+            country_filter = format!(" AND CountryCode IN ({})", country_query.join(","));
+        }
+        let query = format!("SELECT {period} AS Period,CountryCode,CAST(ROUND(AVG(Stations)) AS UNSIGNED),CAST(ROUND(AVG(StationsWorking)) AS UNSIGNED)
+            FROM StatsHistoryCountry WHERE {time_filter}{country_filter} GROUP BY Period,CountryCode ORDER BY Period,CountryCode",
+            period = period, time_filter = time_filter.replace(":from", "?").replace(":to", "?"), country_filter = country_filter);
+        let mut countries_by_period: HashMap<String, Vec<StatsHistoryCountryItem>> = HashMap::new();
+        let result_countries: Vec<(String, String, u64, u64)> = conn.exec(query, country_params)?;
+        for (period, countrycode, stations, stations_working) in result_countries {
+            countries_by_period.entry(period).or_insert_with(Vec::new).push(StatsHistoryCountryItem {
+                countrycode,
+                stations,
+                stations_working,
+            });
+        }
+
+        Ok(result
+            .into_iter()
+            .map(|(time, stations, stations_broken, tags, languages, countries, clicks_last_hour, checks_last_hour)| StatsHistoryItem {
+                countrycodes: countries_by_period.remove(&time).unwrap_or_default(),
+                time,
+                stations,
+                stations_broken,
+                tags,
+                languages,
+                countries,
+                clicks_last_hour,
+                checks_last_hour,
+            })
+            .collect())
+    }
+
+    fn get_stations_trending(&self, countrycode: Option<String>, tag: Option<String>, hidebroken: bool, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>> {
+        let mut where_clauses = vec!["TrendScore>0"];
+        let mut params: Vec<Value> = vec![];
+        if hidebroken {
+            where_clauses.push("LastCheckOK=TRUE");
+        }
+        if let Some(countrycode) = countrycode {
+            where_clauses.push("CountryCode=?");
+            params.push(countrycode.into());
+        }
+        if let Some(tag) = tag {
+            where_clauses.push("(Tags=? OR Tags LIKE CONCAT('%,',?,',%') OR Tags LIKE CONCAT(?,',%') OR Tags LIKE CONCAT('%,',?))");
+            for _ in 0..4 {
+                params.push(tag.clone().into());
+            }
+        }
+        let query = format!("SELECT {columns} from Station WHERE {where_clauses} ORDER BY TrendScore DESC LIMIT {limit}",
+            columns = MysqlConnection::COLUMNS, where_clauses = where_clauses.join(" AND "), limit = limit);
+        let mut conn = self.pool.get_conn()?;
+        let results = conn.exec_iter(query, params)?;
+        self.get_list_from_query_result(results)
+    }
+
+    fn get_station_clicks_hourly(&self, stationuuid: &str, hours: u32) -> Result<Vec<(String, u32)>, Box<dyn Error>> {
+        let query = "SELECT Date_Format(Hour,'%Y-%m-%d %H:%i:%s'), Clicks FROM StationClickHourly
+            WHERE StationUuid=:stationuuid AND Hour>UTC_TIMESTAMP() - INTERVAL :hours HOUR ORDER BY Hour";
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, u32)> = conn.exec(query, params!(stationuuid, hours))?;
+        Ok(result)
+    }
+
+    fn get_station_clicks_daily(&self, stationuuid: &str, days: u32) -> Result<Vec<(String, u32)>, Box<dyn Error>> {
+        let query = "SELECT Date_Format(Hour,'%Y-%m-%d'), SUM(Clicks) FROM StationClickHourly
+            WHERE StationUuid=:stationuuid AND Hour>=UTC_DATE() - INTERVAL :days DAY GROUP BY Date_Format(Hour,'%Y-%m-%d') ORDER BY Date_Format(Hour,'%Y-%m-%d')";
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, u32)> = conn.exec(query, params!(stationuuid, days))?;
+        Ok(result)
+    }
+
+    fn get_station_check_sources(&self, stationuuid: &str) -> Result<Vec<(String, u32, u32)>, Box<dyn Error>> {
+        let query = "SELECT Source, COUNT(*), SUM(CheckOK) FROM StationCheckHistory WHERE StationUuid=:stationuuid GROUP BY Source ORDER BY Source";
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, u32, u32)> = conn.exec(query, params!(stationuuid))?;
+        Ok(result)
+    }
+
+    fn get_station_stream_history(&self, stationuuid: &str, days: u32, limit: u32) -> Result<Vec<(String, String, u32, bool)>, Box<dyn Error>> {
+        let query = "SELECT Date_Format(CheckTime,'%Y-%m-%d %H:%i:%s'), Codec, Bitrate, Hls FROM
+            (SELECT CheckTime, IFNULL(Codec,'') AS Codec, Bitrate, Hls FROM StationCheckHistory
+                WHERE StationUuid=:stationuuid AND CheckOK=TRUE AND CheckTime>=UTC_TIMESTAMP() - INTERVAL :days DAY ORDER BY CheckTime DESC LIMIT :limit) h
+            ORDER BY CheckTime";
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, String, u32, bool)> = conn.exec(query, params!(stationuuid, days, limit))?;
+        Ok(result)
+    }
+
+    fn get_stations_similar(&self, stationuuid: &str, hidebroken: bool, limit: u32) -> Result<Vec<StationItem>, Box<dyn Error>> {
+        let hidebroken_string = if hidebroken {
+            " WHERE LastCheckOK=TRUE"
+        } else {
+            ""
+        };
+        let query = format!("SELECT {columns} from Station JOIN (SELECT SimilarStationUuid, Score FROM StationSimilar WHERE StationUuid=:stationuuid) s ON s.SimilarStationUuid=Station.StationUuid {hidebroken} ORDER BY s.Score DESC LIMIT {limit}",
+            columns = MysqlConnection::COLUMNS, hidebroken = hidebroken_string, limit = limit);
+        let mut conn = self.pool.get_conn()?;
+        let results = conn.exec_iter(query, params!(stationuuid))?;
+        self.get_list_from_query_result(results)
+    }
+
     fn get_stations_by_column(
         &self,
         column_name: &str,
@@ -525,17 +875,27 @@ impl DbConnection for MysqlConnection {
         name_exact: bool,
         country: Option<String>,
         country_exact: bool,
-        countrycode: Option<String>,
-        state: Option<String>,
+        countrycode_list: Vec<String>,
+        state_list: Vec<String>,
         state_exact: bool,
-        language: Option<String>,
+        language_list: Vec<String>,
         language_exact: bool,
+        languagecode_list: Vec<String>,
         tag: Option<String>,
         tag_exact: bool,
         tag_list: Vec<String>,
-        codec: Option<String>,
+        tag_match_all: bool,
+        tag_exclude: Vec<String>,
+        codec_list: Vec<String>,
+        codec_exclude: Vec<String>,
         bitrate_min: u32,
         bitrate_max: u32,
+        hls: Option<bool>,
+        lastcheckok: Option<bool>,
+        votes_min: u32,
+        clickcount_min: u32,
+        has_favicon: Option<bool>,
+        lastcheck_age_max: u32,
         order: &str,
         reverse: bool,
         hidebroken: bool,
@@ -554,6 +914,24 @@ impl DbConnection for MysqlConnection {
             columns = MysqlConnection::COLUMNS
         );
         query.push_str(" Bitrate >= :bitrate_min AND Bitrate <= :bitrate_max");
+        if let Some(hls) = hls {
+            query.push_str(if hls { " AND Hls=TRUE" } else { " AND Hls=FALSE" });
+        }
+        if let Some(lastcheckok) = lastcheckok {
+            query.push_str(if lastcheckok { " AND LastCheckOK=TRUE" } else { " AND LastCheckOK=FALSE" });
+        }
+        if votes_min > 0 {
+            query.push_str(" AND Votes >= :votes_min");
+        }
+        if clickcount_min > 0 {
+            query.push_str(" AND clickcount >= :clickcount_min");
+        }
+        if let Some(has_favicon) = has_favicon {
+            query.push_str(if has_favicon { " AND IFNULL(Favicon,'')<>''" } else { " AND (Favicon IS NULL OR Favicon='')" });
+        }
+        if lastcheck_age_max > 0 {
+            query.push_str(" AND LastCheckTime >= UTC_TIMESTAMP() - INTERVAL :lastcheck_age_max SECOND");
+        }
         if name.is_some() {
             if name_exact {
                 query.push_str(" AND Name=:name");
@@ -568,23 +946,6 @@ impl DbConnection for MysqlConnection {
                 query.push_str(" AND Country LIKE CONCAT('%',:country,'%')");
             }
         }
-        if countrycode.is_some() {
-            query.push_str(" AND UPPER(CountryCode)=UPPER(:countrycode)");
-        }
-        if state.is_some() {
-            if state_exact {
-                query.push_str(" AND Subcountry=:state");
-            } else {
-                query.push_str(" AND Subcountry LIKE CONCAT('%',:state,'%')");
-            }
-        }
-        if language.is_some() {
-            if language_exact {
-                query.push_str(" AND ( Language=:language OR Language LIKE CONCAT('%,',:language,',%') OR Language LIKE CONCAT('%,',:language) OR Language LIKE CONCAT(:language,',%'))");
-            } else {
-                query.push_str(" AND Language LIKE CONCAT('%',:language,'%')");
-            }
-        }
         if tag.is_some() {
             if tag_exact {
                 query.push_str(" AND ( Tags=:tag OR Tags LIKE CONCAT('%,',:tag,',%') OR Tags LIKE CONCAT('%,',:tag) OR Tags LIKE CONCAT(:tag,',%'))");
@@ -592,30 +953,88 @@ impl DbConnection for MysqlConnection {
                 query.push_str(" AND Tags LIKE CONCAT('%',:tag,'%')");
             }
         }
-        if codec.is_some() {
-            query.push_str(" AND LOWER(Codec)=LOWER(:codec)");
-        }
         let mut params: Vec<(String,Value)> = vec!{
             (String::from("name"), Value::from(name.unwrap_or_default())),
             (String::from("country"), Value::from(country.unwrap_or_default())),
-            (String::from("countrycode"), Value::from(countrycode.unwrap_or_default())),
-            (String::from("state"), Value::from(state.unwrap_or_default())),
-            (String::from("language"), Value::from(language.unwrap_or_default())),
             (String::from("tag"), Value::from(tag.unwrap_or_default())),
-            (String::from("codec"), Value::from(codec.unwrap_or_default())),
             (String::from("bitrate_min"), Value::from(bitrate_min)),
             (String::from("bitrate_max"), Value::from(bitrate_max)),
+            (String::from("votes_min"), Value::from(votes_min)),
+            (String::from("clickcount_min"), Value::from(clickcount_min)),
+            (String::from("lastcheck_age_max"), Value::from(lastcheck_age_max)),
         };
-        let mut i = 0;
-        for tag in tag_list {
+
+        // multiple values of single value columns always match any of the values
+        let mut conditions: Vec<String> = vec![];
+        for (i, countrycode) in countrycode_list.into_iter().enumerate() {
+            conditions.push(format!("UPPER(CountryCode)=UPPER(:countrycode{i})", i=i));
+            params.push((format!("countrycode{i}",i=i), Value::from(countrycode)));
+        }
+        push_or_conditions(&mut query, conditions);
+
+        let mut conditions: Vec<String> = vec![];
+        for (i, state) in state_list.into_iter().enumerate() {
+            if state_exact {
+                conditions.push(format!("Subcountry=:state{i}", i=i));
+            } else {
+                conditions.push(format!("Subcountry LIKE CONCAT('%',:state{i},'%')", i=i));
+            }
+            params.push((format!("state{i}",i=i), Value::from(state)));
+        }
+        push_or_conditions(&mut query, conditions);
+
+        let mut conditions: Vec<String> = vec![];
+        for (i, language) in language_list.into_iter().enumerate() {
+            if language_exact {
+                conditions.push(format!("( Language=:language{i} OR Language LIKE CONCAT('%,',:language{i},',%') OR Language LIKE CONCAT('%,',:language{i}) OR Language LIKE CONCAT(:language{i},',%'))", i=i));
+            } else {
+                conditions.push(format!("Language LIKE CONCAT('%',:language{i},'%')", i=i));
+            }
+            params.push((format!("language{i}",i=i), Value::from(language)));
+        }
+        push_or_conditions(&mut query, conditions);
+
+        let mut conditions: Vec<String> = vec![];
+        for (i, languagecode) in languagecode_list.into_iter().enumerate() {
+            conditions.push(format!("( LanguageCodes=:languagecode{i} OR LanguageCodes LIKE CONCAT('%,',:languagecode{i},',%') OR LanguageCodes LIKE CONCAT('%,',:languagecode{i}) OR LanguageCodes LIKE CONCAT(:languagecode{i},',%'))", i=i));
+            params.push((format!("languagecode{i}",i=i), Value::from(languagecode.to_lowercase())));
+        }
+        push_or_conditions(&mut query, conditions);
+
+        let mut conditions: Vec<String> = vec![];
+        for (i, codec) in codec_list.into_iter().enumerate() {
+            conditions.push(format!("LOWER(Codec)=LOWER(:codec{i})", i=i));
+            params.push((format!("codec{i}",i=i), Value::from(codec)));
+        }
+        push_or_conditions(&mut query, conditions);
+
+        let mut conditions: Vec<String> = vec![];
+        for (i, tag) in tag_list.into_iter().enumerate() {
             if tag_exact {
-                query.push_str(&format!(" AND ( Tags=:tag{i} OR Tags LIKE CONCAT('%,',:tag{i},',%') OR Tags LIKE CONCAT('%,',:tag{i}) OR Tags LIKE CONCAT(:tag{i},',%'))",i=i));
+                conditions.push(format!("( Tags=:tag{i} OR Tags LIKE CONCAT('%,',:tag{i},',%') OR Tags LIKE CONCAT('%,',:tag{i}) OR Tags LIKE CONCAT(:tag{i},',%'))",i=i));
             } else {
-                query.push_str(&format!(" AND Tags LIKE CONCAT('%',:tag{i},'%')",i=i));
+                conditions.push(format!("Tags LIKE CONCAT('%',:tag{i},'%')",i=i));
             }
             params.push((format!("tag{i}",i=i), Value::from(tag)));
-            i += 1;
         }
+        if tag_match_all {
+            for condition in conditions {
+                query.push_str(&format!(" AND {}", condition));
+            }
+        } else {
+            push_or_conditions(&mut query, conditions);
+        }
+
+        // exclusions always compare exact values
+        for (i, tag) in tag_exclude.into_iter().enumerate() {
+            query.push_str(&format!(" AND NOT ( Tags=:tagexclude{i} OR Tags LIKE CONCAT('%,',:tagexclude{i},',%') OR Tags LIKE CONCAT('%,',:tagexclude{i}) OR Tags LIKE CONCAT(:tagexclude{i},',%'))",i=i));
+            params.push((format!("tagexclude{i}",i=i), Value::from(tag)));
+        }
+        for (i, codec) in codec_exclude.into_iter().enumerate() {
+            query.push_str(&format!(" AND LOWER(Codec)<>LOWER(:codecexclude{i})",i=i));
+            params.push((format!("codecexclude{i}",i=i), Value::from(codec)));
+        }
+
         query.push_str(&format!(
             " {hidebroken} ORDER BY {order} {reverse} LIMIT {offset},{limit}",
             order = order,
@@ -633,7 +1052,7 @@ impl DbConnection for MysqlConnection {
         self.get_list_from_query_result(results)
     }
 
-    fn get_changes(&self, stationuuid: Option<String>, changeuuid: Option<String>) -> Result<Vec<StationHistoryItem>, Box<dyn Error>> {
+    fn get_changes(&self, stationuuid: Option<String>, changeuuid: Option<String>, newest_first: bool, limit: u32) -> Result<Vec<StationHistoryItem>, Box<dyn Error>> {
         let changeuuid_str = if changeuuid.is_some() {
             " AND StationChangeID >= IFNULL((SELECT StationChangeID FROM StationHistory WHERE ChangeUuid=:changeuuid),0)
               AND StationChangeID <= (SELECT MAX(StationChangeID) FROM StationHistory WHERE Creation <= UTC_TIMESTAMP() - INTERVAL 60 SECOND)
@@ -647,7 +1066,8 @@ impl DbConnection for MysqlConnection {
         }else{
             ""
         };
-        
+        let order = if newest_first { "DESC" } else { "ASC" };
+
         let query: String = format!("SELECT StationChangeID,ChangeUuid,
                 StationUuid,Name,
                 Url,Homepage,
@@ -656,12 +1076,13 @@ impl DbConnection for MysqlConnection {
                 CountryCode,
                 Language,Votes,
                 Date_Format(Creation,'%Y-%m-%d %H:%i:%s') AS CreationFormated
-                from StationHistory WHERE 1=:mynumber {changeuuid_str} {stationuuid} ORDER BY StationChangeID ASC", changeuuid_str = changeuuid_str, stationuuid = stationuuid_str);
+                from StationHistory WHERE 1=:mynumber {changeuuid_str} {stationuuid} ORDER BY StationChangeID {order} LIMIT :limit", changeuuid_str = changeuuid_str, stationuuid = stationuuid_str, order = order);
         let mut conn = self.pool.get_conn()?;
         let results = conn.exec_iter(query, params! {
             "mynumber" => 1,
             "stationuuid" => stationuuid.unwrap_or(String::from("")),
-            "changeuuid" => changeuuid.unwrap_or(String::from(""))
+            "changeuuid" => changeuuid.unwrap_or(String::from("")),
+            limit
         })?;
         self.get_list_from_query_result(results)
     }
@@ -673,8 +1094,8 @@ impl DbConnection for MysqlConnection {
         let countrycode: String = countrycode.unwrap_or_default().to_uppercase();
         let country: String = Country::from_alpha2(&countrycode).map(|c| c.long_name).unwrap_or(String::from(""));
 
-        let query = format!("INSERT INTO Station(Name,Url,Homepage,Favicon,Country,CountryCode,Subcountry,Language,Tags,ChangeUuid,StationUuid, UrlCache,Creation) 
-                        VALUES(:name, :url, :homepage, :favicon, :country, :countrycode, :state, :language, :tags, :changeuuid, :stationuuid, '', UTC_TIMESTAMP())");
+        let query = format!("INSERT INTO Station(Name,Url,Homepage,Favicon,Country,CountryCode,Subcountry,Language,LanguageCodes,Tags,ChangeUuid,StationUuid, UrlCache,Creation) 
+                        VALUES(:name, :url, :homepage, :favicon, :country, :countrycode, :state, :language, :languagecodes, :tags, :changeuuid, :stationuuid, '', UTC_TIMESTAMP())");
 
         let name = name.ok_or(DbError::AddStationError(String::from("name is empty")))?;
         let url = url.ok_or(DbError::AddStationError(String::from("url is empty")))?;
@@ -687,6 +1108,7 @@ impl DbConnection for MysqlConnection {
             return Err(Box::new(DbError::AddStationError(String::from("name is longer than 400 chars"))));
         }
 
+        let language = language.unwrap_or_default();
         let stationuuid = Uuid::new_v4().to_hyphenated().to_string();
         let changeuuid = Uuid::new_v4().to_hyphenated().to_string();
         let params = params!{
@@ -697,7 +1119,8 @@ impl DbConnection for MysqlConnection {
             "country" => country,
             "countrycode" => countrycode,
             "state" => state.unwrap_or_default(),
-            "language" => fix_multi_field(&language.unwrap_or_default()),
+            "language" => fix_multi_field(&language),
+            "languagecodes" => LANGUAGE_NORMALIZER.codes(&language),
             "tags" => fix_multi_field(&tags.unwrap_or_default()),
             "changeuuid" => changeuuid,
             "stationuuid" => stationuuid.clone(),
@@ -705,6 +1128,7 @@ impl DbConnection for MysqlConnection {
 
         transaction.exec_drop(query, params)?;
         MysqlConnection::backup_stations_by_uuid(&mut transaction, &(vec![stationuuid.clone()]))?;
+        MysqlConnection::queue_cache_items_by_uuid(&mut transaction, &(vec![stationuuid.clone()]))?;
         transaction.commit()?;
 
         Ok(stationuuid)
@@ -902,12 +1326,12 @@ impl DbConnection for MysqlConnection {
                     // reuse checkuuid
                     match &item.timestamp {
                         Some(timestamp) => {
-                            insert_station_check_query.push("(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
+                            insert_station_check_query.push("(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
                             insert_station_check_params.push(checkuuid.into());
                             insert_station_check_params.push(timestamp.into());
                         }
                         None => {
-                            insert_station_check_query.push("(?,UTC_TIMESTAMP(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
+                            insert_station_check_query.push("(?,UTC_TIMESTAMP(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
                             insert_station_check_params.push(checkuuid.into());
                         }
                     }
@@ -916,11 +1340,11 @@ impl DbConnection for MysqlConnection {
                     // generate new checkuuid
                     match &item.timestamp {
                         Some(timestamp) => {
-                            insert_station_check_query.push("(UUID(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
+                            insert_station_check_query.push("(UUID(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
                             insert_station_check_params.push(timestamp.into());
                         }
                         None => {
-                            insert_station_check_query.push("(UUID(),UTC_TIMESTAMP(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
+                            insert_station_check_query.push("(UUID(),UTC_TIMESTAMP(),?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,UTC_TIMESTAMP())");
                         }
                     }
                     
@@ -948,6 +1372,12 @@ impl DbConnection for MysqlConnection {
             insert_station_check_params.push(item.homepage.clone().into());
             insert_station_check_params.push(item.favicon.clone().into());
             insert_station_check_params.push(item.loadbalancer.clone().into());
+            insert_station_check_params.push(item.failure_reason.clone().into());
+            insert_station_check_params.push(item.failure_message.clone().into());
+            insert_station_check_params.push(item.audio_codec.clone().into());
+            insert_station_check_params.push(item.audio_bitrate.into());
+            insert_station_check_params.push(item.audio_samplerate.into());
+            insert_station_check_params.push(item.audio_channels.into());
         }
 
         trace!("Ignored checks(no stations) for insert: {}", ignored_checks_no_station);
@@ -956,7 +1386,8 @@ impl DbConnection for MysqlConnection {
         if insert_station_check_query.len() > 0 {
             let insert_station_check_params_str = insert_station_check_query.join(",");
             let query_insert_station_check_history = format!("INSERT INTO StationCheckHistory(CheckUuid,CheckTime,StationUuid,Source,Codec,Bitrate,Hls,CheckOK,UrlCache,
-                MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,InsertTime) VALUES{}", insert_station_check_params_str);
+                MetainfoOverridesDatabase,Public,Name,Description,Tags,CountryCode,Homepage,Favicon,Loadbalancer,FailureReason,FailureMessage,
+                AudioCodec,AudioBitrate,AudioSampleRate,AudioChannels,InsertTime) VALUES{}", insert_station_check_params_str);
             transaction.exec_drop(query_insert_station_check_history, insert_station_check_params)?;
         }
 
@@ -980,6 +1411,9 @@ impl DbConnection for MysqlConnection {
             list_station_uuid_query.push("?");
         }
         let query_in = list_station_uuid_query.join(",");
+        let list_station_uuid_queue: Vec<String> = list.iter().map(|item| item.station_uuid.clone()).collect();
+        // values of deleted stations and old tags are only available before the update
+        let cache_items_before = MysqlConnection::get_cache_items_by_uuid(&mut transaction, &list_station_uuid_queue)?;
 
         let mut majority_vote: HashMap<String,bool> = HashMap::new();
         if list.len() > 0 {
@@ -1008,6 +1442,16 @@ impl DbConnection for MysqlConnection {
                     (String::from("vote"), Value::from(vote)),
                 };
 
+                // only deep checks know the audio format, other checks keep the last one
+                let mut query_audio = "";
+                if let Some(audio_codec) = &item.audio_codec {
+                    params.push((String::from("audio_codec"), audio_codec.into(),));
+                    params.push((String::from("audio_bitrate"), item.audio_bitrate.unwrap_or(0).into(),));
+                    params.push((String::from("audio_samplerate"), item.audio_samplerate.unwrap_or(0).into(),));
+                    params.push((String::from("audio_channels"), item.audio_channels.unwrap_or(0).into(),));
+                    query_audio = "AudioCodec=:audio_codec,AudioBitrate=:audio_bitrate,AudioSampleRate=:audio_samplerate,AudioChannels=:audio_channels,";
+                }
+
                 if item.metainfo_overrides_database {
                     let mut query = vec![];
                     let public = item.public.unwrap_or(true);
@@ -1041,7 +1485,7 @@ impl DbConnection for MysqlConnection {
                         }
 
                         if item.check_ok {
-                            let query_update_ok = format!("UPDATE Station SET LastCheckOkTime=UTC_TIMESTAMP(),LastCheckTime=UTC_TIMESTAMP(),Codec=:codec,Bitrate=:bitrate,Hls=:hls,UrlCache=:urlcache,{} WHERE StationUuid=:stationuuid", query.join(","));
+                            let query_update_ok = format!("UPDATE Station SET LastCheckOkTime=UTC_TIMESTAMP(),LastCheckTime=UTC_TIMESTAMP(),Codec=:codec,Bitrate=:bitrate,Hls=:hls,{}UrlCache=:urlcache,{} WHERE StationUuid=:stationuuid", query_audio, query.join(","));
                             transaction.exec_drop(query_update_ok, params)?;
                         }
                     }else{
@@ -1052,8 +1496,9 @@ impl DbConnection for MysqlConnection {
                     if item.check_ok {
                         params.push((String::from("urlcache"), item.url.clone().into(),));
 
-                        let query_update_ok = format!("UPDATE Station SET {lastlocalchecktime}LastCheckOkTime=UTC_TIMESTAMP(),LastCheckTime=UTC_TIMESTAMP(),Codec=:codec,Bitrate=:bitrate,Hls=:hls,UrlCache=:urlcache,LastCheckOk=:vote WHERE StationUuid=:stationuuid",
+                        let query_update_ok = format!("UPDATE Station SET {lastlocalchecktime}LastCheckOkTime=UTC_TIMESTAMP(),LastCheckTime=UTC_TIMESTAMP(),Codec=:codec,Bitrate=:bitrate,Hls=:hls,{audio}UrlCache=:urlcache,LastCheckOk=:vote WHERE StationUuid=:stationuuid",
                             lastlocalchecktime = if local {"LastLocalCheckTime=UTC_TIMESTAMP(),"} else {""},
+                            audio = query_audio,
                         );
                         transaction.exec_drop(query_update_ok, params)?;
                     }else{
@@ -1065,6 +1510,26 @@ impl DbConnection for MysqlConnection {
                 }
             }
         }
+        // most checks do not change the state of a station, only queue the values of stations
+        // that changed their state or cached values or that were deleted
+        let cache_items_after = MysqlConnection::get_cache_items_by_uuid(&mut transaction, &list_station_uuid_queue)?;
+        let mut changed_items: HashSet<(&str, String)> = HashSet::new();
+        for (stationuuid, before) in cache_items_before {
+            match cache_items_after.get(&stationuuid) {
+                Some(after) => {
+                    if before != *after {
+                        changed_items.extend(before.1);
+                        changed_items.extend(after.1.iter().cloned());
+                    }
+                }
+                None => {
+                    changed_items.extend(before.1);
+                }
+            }
+        }
+        if changed_items.len() > 0 {
+            MysqlConnection::queue_cache_items(&mut transaction, changed_items)?;
+        }
         transaction.commit()?;
 
         Ok(())
@@ -1114,6 +1579,9 @@ impl DbConnection for MysqlConnection {
 
         let mut insert_click_params: Vec<Value> = vec![];
         let mut insert_click_query = vec![];
+        let mut insert_bucket_params: Vec<Value> = vec![];
+        let mut insert_bucket_query = vec![];
+        let mut update_station_params: Vec<(String, String)> = vec![];
         let mut ignored_clicks = 0;
         for item in list {
             if !found_stationuuids.contains(&item.stationuuid) {
@@ -1126,6 +1594,12 @@ impl DbConnection for MysqlConnection {
                 insert_click_params.push(item.clicktimestamp.clone().into());
 
                 insert_click_query.push("(?,?,?,UTC_TIMESTAMP())");
+
+                insert_bucket_params.push(item.stationuuid.clone().into());
+                insert_bucket_params.push(item.clicktimestamp.clone().into());
+                insert_bucket_query.push("(?,DATE_FORMAT(?,'%Y-%m-%d %H:00:00'),1)");
+
+                update_station_params.push((item.stationuuid.clone(), item.clicktimestamp.clone()));
             }
         }
 
@@ -1134,6 +1608,15 @@ impl DbConnection for MysqlConnection {
         if insert_click_query.len() > 0 {
             let query = format!("INSERT INTO StationClick(ClickUuid, StationUuid, ClickTimestamp, InsertTime) VALUES{}", insert_click_query.join(","));
             transaction.exec_drop(query, insert_click_params)?;
+
+            let query = format!("INSERT INTO StationClickHourly(StationUuid, Hour, Clicks) VALUES{} ON DUPLICATE KEY UPDATE Clicks=Clicks+VALUES(Clicks)", insert_bucket_query.join(","));
+            transaction.exec_drop(query, insert_bucket_params)?;
+
+            transaction.exec_batch("UPDATE Station SET ClickTimestamp=:clicktimestamp WHERE StationUuid=:stationuuid AND (ClickTimestamp IS NULL OR ClickTimestamp<:clicktimestamp)",
+                update_station_params.iter().map(|(stationuuid, clicktimestamp)| params!{
+                    "stationuuid" => stationuuid,
+                    "clicktimestamp" => clicktimestamp,
+                }))?;
         }
 
         transaction.commit()?;
@@ -1141,7 +1624,7 @@ impl DbConnection for MysqlConnection {
         Ok(())
     }
 
-    fn get_checks(&self, stationuuid: Option<String>, checkuuid: Option<String>, seconds: u32, include_history: bool) -> Result<Vec<StationCheckItem>, Box<dyn Error>> {
+    fn get_checks(&self, stationuuid: Option<String>, checkuuid: Option<String>, seconds: u32, include_history: bool, limit: u32) -> Result<Vec<StationCheckItem>, Box<dyn Error>> {
         let table_name = if include_history { "StationCheckHistory" } else { "StationCheck" };
         let where_seconds = if seconds > 0 {
             format!(
@@ -1152,7 +1635,7 @@ impl DbConnection for MysqlConnection {
             String::from("")
         };
 
-        let mut query_params: Vec<(String, Value)> = vec!{(String::from("one"), Value::from(1))};
+        let mut query_params: Vec<(String, Value)> = vec!{(String::from("one"), Value::from(1)), (String::from("limit"), Value::from(limit))};
         let where_checkuuid_str = match checkuuid {
             Some(checkuuid) => {
                 query_params.push((String::from("checkuuid"), checkuuid.into(),));
@@ -1166,10 +1649,10 @@ impl DbConnection for MysqlConnection {
         let query = match stationuuid {
             Some(stationuuid) => {
                 query_params.push((String::from("stationuuid"), stationuuid.into(),));
-                format!("SELECT {columns} FROM {table_name} WHERE StationUuid=:stationuuid {where_checkuuid} {where_seconds} ORDER BY CheckID", columns = MysqlConnection::COLUMNS_CHECK, where_seconds = where_seconds, where_checkuuid = where_checkuuid_str, table_name = table_name)
+                format!("SELECT {columns} FROM {table_name} WHERE StationUuid=:stationuuid {where_checkuuid} {where_seconds} ORDER BY CheckID LIMIT :limit", columns = MysqlConnection::COLUMNS_CHECK, where_seconds = where_seconds, where_checkuuid = where_checkuuid_str, table_name = table_name)
             }
             None => {
-                format!("SELECT {columns} FROM {table_name} WHERE 1=:one {where_checkuuid} {where_seconds} ORDER BY CheckID", columns = MysqlConnection::COLUMNS_CHECK, where_seconds = where_seconds, where_checkuuid = where_checkuuid_str, table_name = table_name)
+                format!("SELECT {columns} FROM {table_name} WHERE 1=:one {where_checkuuid} {where_seconds} ORDER BY CheckID LIMIT :limit", columns = MysqlConnection::COLUMNS_CHECK, where_seconds = where_seconds, where_checkuuid = where_checkuuid_str, table_name = table_name)
             }
         };
 
@@ -1180,7 +1663,7 @@ impl DbConnection for MysqlConnection {
         self.get_list_from_query_result(results)
     }
 
-    fn get_clicks(&self, stationuuid: Option<String>, clickuuid: Option<String>, seconds: u32) -> Result<Vec<StationClickItem>, Box<dyn Error>> {
+    fn get_clicks(&self, stationuuid: Option<String>, clickuuid: Option<String>, seconds: u32, limit: u32) -> Result<Vec<StationClickItem>, Box<dyn Error>> {
         let where_seconds = if seconds > 0 {
             format!(
                 "AND TIMESTAMPDIFF(SECOND,ClickTimestamp,UTC_TIMESTAMP())<{seconds}",
@@ -1190,7 +1673,7 @@ impl DbConnection for MysqlConnection {
             String::from("")
         };
 
-        let mut query_params: Vec<(String, Value)> = vec!{(String::from("one"), Value::from(1))};
+        let mut query_params: Vec<(String, Value)> = vec!{(String::from("one"), Value::from(1)), (String::from("limit"), Value::from(limit.min(10000)))};
         let where_clickuuid_str = match clickuuid {
             Some(clickuuid) => {
                 query_params.push((String::from("clickuuid"), clickuuid.into(),));
@@ -1203,10 +1686,10 @@ impl DbConnection for MysqlConnection {
         let query = match stationuuid {
             Some(stationuuid) => {
                 query_params.push((String::from("stationuuid"), stationuuid.into(),));
-                format!("SELECT {columns} FROM StationClick WHERE StationUuid=:stationuuid {where_clickuuid} {where_seconds} ORDER BY ClickID LIMIT 10000", columns = MysqlConnection::COLUMNS_CLICK, where_seconds = where_seconds, where_clickuuid = where_clickuuid_str)
+                format!("SELECT {columns} FROM StationClick WHERE StationUuid=:stationuuid {where_clickuuid} {where_seconds} ORDER BY ClickID LIMIT :limit", columns = MysqlConnection::COLUMNS_CLICK, where_seconds = where_seconds, where_clickuuid = where_clickuuid_str)
             }
             None => {
-                format!("SELECT {columns} FROM StationClick WHERE 1=:one {where_clickuuid} {where_seconds} ORDER BY ClickID LIMIT 10000", columns = MysqlConnection::COLUMNS_CLICK, where_seconds = where_seconds, where_clickuuid = where_clickuuid_str)
+                format!("SELECT {columns} FROM StationClick WHERE 1=:one {where_clickuuid} {where_seconds} ORDER BY ClickID LIMIT :limit", columns = MysqlConnection::COLUMNS_CLICK, where_seconds = where_seconds, where_clickuuid = where_clickuuid_str)
             }
         };
 
@@ -1225,6 +1708,8 @@ impl DbConnection for MysqlConnection {
         order: String,
         reverse: bool,
         hidebroken: bool,
+        offset: u32,
+        limit: u32,
     ) -> Result<Vec<ExtraInfo>, Box<dyn Error>> {
         let order = filter_order_1_n(&order)?;
         let mut params: Vec<Value> = Vec::with_capacity(1);
@@ -1238,12 +1723,12 @@ impl DbConnection for MysqlConnection {
         let search_string = match search {
             Some(c) => {
                 params.push((format!("%{}%", c)).into());
-                format!(" AND {} LIKE ?", column_name)
+                format!(" AND UPPER({}) LIKE UPPER(?)", column_name)
             }
             None => "".to_string(),
         };
         let mut conn = self.pool.get_conn()?;
-        let result = conn.exec_iter(format!("SELECT {column_name} AS name, {hidebroken} FROM {table_name} WHERE {column_name} <> '' {search} HAVING stationcount > 0 ORDER BY {order} {reverse}",search = search_string, order = order, reverse = reverse_string, hidebroken = hidebroken_string, table_name = table_name, column_name = column_name), params)?;
+        let result = conn.exec_iter(format!("SELECT {column_name} AS name, {hidebroken} FROM {table_name} WHERE {column_name} <> '' {search} HAVING stationcount > 0 ORDER BY {order} {reverse} LIMIT {offset},{limit}",search = search_string, offset = offset, limit = limit, order = order, reverse = reverse_string, hidebroken = hidebroken_string, table_name = table_name, column_name = column_name), params)?;
         for row in result {
             let mut mut_row = row?;
             items.push(ExtraInfo::new(
@@ -1254,41 +1739,27 @@ impl DbConnection for MysqlConnection {
         Ok(items)
     }
 
-    fn get_1_n(
-        &self,
-        column: &str,
-        search: Option<String>,
-        order: String,
-        reverse: bool,
-        hidebroken: bool,
-    ) -> Result<Vec<ExtraInfo>, Box<dyn Error>> {
-        let order = filter_order_1_n(&order)?;
-        let query: String;
-        let reverse_string = if reverse { "DESC" } else { "ASC" };
+    fn get_extra_by_names(&self, table_name: &str, column_name: &str, names: &Vec<String>, hidebroken: bool) -> Result<Vec<ExtraInfo>, Box<dyn Error>> {
+        let mut items = vec![];
+        if names.len() == 0 {
+            return Ok(items);
+        }
         let hidebroken_string = if hidebroken {
-            " AND LastCheckOK=TRUE"
+            "StationCountWorking as stationcount"
         } else {
-            ""
+            "StationCount as stationcount"
         };
+        let select_query: Vec<&str> = names.iter().map(|_| "?").collect();
         let mut conn = self.pool.get_conn()?;
-        let result = match search {
-            Some(value) => {
-                query = format!("SELECT {column} AS name,COUNT(*) AS stationcount FROM Station WHERE UPPER({column}) LIKE UPPER(CONCAT('%',?,'%')) AND {column}<>'' {hidebroken} GROUP BY {column} ORDER BY {order} {reverse}", column = column, order = order, reverse = reverse_string, hidebroken = hidebroken_string);
-                conn.exec_iter(query, (value,))
-            }
-            None => {
-                query = format!("SELECT {column} AS name,COUNT(*) AS stationcount FROM Station WHERE {column}<>'' {hidebroken} GROUP BY {column} ORDER BY {order} {reverse}", column = column, order = order, reverse = reverse_string, hidebroken = hidebroken_string);
-                conn.exec_iter(query, ())
-            }
-        }?;
-
-        let mut stations = vec!();
+        let result = conn.exec_iter(format!("SELECT {column_name} AS name, {hidebroken} FROM {table_name} WHERE {column_name} IN ({names}) HAVING stationcount > 0", names = select_query.join(","), hidebroken = hidebroken_string, table_name = table_name, column_name = column_name), names)?;
         for row in result {
-            let row = row?;
-            let (name, stationcount) = mysql::from_row_opt(row)?;
-            stations.push(ExtraInfo::new(name, stationcount));
+            let mut mut_row = row?;
+            items.push(ExtraInfo::new(
+                mut_row.take(0).unwrap_or("".into()),
+                mut_row.take(1).unwrap_or(0),
+            ));
         }
-        Ok(stations)
+        Ok(items)
     }
 
     fn get_states(
@@ -1299,30 +1770,31 @@ impl DbConnection for MysqlConnection {
         reverse: bool,
         hidebroken: bool,
     ) -> Result<Vec<State>, Box<dyn Error>> {
+        let order = filter_order_1_n(&order)?;
         let mut params: Vec<Value> = Vec::with_capacity(1);
         let reverse_string = if reverse { "DESC" } else { "ASC" };
         let hidebroken_string = if hidebroken {
-            " AND LastCheckOK=TRUE"
+            "StationCountWorking as stationcount"
         } else {
-            ""
+            "StationCount as stationcount"
         };
         let country_string = match country {
             Some(c) => {
                 params.push(c.into());
-                format!(" AND Country=?")
+                format!(" AND CountryName=?")
             }
             None => "".to_string(),
         };
         let search_string = match search {
             Some(c) => {
                 params.push((format!("%{}%", c)).into());
-                format!(" AND Subcountry LIKE ?")
+                format!(" AND StateName LIKE ?")
             }
             None => "".to_string(),
         };
 
         let mut conn = self.pool.get_conn()?;
-        let result = conn.exec_iter(format!(r"SELECT Subcountry AS name,Country,COUNT(*) AS stationcount FROM Station WHERE Subcountry <> '' {country} {search} {hidebroken} GROUP BY Subcountry, Country ORDER BY {order} {reverse}",hidebroken = hidebroken_string, order = order, country = country_string, reverse = reverse_string, search = search_string), params)?;
+        let result = conn.exec_iter(format!(r"SELECT StateName AS name,CountryName,{hidebroken} FROM StateCache WHERE StateName <> '' {country} {search} HAVING stationcount > 0 ORDER BY {order} {reverse}",hidebroken = hidebroken_string, order = order, country = country_string, reverse = reverse_string, search = search_string), params)?;
         let mut states: Vec<State> = vec![];
 
         for row in result {
@@ -1335,7 +1807,8 @@ impl DbConnection for MysqlConnection {
 
     /// Get items from a single column from Station table, add number of occurences
     /// Supports columns with multiple values that are split by komma
-    fn get_stations_multi_items(&self, column_name: &str) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>> {
+    fn get_stations_multi_items(&self, column_name: &str, values: Option<&Vec<&String>>) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>> {
+        let values: Option<HashSet<&str>> = values.map(|values| values.iter().map(|value| value.as_str()).collect());
         let mut items = HashMap::new();
         let mut conn = self.pool.get_conn()?;
         let result = conn
@@ -1350,6 +1823,11 @@ impl DbConnection for MysqlConnection {
             for single_tag in tags_arr {
                 let single_tag_trimmed = single_tag.trim().to_lowercase();
                 if single_tag_trimmed != "" {
+                    if let Some(values) = &values {
+                        if !values.contains(single_tag_trimmed.as_str()) {
+                            continue;
+                        }
+                    }
                     let counter = items.entry(single_tag_trimmed).or_insert((0,0));
                     counter.0 += 1;
                     if ok{
@@ -1361,6 +1839,42 @@ impl DbConnection for MysqlConnection {
         Ok(items)
     }
 
+    /// Get items from a single column from Station table, add number of occurences
+    /// The values are counted as they are, without splitting, values that only differ in case are counted together
+    fn get_stations_single_items(&self, column_name: &str, value: Option<&str>) -> Result<HashMap<String, (u32,u32)>, Box<dyn Error>> {
+        let mut rows = vec![];
+        let mut conn = self.pool.get_conn()?;
+        let result = conn
+            .exec_iter(format!(
+                "SELECT {column_name}, COUNT(*), SUM(LastCheckOK) FROM Station WHERE {column_name} <> '' {value_filter} GROUP BY {column_name}",
+                column_name = column_name,
+                value_filter = if value.is_some() { format!("AND LOWER({})=:value", column_name) } else { String::from("") },
+            ), match value { Some(value) => params!(value), None => Params::Empty })?;
+
+        for row in result {
+            let (key, value, value_working): (String, u32, u32) = mysql::from_row_opt(row?)?;
+            rows.push((key, (value, value_working)));
+        }
+        Ok(sum_case_insensitive(rows, |key| key.trim().to_lowercase()))
+    }
+
+    /// Get all pairs of state and country from Station table, add number of occurences
+    /// Pairs that only differ in case are counted together
+    fn get_stations_state_items(&self, state: Option<&str>) -> Result<HashMap<(String, String), (u32,u32)>, Box<dyn Error>> {
+        let mut rows = vec![];
+        let mut conn = self.pool.get_conn()?;
+        let result = conn.exec_iter(format!(
+            "SELECT Subcountry, IFNULL(Country,''), COUNT(*), SUM(LastCheckOK) FROM Station WHERE Subcountry <> '' {} GROUP BY Subcountry, Country",
+            if state.is_some() { "AND LOWER(Subcountry)=:state" } else { "" },
+        ), match state { Some(state) => params!(state), None => Params::Empty })?;
+
+        for row in result {
+            let (state, country, value, value_working): (String, String, u32, u32) = mysql::from_row_opt(row?)?;
+            rows.push(((state, country), (value, value_working)));
+        }
+        Ok(sum_case_insensitive(rows, |(state, country)| (state.trim().to_lowercase(), country.trim().to_lowercase())))
+    }
+
     /// Get currently cached items from table
     fn get_cached_items(
         &self,
@@ -1378,8 +1892,7 @@ impl DbConnection for MysqlConnection {
 
         for row in result {
             let (key, value, value_working): (String, u32, u32) = mysql::from_row_opt(row?)?;
-            let lower = key.to_lowercase();
-            items.insert(lower, (value, value_working));
+            items.insert(key, (value, value_working));
         }
         Ok(items)
     }
@@ -1426,6 +1939,121 @@ impl DbConnection for MysqlConnection {
         Ok(())
     }
 
+    fn get_cache_queue(&self) -> Result<Vec<(String, String, u32)>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let items: Vec<(String, String, u32)> = conn.query("SELECT ColumnName, Value, Version FROM CacheQueue")?;
+        Ok(items)
+    }
+
+    /// Remove applied items from the queue, items that were queued again since they were read keep their place
+    fn remove_from_cache_queue(&self, items: &[(String, String, u32)]) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        transaction.exec_batch("DELETE FROM CacheQueue WHERE ColumnName=? AND Value=? AND Version=?", items.iter().map(|(column_name, value, version)| (column_name, value, version)))?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_cached_states(&self) -> Result<HashMap<(String, String), (u32, u32)>, Box<dyn Error>> {
+        let mut items = HashMap::new();
+        let mut conn = self.pool.get_conn()?;
+        let result = conn.exec_iter("SELECT StateName, CountryName, StationCount, StationCountWorking FROM StateCache", ())?;
+
+        for row in result {
+            let (state, country, value, value_working): (String, String, u32, u32) = mysql::from_row_opt(row?)?;
+            items.insert((state, country), (value, value_working));
+        }
+        Ok(items)
+    }
+
+    fn update_cache_state(&self, state: &(String, String), count: u32, count_working: u32) -> Result<(), Box<dyn Error>> {
+        let query = "UPDATE StateCache SET StationCount=?, StationCountWorking=? WHERE StateName=? AND CountryName=?";
+        self.pool.get_conn()?.exec_drop(query, (count, count_working, &state.0, &state.1))?;
+        Ok(())
+    }
+
+    fn insert_states_to_cache(&self, states: HashMap<&(String, String), (u32,u32)>) -> Result<(), Box<dyn Error>> {
+        let query = "INSERT INTO StateCache(StateName,CountryName,StationCount,StationCountWorking) VALUES(?,?,?,?)";
+        self.pool.get_conn()?.exec_batch(query, states.iter().map(|item| (&(item.0).0, &(item.0).1, (item.1).0, (item.1).1)))?;
+        Ok(())
+    }
+
+    fn remove_states_from_cache(&self, states: Vec<&(String, String)>) -> Result<(), Box<dyn Error>> {
+        let query = "DELETE FROM StateCache WHERE StateName=? AND CountryName=?";
+        self.pool.get_conn()?.exec_batch(query, states.iter().map(|item| (&item.0, &item.1)))?;
+        Ok(())
+    }
+
+    fn insert_check_request(&self, ip: &str, stationuuid: &str, request_type: &str, station_seconds: u64, ip_per_hour: u32) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        // counting and inserting is serialized over all api servers by a named lock,
+        // otherwise parallel requests would all pass the limits before one of them is inserted
+        let locked: Option<Option<i64>> = conn.query_first("SELECT GET_LOCK('StationCheckRequest',10)")?;
+        if locked != Some(Some(1)) {
+            return Err(Box::new(DbError::ConnectionError("could not lock check requests".to_string())));
+        }
+        let result = MysqlConnection::insert_check_request_locked(&mut conn, ip, stationuuid, request_type, station_seconds, ip_per_hour);
+        conn.query_drop("DO RELEASE_LOCK('StationCheckRequest')")?;
+        result
+    }
+
+    fn insert_check_lease(&self, leaseuuid: &str, source: &str, stationuuids: &[String], seconds: u64) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        transaction.exec_drop("DELETE FROM StationCheckLease WHERE ExpireTime <= UTC_TIMESTAMP()", ())?;
+        // stations that were leased in the meantime by another request are ignored by the primary key
+        transaction.exec_batch("INSERT IGNORE INTO StationCheckLease(StationUuid,LeaseUuid,Source,ExpireTime) VALUES(:stationuuid,:leaseuuid,:source,UTC_TIMESTAMP() + INTERVAL :seconds SECOND)",
+            stationuuids.iter().map(|stationuuid| params!(stationuuid, leaseuuid, source, seconds)))?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_check_lease_stations(&self, leaseuuid: &str, source: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<String> = conn.exec("SELECT StationUuid FROM StationCheckLease WHERE LeaseUuid=:leaseuuid AND Source=:source AND ExpireTime > UTC_TIMESTAMP()", params!(leaseuuid, source))?;
+        Ok(result)
+    }
+
+    fn delete_check_lease(&self, leaseuuid: &str) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        conn.exec_drop("DELETE FROM StationCheckLease WHERE LeaseUuid=:leaseuuid", params!(leaseuuid))?;
+        Ok(())
+    }
+
+    fn insert_station_titles(&self, list: &[(String, String)], keep: u32) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
+        for (stationuuid, title) in list {
+            let title: String = title.chars().take(255).collect();
+            transaction.exec_drop("INSERT INTO StationNowPlaying(StationUuid,Title,UpdateTime) VALUES(:stationuuid,:title,UTC_TIMESTAMP())
+                ON DUPLICATE KEY UPDATE Title=VALUES(Title),UpdateTime=VALUES(UpdateTime)", params!(stationuuid, "title" => &title))?;
+            if keep == 0 || title.is_empty() {
+                continue;
+            }
+            // every check sees the same title until it changes
+            let last_title: Option<String> = transaction.exec_first("SELECT Title FROM StationTitleHistory WHERE StationUuid=:stationuuid ORDER BY TitleID DESC LIMIT 1", params!(stationuuid))?;
+            if last_title.as_ref() == Some(&title) {
+                continue;
+            }
+            transaction.exec_drop("INSERT INTO StationTitleHistory(StationUuid,Title,TitleTime) VALUES(:stationuuid,:title,UTC_TIMESTAMP())", params!(stationuuid, "title" => &title))?;
+            transaction.exec_drop("DELETE FROM StationTitleHistory WHERE StationUuid=:stationuuid AND TitleID NOT IN
+                (SELECT TitleID FROM (SELECT TitleID FROM StationTitleHistory WHERE StationUuid=:stationuuid ORDER BY TitleID DESC LIMIT :keep) AS KeepTitles)", params!(stationuuid, keep))?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_station_now_playing(&self, stationuuid: &str) -> Result<Option<(StationTitleItem, u64)>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let result: Option<(String, String, u64)> = conn.exec_first("SELECT Title,Date_Format(UpdateTime,'%Y-%m-%d %H:%i:%s'),GREATEST(TIMESTAMPDIFF(SECOND,UpdateTime,UTC_TIMESTAMP()),0) FROM StationNowPlaying
+            WHERE StationUuid=:stationuuid", params!(stationuuid))?;
+        Ok(result.map(|(title, time, age)| (StationTitleItem { title, time }, age)))
+    }
+
+    fn get_station_titles(&self, stationuuid: &str) -> Result<Vec<StationTitleItem>, Box<dyn Error>> {
+        let mut conn = self.pool.get_conn()?;
+        let result: Vec<(String, String)> = conn.exec("SELECT Title,Date_Format(TitleTime,'%Y-%m-%d %H:%i:%s') FROM StationTitleHistory
+            WHERE StationUuid=:stationuuid ORDER BY TitleID DESC", params!(stationuuid))?;
+        Ok(result.into_iter().map(|(title, time)| StationTitleItem { title, time }).collect())
+    }
+
     fn vote_for_station(&self, ip: &str, station: Option<StationItem>) -> Result<String, Box<dyn Error>> {
         match station {
             Some(station) => {
@@ -1458,33 +2086,36 @@ impl DbConnection for MysqlConnection {
                 if result_4_update_votes == 1 {
                     Ok("voted for station successfully".to_string())
                 } else {
-                    Err(Box::new(DbError::VoteError("could not find station with matching id".to_string())))
+                    Err(Box::new(DbError::StationNotFoundError("could not find station with matching id".to_string())))
                 }
             }
-            _ => Err(Box::new(DbError::VoteError("could not find station with matching id".to_string()))),
+            _ => Err(Box::new(DbError::StationNotFoundError("could not find station with matching id".to_string()))),
         }
     }
 
     fn increase_clicks(&self, ip: &str, station: &StationItem, seconds: u64) -> Result<bool,Box<dyn std::error::Error>> {
-        let mut conn = self.pool.get_conn()?;
+        // the click and the hourly click count are written together or not at all
+        let mut transaction = self.pool.start_transaction(TxOpts::default())?;
         let query = "SELECT StationUuid, IP FROM StationClick WHERE StationUuid=:stationuuid AND IP=:ip AND TIME_TO_SEC(TIMEDIFF(UTC_TIMESTAMP(),ClickTimestamp))<:seconds";
-        let result = conn.exec_iter(query, params!{"stationuuid" => &station.stationuuid, ip, seconds})?;
-
-        for _ in result {
+        let result: Option<(String, String)> = transaction.exec_first(query, params!{"stationuuid" => &station.stationuuid, ip, seconds})?;
+        if result.is_some() {
             return Ok(false);
         }
 
         let query2 = "INSERT INTO StationClick(IP,StationUuid,ClickUuid,ClickTimestamp,InsertTime) VALUES(:ip,:stationuuid,UUID(),UTC_TIMESTAMP(),UTC_TIMESTAMP())";
-        let result2 = conn.exec_iter(query2, params!{"stationuuid" => &station.stationuuid, "ip" => ip})?.affected_rows();
+        let result2 = transaction.exec_iter(query2, params!{"stationuuid" => &station.stationuuid, "ip" => ip})?.affected_rows();
+        if result2 != 1 {
+            return Ok(false);
+        }
 
         let query3 = "UPDATE Station SET ClickTimestamp=UTC_TIMESTAMP() WHERE StationUuid=:stationuuid";
-        let result3 = conn.exec_iter(query3, params!{"stationuuid" => &station.stationuuid})?.affected_rows();
+        let result3 = transaction.exec_iter(query3, params!{"stationuuid" => &station.stationuuid})?.affected_rows();
 
-        if result2 == 1 && result3 == 1 {
-            return Ok(true);
-        } else {
-            return Ok(false);
-        }
+        let query4 = "INSERT INTO StationClickHourly(StationUuid,Hour,Clicks) VALUES(:stationuuid,DATE_FORMAT(UTC_TIMESTAMP(),'%Y-%m-%d %H:00:00'),1) ON DUPLICATE KEY UPDATE Clicks=Clicks+1";
+        transaction.exec_drop(query4, params!{"stationuuid" => &station.stationuuid})?;
+        transaction.commit()?;
+
+        Ok(result3 == 1)
     }
 
     fn sync_votes(&self, list: Vec<Station>) -> Result<(), Box<dyn Error>> {
@@ -1523,34 +2154,61 @@ impl DbConnection for MysqlConnection {
     }
 }
 
+/// Sum up the counts of keys that are equal after normalize, the keys of the cache tables are case insensitive,
+/// the smallest spelling is used for all of them so that it does not change between refreshes
+fn sum_case_insensitive<K, F>(mut rows: Vec<(K, (u32,u32))>, normalize: F) -> HashMap<K, (u32,u32)> where K: Eq + Hash + Ord + Clone, F: Fn(&K) -> K {
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut spellings: HashMap<K, K> = HashMap::new();
+    let mut items: HashMap<K, (u32,u32)> = HashMap::new();
+    for (key, (count, count_working)) in rows {
+        let spelling = spellings.entry(normalize(&key)).or_insert(key).clone();
+        let counter = items.entry(spelling).or_insert((0,0));
+        counter.0 += count;
+        counter.1 += count_working;
+    }
+    items
+}
+
 fn fix_multi_field(value: &str) -> String {
-    let values: Vec<String> = value.split(",").map(|v| v.trim().to_lowercase().to_string()).collect();
+    let values: Vec<String> = value.split(",").map(|v| normalize_text(v)).collect();
     values.join(",")
 }
 
-fn filter_order(order: &str) -> &str {
-    match order {
-        "name" => "Name",
-        "url" => "Url",
-        "homepage" => "Homepage",
-        "favicon" => "Favicon",
-        "tags" => "Tags",
-        "country" => "Country",
-        "state" => "Subcountry",
-        "language" => "Language",
-        "votes" => "Votes",
-        "codec" => "Codec",
-        "bitrate" => "Bitrate",
-        "lastcheckok" => "LastCheckOK",
-        "lastchecktime" => "LastCheckTime",
-        "clicktimestamp" => "ClickTimestamp",
-        "clickcount" => "clickcount",
-        "clicktrend" => "ClickTrend",
-        "random" => "RAND()",
-        _ => "Name",
+/// Append conditions to a where clause, the conditions are combined with OR
+fn push_or_conditions(query: &mut String, conditions: Vec<String>) {
+    if conditions.len() > 0 {
+        query.push_str(&format!(" AND ({})", conditions.join(" OR ")));
     }
 }
 
+/// Allowed values of the order parameter for lists of stations and the columns they order by
+pub const STATION_ORDERS: &[(&str, &str)] = &[
+    ("name", "Name"),
+    ("url", "Url"),
+    ("homepage", "Homepage"),
+    ("favicon", "Favicon"),
+    ("tags", "Tags"),
+    ("country", "Country"),
+    ("state", "Subcountry"),
+    ("language", "Language"),
+    ("votes", "Votes"),
+    ("codec", "Codec"),
+    ("bitrate", "Bitrate"),
+    ("lastcheckok", "LastCheckOK"),
+    ("lastchecktime", "LastCheckTime"),
+    ("clicktimestamp", "ClickTimestamp"),
+    ("clickcount", "clickcount"),
+    ("clicktrend", "ClickTrend"),
+    ("random", "RAND()"),
+];
+
+fn filter_order(order: &str) -> &'static str {
+    STATION_ORDERS.iter()
+        .find(|(name, _)| *name == order)
+        .map(|(_, column)| *column)
+        .unwrap_or("Name")
+}
+
 fn filter_order_1_n(order: &str) -> Result<&str, Box<dyn Error>> {
     match order {
         "name" => Ok("name"),
@@ -6,5 +6,6 @@ pub mod models;
 
 pub use self::db::DbConnection;
 pub use self::db_mysql::MysqlConnection;
+pub use self::db_mysql::STATION_ORDERS;
 pub use self::db_error::DbError;
 pub use self::db::connect;
\ No newline at end of file
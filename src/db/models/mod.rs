@@ -7,6 +7,8 @@ mod station_change_item_new;
 mod station_history_item;
 mod station_click_item;
 mod station_click_item_new;
+mod stats_history_item;
+mod station_title_item;
 
 pub use station_click_item::StationClickItem;
 pub use station_click_item_new::StationClickItemNew;
@@ -16,4 +18,7 @@ pub use station_check_item::StationCheckItem;
 pub use station_check_item_new::StationCheckItemNew;
 pub use station_item::StationItem;
 pub use extra_info::ExtraInfo;
-pub use state::State;
\ No newline at end of file
+pub use state::State;
+pub use stats_history_item::StatsHistoryItem;
+pub use stats_history_item::StatsHistoryCountryItem;
+pub use station_title_item::StationTitleItem;
\ No newline at end of file
@@ -21,4 +21,12 @@ pub struct StationCheckItem {
     pub homepage: Option<String>,
     pub favicon: Option<String>,
     pub loadbalancer: Option<String>,
+
+    pub failure_reason: Option<String>,
+    pub failure_message: Option<String>,
+
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<u32>,
+    pub audio_samplerate: Option<u32>,
+    pub audio_channels: Option<u8>,
 }
@@ -1,4 +1,4 @@
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Serialize,Deserialize)]
 pub struct StationCheckItemNew {
     pub checkuuid: Option<String>,
     pub station_uuid: String,
@@ -19,4 +19,24 @@ pub struct StationCheckItemNew {
     pub homepage: Option<String>,
     pub favicon: Option<String>,
     pub loadbalancer: Option<String>,
+
+    /// Why the check failed, None for successful checks
+    pub failure_reason: Option<String>,
+    pub failure_message: Option<String>,
+
+    /// Now playing title of the stream, only set by checks that capture titles,
+    /// an empty string if the stream did not send one
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Audio format that was read from the frames of the stream, only set by deep checks
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    /// Average bitrate of the frames in kbit/s
+    #[serde(default)]
+    pub audio_bitrate: Option<u32>,
+    #[serde(default)]
+    pub audio_samplerate: Option<u32>,
+    #[serde(default)]
+    pub audio_channels: Option<u8>,
 }
\ No newline at end of file
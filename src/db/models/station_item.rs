@@ -13,6 +13,7 @@ pub struct StationItem {
     pub countrycode: String,
     pub state: String,
     pub language: String,
+    pub languagecodes: String,
     pub votes: i32,
     pub lastchangetime: String,
     pub codec: String,
@@ -25,4 +26,8 @@ pub struct StationItem {
     pub clicktimestamp: String,
     pub clickcount: u32,
     pub clicktrend: i32,
+    pub audio_codec: String,
+    pub audio_bitrate: u32,
+    pub audio_samplerate: u32,
+    pub audio_channels: u32,
 }
\ No newline at end of file
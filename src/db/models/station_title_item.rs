@@ -0,0 +1,7 @@
+/// Now playing title of a station and the time it was seen,
+/// an empty title means that the stream did not send one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StationTitleItem {
+    pub title: String,
+    pub time: String,
+}
@@ -0,0 +1,50 @@
+#[derive(PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsHistoryCountryItem {
+    pub countrycode: String,
+    pub stations: u64,
+    pub stations_working: u64,
+}
+
+/// Snapshot of the server stats, averaged over the resolution of the request
+#[derive(PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsHistoryItem {
+    pub time: String,
+    pub stations: u64,
+    pub stations_broken: u64,
+    pub tags: u64,
+    pub languages: u64,
+    pub countries: u64,
+    pub clicks_last_hour: u64,
+    pub checks_last_hour: u64,
+    pub countrycodes: Vec<StatsHistoryCountryItem>,
+}
+
+impl StatsHistoryItem {
+    pub fn serialize_stats_history(entries: Vec<StatsHistoryItem>) -> std::io::Result<String> {
+        let mut xml = xml_writer::XmlWriter::new(Vec::new());
+        xml.begin_elem("result")?;
+        for entry in entries {
+            xml.begin_elem("stats")?;
+            xml.attr_esc("time", &entry.time)?;
+            xml.attr_esc("stations", &entry.stations.to_string())?;
+            xml.attr_esc("stations_broken", &entry.stations_broken.to_string())?;
+            xml.attr_esc("tags", &entry.tags.to_string())?;
+            xml.attr_esc("languages", &entry.languages.to_string())?;
+            xml.attr_esc("countries", &entry.countries.to_string())?;
+            xml.attr_esc("clicks_last_hour", &entry.clicks_last_hour.to_string())?;
+            xml.attr_esc("checks_last_hour", &entry.checks_last_hour.to_string())?;
+            for country in entry.countrycodes {
+                xml.begin_elem("countrycode")?;
+                xml.attr_esc("countrycode", &country.countrycode)?;
+                xml.attr_esc("stations", &country.stations.to_string())?;
+                xml.attr_esc("stations_working", &country.stations_working.to_string())?;
+                xml.end_elem()?;
+            }
+            xml.end_elem()?;
+        }
+        xml.end_elem()?;
+        xml.close()?;
+        xml.flush()?;
+        Ok(String::from_utf8(xml.into_inner()).unwrap_or("encoding error".to_string()))
+    }
+}
@@ -0,0 +1,129 @@
+use crate::api::data::Health;
+use crate::api::data::HealthWorker;
+use crate::api::prometheus_exporter;
+use crate::config::Role;
+
+use chrono::DateTime;
+use chrono::Utc;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+/// A worker is unhealthy if its last run failed or if it did not finish
+/// a run for this many intervals
+const MAX_MISSED_INTERVALS: i64 = 3;
+/// Workers with short intervals get at least this time in seconds for a run
+const MIN_MAX_AGE: i64 = 600;
+
+struct WorkerState {
+    name: &'static str,
+    interval: u64,
+    started: DateTime<Utc>,
+    last_run: Option<DateTime<Utc>>,
+    last_ok: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+impl WorkerState {
+    fn is_healthy(&self, now: DateTime<Utc>) -> bool {
+        let max_age = (self.interval as i64 * MAX_MISSED_INTERVALS).max(MIN_MAX_AGE);
+        let last_ok = self.last_ok.unwrap_or(self.started);
+        self.last_run == self.last_ok && (now - last_ok).num_seconds() <= max_age
+    }
+}
+
+static WORKERS: Mutex<Vec<WorkerState>> = Mutex::new(Vec::new());
+
+/// Register a worker that runs about every interval seconds, it is healthy until
+/// it misses its first runs
+pub fn register(name: &'static str, interval: u64) {
+    if let Ok(mut workers) = WORKERS.lock() {
+        workers.retain(|worker| worker.name != name);
+        workers.push(WorkerState {
+            name,
+            interval,
+            started: Utc::now(),
+            last_run: None,
+            last_ok: None,
+            last_error: None,
+        });
+    }
+}
+
+/// Store the result of a run of a registered worker
+pub fn report<T, E>(name: &'static str, result: &Result<T, E>) where E: Display {
+    if let Ok(mut workers) = WORKERS.lock() {
+        if let Some(worker) = workers.iter_mut().find(|worker| worker.name == name) {
+            let now = Utc::now();
+            worker.last_run = Some(now);
+            match result {
+                Ok(_) => worker.last_ok = Some(now),
+                Err(err) => worker.last_error = Some(err.to_string()),
+            }
+        }
+    }
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Health of all registered workers of the roles of this process
+pub fn get_health(roles: &[Role]) -> Health {
+    let now = Utc::now();
+    let workers: Vec<HealthWorker> = match WORKERS.lock() {
+        Ok(workers) => workers
+            .iter()
+            .map(|worker| HealthWorker {
+                name: worker.name.to_string(),
+                healthy: worker.is_healthy(now),
+                lastrun: format_time(worker.last_run),
+                lastok: format_time(worker.last_ok),
+                lasterror: worker.last_error.clone(),
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    Health {
+        healthy: workers.iter().all(|worker| worker.healthy),
+        roles: roles.iter().map(|role| (*role).into()).collect(),
+        workers,
+    }
+}
+
+/// Json response of the health, HTTP status 503 if it is not healthy
+pub fn get_response(health: &Health) -> rouille::Response {
+    let status_code = if health.healthy { 200 } else { 503 };
+    match serde_json::to_string(health) {
+        Ok(text) => rouille::Response::text(text)
+            .with_unique_header("Content-Type", "application/json")
+            .with_no_cache()
+            .with_status_code(status_code),
+        Err(err) => rouille::Response::text(err.to_string()).with_status_code(500),
+    }
+}
+
+/// Answer /health for processes without the api role, blocks forever,
+/// check_metrics_prefix: also answer /metrics with the metrics of the checks that run in this process
+pub fn start_server(listen_host: &str, listen_port: i32, roles: Vec<Role>, check_metrics_prefix: Option<String>) {
+    let listen_str = format!("{}:{}", listen_host, listen_port);
+    info!("Health on {}/health", listen_str);
+    let metrics = check_metrics_prefix.and_then(|prefix| match prometheus_exporter::create_check_registry(&prefix) {
+        Ok(metrics) => Some(metrics),
+        Err(err) => {
+            error!("Unable to create metrics of checks: {}", err);
+            None
+        }
+    });
+    rouille::start_server(listen_str, move |request| {
+        if request.url() == "/health" {
+            get_response(&get_health(&roles))
+        } else if let (true, Some((registry, checks))) = (request.url() == "/metrics", &metrics) {
+            match prometheus_exporter::render_check_metrics(registry, checks) {
+                Ok(text) => rouille::Response::text(text),
+                Err(err) => rouille::Response::text(err.to_string()).with_status_code(500),
+            }
+        } else {
+            rouille::Response::empty_404()
+        }
+    });
+}
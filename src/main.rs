@@ -2,6 +2,8 @@
 extern crate serde_derive;
 #[macro_use]
 extern crate clap;
+#[macro_use]
+extern crate lazy_static;
 extern crate handlebars;
 extern crate url;
 #[macro_use]
@@ -11,6 +13,7 @@ extern crate toml;
 extern crate log;
 
 extern crate humantime;
+extern crate isolang;
 extern crate uuid;
 
 extern crate memcache;
@@ -19,25 +22,35 @@ extern crate redis;
 extern crate prometheus;
 
 extern crate av_stream_info_rust;
+extern crate hls_m3u8;
+extern crate playlist_decoder;
 extern crate colored;
 extern crate hostname;
 extern crate native_tls;
 extern crate reqwest;
-extern crate threadpool;
+extern crate tokio;
+extern crate tokio_tls;
+extern crate tree_magic;
+extern crate unicode_normalization;
 extern crate website_icon_extract;
 use core::fmt::Display;
 use core::fmt::Formatter;
 use std::error::Error;
 use std::{thread, time};
 
+use config::Role;
+
 mod api;
 mod check;
 mod cleanup;
 mod config;
 mod db;
+mod health;
 mod logger;
+mod normalize;
 mod pull;
 mod refresh;
+mod similar;
 
 #[derive(Debug, Clone)]
 enum MainError {
@@ -67,8 +80,32 @@ fn mainloop() -> Result<(), Box<dyn Error>> {
 
     info!("Config: {:#?}", config);
 
+    info!("Roles: {:?}", config.roles);
+
+    if !config.check_coordinator.is_empty() {
+        // check workers do not need a database
+        info!("Check worker of coordinator {}", config.check_coordinator);
+        check::start_worker(
+            config.check_coordinator,
+            config.check_coordinator_token.expose().to_string(),
+            config.source,
+            config.concurrency,
+            config.check_stations,
+            config.useragent,
+            config.tcp_timeout.as_secs(),
+            config.max_depth,
+            config.retries,
+            config.now_playing,
+            config.deep_check,
+            config.pause.as_secs(),
+        );
+        let check_metrics_prefix = if config.prometheus_exporter { Some(config.prometheus_exporter_prefix.clone()) } else { None };
+        health::start_server(&config.listen_host, config.listen_port, config.roles, check_metrics_prefix);
+        return Ok(());
+    }
+
     loop {
-        let connection = db::MysqlConnection::new(&config.connection_string);
+        let connection = db::MysqlConnection::new(config.connection_string.expose());
         match connection {
             Ok(connection) => {
                 let migration_result = connection.do_migrations(
@@ -79,40 +116,63 @@ fn mainloop() -> Result<(), Box<dyn Error>> {
                     Ok(_) => {
                         let config_for_api = config.clone();
 
-                        refresh::start(
-                            config.connection_string.clone(),
-                            config.update_caches_interval.as_secs(),
-                        );
-                        pull::start(
-                            config.connection_string.clone(),
-                            config.servers_pull,
-                            config.mirror_pull_interval.as_secs(),
-                        );
-                        cleanup::start(
-                            config.connection_string.clone(),
-                            config.delete,
-                            3600,
-                            config.click_valid_timeout.as_secs(),
-                            config.broken_stations_never_working_timeout.as_secs(),
-                            config.broken_stations_timeout.as_secs(),
-                            config.checks_timeout.as_secs(),
-                            config.clicks_timeout.as_secs(),
-                        );
+                        if config.roles.contains(&Role::Refresh) {
+                            refresh::start(
+                                config.connection_string.expose().to_string(),
+                                config.update_caches_interval.as_secs(),
+                                config.update_caches_full_interval.as_secs(),
+                            );
+                            similar::start(
+                                config.connection_string.expose().to_string(),
+                                config.similar_stations_interval.as_secs(),
+                            );
+                        }
+                        if config.roles.contains(&Role::Pull) {
+                            pull::start(
+                                config.connection_string.expose().to_string(),
+                                config.servers_pull,
+                                config.tag_synonyms.clone(),
+                                config.mirror_pull_interval.as_secs(),
+                            );
+                        }
+                        if config.roles.contains(&Role::Cleanup) {
+                            cleanup::start(
+                                config.connection_string.expose().to_string(),
+                                config.delete,
+                                config.tag_synonyms.clone(),
+                                3600,
+                                config.click_valid_timeout.as_secs(),
+                                config.broken_stations_never_working_timeout.as_secs(),
+                                config.broken_stations_timeout.as_secs(),
+                                config.checks_timeout.as_secs(),
+                                config.clicks_timeout.as_secs(),
+                                config.stats_history_timeout.as_secs(),
+                            );
+                        }
                         check::start(
-                            config.connection_string,
+                            config.connection_string.expose().to_string(),
                             config.source,
                             config.concurrency,
                             config.check_stations,
+                            config.check_priority,
                             config.useragent,
                             config.tcp_timeout.as_secs(),
                             config.max_depth,
                             config.retries,
                             config.favicon,
-                            config.enable_check,
+                            config.now_playing,
+                            config.deep_check,
+                            config.now_playing_history,
+                            config.roles.contains(&Role::Check),
                             config.pause.as_secs(),
                         );
 
-                        api::start(connection, config_for_api);
+                        if config.roles.contains(&Role::Api) {
+                            api::start(connection, config_for_api);
+                        } else {
+                            let check_metrics_prefix = if config.prometheus_exporter && config.roles.contains(&Role::Check) { Some(config.prometheus_exporter_prefix.clone()) } else { None };
+                            health::start_server(&config.listen_host, config.listen_port, config.roles, check_metrics_prefix);
+                        }
                     }
                     Err(err) => {
                         error!("Migrations error: {}", err);
@@ -0,0 +1,80 @@
+use super::normalize_text;
+use isolang::Language;
+use std::collections::HashMap;
+
+lazy_static! {
+    /// Shared normalizer, building the name map of all languages is expensive
+    pub static ref LANGUAGE_NORMALIZER: LanguageNormalizer = LanguageNormalizer::new();
+}
+
+/// Maps free text languages ("german", "deutsch", "de", "deu") to
+/// ISO 639-1 codes, or ISO 639-3 codes for languages without a 2 letter code
+#[derive(Debug, Clone)]
+pub struct LanguageNormalizer {
+    names: HashMap<String, Language>,
+}
+
+impl LanguageNormalizer {
+    pub fn new() -> Self {
+        let mut names: HashMap<String, Language> = HashMap::new();
+        for language in isolang::languages() {
+            let name = normalize_text(language.to_name());
+            // "Modern Greek (1453-)" should also be found by "greek"
+            let short_name = name.split(" (").next().unwrap_or("").trim_start_matches("modern ").to_string();
+            let mut keys = vec![name, short_name];
+            if let Some(autonym) = language.to_autonym() {
+                keys.push(normalize_text(autonym));
+            }
+            for key in keys {
+                // prefer the major languages on name clashes
+                let replace = match names.get(&key) {
+                    Some(existing) => existing.to_639_1().is_none() && language.to_639_1().is_some(),
+                    None => true,
+                };
+                if replace {
+                    names.insert(key, language);
+                }
+            }
+        }
+        LanguageNormalizer { names }
+    }
+
+    /// Code of a single language, None if it is unknown
+    pub fn code(&self, language: &str) -> Option<String> {
+        let language = normalize_text(language);
+        if language == "" {
+            return None;
+        }
+        let found = match language.len() {
+            2 => Language::from_639_1(&language),
+            3 => Language::from_639_3(&language),
+            _ => None,
+        };
+        found
+            .or_else(|| self.names.get(&language).cloned())
+            .map(|language| language.to_639_1().unwrap_or(language.to_639_3()).to_string())
+    }
+
+    /// Maximum length of a list of codes, the size of the column Station.LanguageCodes
+    pub const MAX_CODES_LENGTH: usize = 255;
+
+    /// Codes of a comma separated list of languages, unknown and duplicate languages are removed,
+    /// codes that do not fit into MAX_CODES_LENGTH are left out
+    pub fn codes(&self, languages: &str) -> String {
+        let mut list: Vec<String> = vec![];
+        let mut length = 0;
+        for language in languages.split(',') {
+            if let Some(code) = self.code(language) {
+                if !list.contains(&code) {
+                    let length_new = if list.is_empty() { code.len() } else { length + 1 + code.len() };
+                    if length_new > LanguageNormalizer::MAX_CODES_LENGTH {
+                        break;
+                    }
+                    length = length_new;
+                    list.push(code);
+                }
+            }
+        }
+        list.join(",")
+    }
+}
@@ -0,0 +1,14 @@
+mod language;
+mod tag;
+
+pub use self::language::LANGUAGE_NORMALIZER;
+pub use self::tag::TagNormalizer;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode compatibility normalization (NFKC), lowercase and
+/// whitespace collapsed to single spaces
+pub fn normalize_text(value: &str) -> String {
+    let value: String = value.nfkc().collect::<String>().to_lowercase();
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
@@ -0,0 +1,115 @@
+use super::normalize_text;
+use std::collections::HashMap;
+
+/// Synonyms that are known without configuration, as canonical tag and aliases
+const DEFAULT_SYNONYMS: [(&str, &[&str]); 3] = [
+    ("hip hop", &["hiphop"]),
+    ("drum and bass", &["dnb", "drum & bass", "drum n bass", "drum'n'bass"]),
+    ("r&b", &["rnb", "r & b", "r and b"]),
+];
+
+/// Normalize the text of a tag, hyphens and underscores are folded into spaces
+fn normalize_tag(tag: &str) -> String {
+    normalize_text(&tag.replace(|c: char| c == '-' || c == '_', " "))
+}
+
+/// Maps tags to their canonical form, by normalizing the text
+/// and replacing known synonyms
+#[derive(Debug, Clone)]
+pub struct TagNormalizer {
+    synonyms: HashMap<String, String>,
+}
+
+impl TagNormalizer {
+    /// synonyms is a map of canonical tags to lists of aliases, they are added to the default synonyms.
+    /// A configured canonical tag is never replaced by a default synonym.
+    pub fn new(synonyms: &HashMap<String, Vec<String>>) -> Self {
+        let mut map = HashMap::new();
+        for (canonical, aliases) in DEFAULT_SYNONYMS.iter() {
+            for alias in aliases.iter() {
+                map.insert(normalize_tag(alias), normalize_tag(canonical));
+            }
+        }
+        for (canonical, aliases) in synonyms {
+            let canonical = normalize_tag(canonical);
+            map.remove(&canonical);
+            for alias in aliases {
+                let alias = normalize_tag(alias);
+                if alias != canonical {
+                    map.insert(alias, canonical.clone());
+                }
+            }
+        }
+        // aliases of default synonyms whose canonical tag was configured as alias point to the configured tag
+        let resolved: HashMap<String, String> = map.iter().map(|(alias, canonical)| {
+            let canonical = map.get(canonical).unwrap_or(canonical).clone();
+            (alias.clone(), canonical)
+        }).filter(|(alias, canonical)| alias != canonical).collect();
+        TagNormalizer { synonyms: resolved }
+    }
+
+    pub fn canonical(&self, tag: &str) -> String {
+        let tag = normalize_tag(tag);
+        match self.synonyms.get(&tag) {
+            Some(canonical) => canonical.clone(),
+            None => tag,
+        }
+    }
+
+    pub fn canonical_option(&self, tag: Option<String>) -> Option<String> {
+        tag.map(|tag| self.canonical(&tag))
+    }
+
+    pub fn canonical_vec(&self, tags: Vec<String>) -> Vec<String> {
+        tags.iter().map(|tag| self.canonical(tag)).collect()
+    }
+
+    /// Canonicalize a comma separated list of tags, empty and duplicate tags are removed
+    pub fn canonical_list(&self, tags: &str) -> String {
+        let mut list: Vec<String> = vec![];
+        for tag in tags.split(',') {
+            let tag = self.canonical(tag);
+            if tag != "" && !list.contains(&tag) {
+                list.push(tag);
+            }
+        }
+        list.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synonyms(list: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        list.iter().map(|(canonical, aliases)| (canonical.to_string(), aliases.iter().map(|alias| alias.to_string()).collect())).collect()
+    }
+
+    #[test]
+    fn canonical_folds_separators() {
+        let normalizer = TagNormalizer::new(&HashMap::new());
+        assert_eq!(normalizer.canonical("Hip-Hop"), "hip hop");
+        assert_eq!(normalizer.canonical("hip_hop"), "hip hop");
+        assert_eq!(normalizer.canonical("HipHop"), "hip hop");
+        assert_eq!(normalizer.canonical(" hip  hop "), "hip hop");
+        assert_eq!(normalizer.canonical_list("hiphop,Hip-Hop,rock"), "hip hop,rock");
+    }
+
+    #[test]
+    fn configured_synonyms_override_defaults() {
+        let normalizer = TagNormalizer::new(&synonyms(&[("hiphop", &["hip hop", "rap"])]));
+        assert_eq!(normalizer.canonical("hiphop"), "hiphop");
+        assert_eq!(normalizer.canonical("hip-hop"), "hiphop");
+        assert_eq!(normalizer.canonical("Rap"), "hiphop");
+        assert_eq!(normalizer.canonical("dnb"), "drum and bass");
+    }
+
+    #[test]
+    fn canonical_tags_are_stable() {
+        let normalizer = TagNormalizer::new(&synonyms(&[("hiphop", &["hip hop"]), ("dnb", &["drum and bass"])]));
+        for tag in &["hip hop", "hiphop", "hip-hop", "dnb", "drum & bass", "drum and bass", "r and b", "rock"] {
+            let canonical = normalizer.canonical(tag);
+            assert_eq!(normalizer.canonical(&canonical), canonical, "tag '{}'", tag);
+        }
+    }
+}
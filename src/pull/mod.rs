@@ -1,5 +1,6 @@
 mod pull_error;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::thread;
 use crate::time;
@@ -20,6 +21,8 @@ use crate::api::data::Station;
 use crate::api::data::StationV0;
 use crate::db::DbConnection;
 use crate::db::connect;
+use crate::health;
+use crate::normalize::TagNormalizer;
 use crate::db::models::StationCheckItemNew;
 use crate::db::models::StationChangeItemNew;
 use crate::db::models::StationClickItemNew;
@@ -29,10 +32,10 @@ fn add_default_request_headers(req: RequestBuilder) -> RequestBuilder {
     req.header(USER_AGENT, format!("radiobrowser-api-rust/{}",pkg_version))
 }
 
-fn pull_worker(client: &Client, connection_string: String, mirrors: &Vec<String>) -> Result<(),Box<dyn Error>> {
+fn pull_worker(client: &Client, connection_string: String, mirrors: &Vec<String>, tag_normalizer: &TagNormalizer) -> Result<(),Box<dyn Error>> {
     let pool = connect(connection_string)?;
     for server in mirrors.iter() {
-        let result = pull_server(client, &pool, &server);
+        let result = pull_server(client, &pool, &server, tag_normalizer);
         match result {
             Ok(_) => {
             },
@@ -44,12 +47,15 @@ fn pull_worker(client: &Client, connection_string: String, mirrors: &Vec<String>
     Ok(())
 }
 
-pub fn start(connection_string: String, mirrors: Vec<String>, pull_interval: u64) {
+pub fn start(connection_string: String, mirrors: Vec<String>, tag_synonyms: HashMap<String, Vec<String>>, pull_interval: u64) {
     if mirrors.len() > 0 {
+        let tag_normalizer = TagNormalizer::new(&tag_synonyms);
+        health::register("pull", pull_interval);
         thread::spawn(move || {
             let client = Client::new();
             loop {
-                let result = pull_worker(&client, connection_string.clone(), &mirrors);
+                let result = pull_worker(&client, connection_string.clone(), &mirrors, &tag_normalizer);
+                health::report("pull", &result);
                 match result {
                     Ok(_) => {
                     },
@@ -162,7 +168,7 @@ fn pull_stations(client: &Client, server: &str, api_version: u32) -> Result<Vec<
     }
 }
 
-fn pull_server(client: &Client, connection_new: &Box<dyn DbConnection>, server: &str) -> Result<(),Box<dyn std::error::Error>> {
+fn pull_server(client: &Client, connection_new: &Box<dyn DbConnection>, server: &str, tag_normalizer: &TagNormalizer) -> Result<(),Box<dyn std::error::Error>> {
     let insert_chunksize = 1000;
     let mut station_change_count = 0;
     let mut station_check_count = 0;
@@ -179,7 +185,10 @@ fn pull_server(client: &Client, connection_new: &Box<dyn DbConnection>, server:
         for station in list_changes {
             let changeuuid = station.changeuuid.clone();
             station_change_count = station_change_count + 1;
-            list_stations.push(station.into());
+            let mut value: StationChangeItemNew = station.into();
+            // tags are stored in their canonical form, like the cleanup does for existing stations
+            value.tags = tag_normalizer.canonical_list(&value.tags);
+            list_stations.push(value);
 
             if station_change_count % insert_chunksize == 0 || station_change_count == len {
                 trace!("Insert {} station changes..", list_stations.len());
@@ -277,6 +286,14 @@ impl From<StationCheck> for StationCheckItemNew {
             homepage: item.homepage,
             favicon: item.favicon,
             loadbalancer: item.loadbalancer,
+
+            failure_reason: item.failure_reason,
+            failure_message: item.failure_message,
+            title: None,
+            audio_codec: item.audio_codec,
+            audio_bitrate: item.audio_bitrate,
+            audio_samplerate: item.audio_samplerate,
+            audio_channels: item.audio_channels,
         }
     }
 }
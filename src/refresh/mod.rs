@@ -1,8 +1,12 @@
 use std;
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
 use crate::thread;
 use crate::db::connect;
 use crate::db::DbConnection;
+use crate::health;
+use crate::normalize::LANGUAGE_NORMALIZER;
 
 pub struct RefreshCacheStatus{
     old_items: usize,
@@ -10,49 +14,89 @@ pub struct RefreshCacheStatus{
     changed_items: usize,
 }
 
-pub fn refresh_cache_items(
-    pool: &Box<dyn DbConnection>,
-    cache_table_name: &str,
-    cache_column_name: &str,
-    station_column_name: &str,
-)-> Result<RefreshCacheStatus, Box<dyn std::error::Error>> {
-    let items_cached = pool.get_cached_items(cache_table_name, cache_column_name)?;
-    let items_current = pool.get_stations_multi_items(station_column_name)?;
-    let mut changed = 0;
-    let max_cache_item_len = 110;
-
+/// Compare cached with current counts, returns the keys to delete, the items to insert and the items that changed
+fn diff_cache_items<'a, K: Eq + Hash>(
+    items_cached: &'a HashMap<K, (u32,u32)>,
+    items_current: &'a HashMap<K, (u32,u32)>,
+) -> (Vec<&'a K>, HashMap<&'a K, (u32,u32)>, Vec<(&'a K, (u32,u32))>) {
     let mut to_delete = vec![];
     for item_cached in items_cached.keys() {
         if !items_current.contains_key(item_cached) {
             to_delete.push(item_cached);
         }
     }
-    pool.remove_from_cache(to_delete, cache_table_name, cache_column_name)?;
 
-    let mut to_insert: HashMap<&String, (u32,u32)> = HashMap::new();
-    for item_current in items_current.keys() {
-        if !items_cached.contains_key(item_current) {
-            if item_current.len() < max_cache_item_len {
-                to_insert.insert(item_current, *items_current.get(item_current).unwrap_or(&(0,0)));
-            }else{
-                debug!("cached '{}' item too long: '{}'", station_column_name, item_current);
+    let mut to_insert: HashMap<&K, (u32,u32)> = HashMap::new();
+    let mut to_update = vec![];
+    for (item_current, value_new) in items_current.iter() {
+        match items_cached.get(item_current) {
+            Some(value_old) => {
+                if value_old != value_new {
+                    to_update.push((item_current, *value_new));
+                }
             }
-        } else {
-            let value_new = *items_current.get(item_current).unwrap_or(&(0,0));
-            let value_old = *items_cached.get(item_current).unwrap_or(&(0,0));
-            if value_old != value_new {
-                pool.update_cache_item(
-                    item_current,
-                    value_new.0,
-                    value_new.1,
-                    cache_table_name,
-                    cache_column_name,
-                )?;
-                changed = changed + 1;
+            None => {
+                to_insert.insert(item_current, *value_new);
             }
         }
     }
+    (to_delete, to_insert, to_update)
+}
+
+/// Write the differences of cached and current items to a cache table,
+/// returns the number of deleted, inserted and changed items
+fn apply_cache_items(
+    pool: &Box<dyn DbConnection>,
+    cache_table_name: &str,
+    cache_column_name: &str,
+    station_column_name: &str,
+    items_cached: &HashMap<String, (u32,u32)>,
+    items_current: &HashMap<String, (u32,u32)>,
+)-> Result<(usize, usize, usize), Box<dyn std::error::Error>> {
+    let max_cache_item_len = 110;
+
+    let (to_delete, mut to_insert, to_update) = diff_cache_items(items_cached, items_current);
+    let deleted = to_delete.len();
+    pool.remove_from_cache(to_delete, cache_table_name, cache_column_name)?;
+    to_insert.retain(|item_current, _| {
+        if item_current.len() < max_cache_item_len {
+            true
+        } else {
+            debug!("cached '{}' item too long: '{}'", station_column_name, item_current);
+            false
+        }
+    });
+    for (item_current, value_new) in to_update.iter() {
+        pool.update_cache_item(
+            item_current,
+            value_new.0,
+            value_new.1,
+            cache_table_name,
+            cache_column_name,
+        )?;
+    }
+    let inserted = to_insert.len();
     pool.insert_to_cache(to_insert, cache_table_name, cache_column_name)?;
+    Ok((deleted, inserted, to_update.len()))
+}
+
+/// Update a cache table from the current values of a station column
+/// multi_value: column contains comma separated lists (tags, languages), else the values are counted as they are
+pub fn refresh_cache_items(
+    pool: &Box<dyn DbConnection>,
+    cache_table_name: &str,
+    cache_column_name: &str,
+    station_column_name: &str,
+    multi_value: bool,
+)-> Result<RefreshCacheStatus, Box<dyn std::error::Error>> {
+    let items_cached = pool.get_cached_items(cache_table_name, cache_column_name)?;
+    let items_current = if multi_value {
+        pool.get_stations_multi_items(station_column_name, None)?
+    } else {
+        pool.get_stations_single_items(station_column_name, None)?
+    };
+
+    let (_, _, changed) = apply_cache_items(pool, cache_table_name, cache_column_name, station_column_name, &items_cached, &items_current)?;
     trace!(
         "{}: {} -> {}, Changed: {}",
         station_column_name,
@@ -68,22 +112,181 @@ pub fn refresh_cache_items(
     })
 }
 
-fn refresh_worker(connection_string: String) -> Result<(), Box<dyn std::error::Error>> {
+/// Update only the given values of a cache table, used for the values that were queued by station changes,
+/// queued values are lower case
+pub fn refresh_cache_items_queued(
+    pool: &Box<dyn DbConnection>,
+    cache_table_name: &str,
+    cache_column_name: &str,
+    station_column_name: &str,
+    multi_value: bool,
+    values: &Vec<&String>,
+)-> Result<RefreshCacheStatus, Box<dyn std::error::Error>> {
+    let items_cached_all = pool.get_cached_items(cache_table_name, cache_column_name)?;
+    let mut items_cached = HashMap::new();
+    let mut items_current = HashMap::new();
+    if multi_value {
+        for value in values {
+            if let Some(counts) = items_cached_all.get(*value) {
+                items_cached.insert(value.to_string(), *counts);
+            }
+        }
+        items_current = pool.get_stations_multi_items(station_column_name, Some(values))?;
+    } else {
+        // single values keep their spelling, the cached items are found by their lower case value
+        for (key, counts) in items_cached_all.iter() {
+            if values.contains(&&key.trim().to_lowercase()) {
+                items_cached.insert(key.clone(), *counts);
+            }
+        }
+        for value in values {
+            items_current.extend(pool.get_stations_single_items(station_column_name, Some(value))?);
+        }
+    }
+
+    let (deleted, inserted, changed) = apply_cache_items(pool, cache_table_name, cache_column_name, station_column_name, &items_cached, &items_current)?;
+    trace!(
+        "{} (queued {}): {} -> {}, Changed: {}",
+        station_column_name,
+        values.len(),
+        items_cached_all.len(),
+        items_cached_all.len() - deleted + inserted,
+        changed
+    );
+    Ok(
+    RefreshCacheStatus{
+        old_items: items_cached_all.len(),
+        new_items: items_cached_all.len() - deleted + inserted,
+        changed_items: changed,
+    })
+}
+
+/// Same as refresh_cache_items for the StateCache table, states are identified by state and country,
+/// only the queued states are updated if they are given
+pub fn refresh_cache_states(
+    pool: &Box<dyn DbConnection>,
+    values: Option<&Vec<&String>>,
+)-> Result<RefreshCacheStatus, Box<dyn std::error::Error>> {
+    let items_cached_all = pool.get_cached_states()?;
+    let (items_cached, items_current) = match values {
+        Some(values) => {
+            let mut items_cached = HashMap::new();
+            for (key, counts) in items_cached_all.iter() {
+                if values.contains(&&key.0.trim().to_lowercase()) {
+                    items_cached.insert(key.clone(), *counts);
+                }
+            }
+            let mut items_current = HashMap::new();
+            for value in values {
+                items_current.extend(pool.get_stations_state_items(Some(value))?);
+            }
+            (items_cached, items_current)
+        }
+        None => (items_cached_all.clone(), pool.get_stations_state_items(None)?),
+    };
+
+    let (to_delete, to_insert, to_update) = diff_cache_items(&items_cached, &items_current);
+    let deleted = to_delete.len();
+    let inserted = to_insert.len();
+    pool.remove_states_from_cache(to_delete)?;
+    for (item_current, value_new) in to_update.iter() {
+        pool.update_cache_state(item_current, value_new.0, value_new.1)?;
+    }
+    pool.insert_states_to_cache(to_insert)?;
+    trace!(
+        "States: {} -> {}, Changed: {}",
+        items_cached_all.len(),
+        items_cached_all.len() - deleted + inserted,
+        to_update.len()
+    );
+    Ok(
+    RefreshCacheStatus{
+        old_items: items_cached_all.len(),
+        new_items: items_cached_all.len() - deleted + inserted,
+        changed_items: to_update.len(),
+    })
+}
+
+/// Update the language codes of all stations whose languages map to other codes than stored
+fn refresh_language_codes(pool: &Box<dyn DbConnection>) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut changed = vec![];
+    for (language, languagecodes) in pool.get_distinct_languages()? {
+        let languagecodes_new = LANGUAGE_NORMALIZER.codes(&language);
+        if languagecodes_new != languagecodes {
+            changed.push((language, languagecodes_new));
+        }
+    }
+    let count = changed.len();
+    pool.update_language_codes(changed)?;
+    Ok(count)
+}
+
+/// Update the caches completely or only for the values that were queued by station changes
+fn refresh_caches(pool: &Box<dyn DbConnection>, full: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // queue has to be read before a full refresh, changes after that point are in the next refresh,
+    // items are only removed after they were applied, so that errors do not lose them
+    let queue = pool.get_cache_queue()?;
+    let queued = |station_column_name: &str| -> Vec<&String> {
+        queue.iter().filter(|(column_name, _, _)| column_name == station_column_name).map(|(_, value, _)| value).collect()
+    };
+    // mappings of languages to codes only change with new versions, the first refresh after startup is a full one
+    let languages_mapped = if full { refresh_language_codes(pool)? } else { 0 };
+    let caches = [
+        ("TagCache", "TagName", "Tags", true),
+        ("LanguageCache", "LanguageName", "Language", true),
+        ("LanguageCodeCache", "LanguageCode", "LanguageCodes", true),
+        ("CountryCache", "CountryName", "Country", false),
+        ("CountryCodeCache", "CountryCode", "CountryCode", false),
+        ("CodecCache", "CodecName", "Codec", false),
+    ];
+    for (cache_table_name, cache_column_name, station_column_name, multi_value) in caches.iter() {
+        let status = if full {
+            refresh_cache_items(pool, cache_table_name, cache_column_name, station_column_name, *multi_value)?
+        } else {
+            let values = queued(station_column_name);
+            if values.len() == 0 {
+                continue;
+            }
+            refresh_cache_items_queued(pool, cache_table_name, cache_column_name, station_column_name, *multi_value, &values)?
+        };
+        debug!("Refresh({}={}->{} changed={} full={})", station_column_name, status.old_items, status.new_items, status.changed_items, full);
+    }
+    let states = queued("Subcountry");
+    if full || states.len() > 0 {
+        let status = refresh_cache_states(pool, if full { None } else { Some(&states) })?;
+        debug!("Refresh(States={}->{} changed={} full={})", status.old_items, status.new_items, status.changed_items, full);
+    }
+    if languages_mapped > 0 {
+        debug!("Refresh(LanguageCodes mapped={})", languages_mapped);
+    }
+    pool.remove_from_cache_queue(&queue)?;
+    Ok(())
+}
+
+fn refresh_worker(connection_string: String, full: bool) -> Result<(), Box<dyn std::error::Error>> {
     let pool = connect(connection_string)?;
     trace!("REFRESH START");
-    let tags = refresh_cache_items(&pool, "TagCache", "TagName", "Tags")?;
-    let languages = refresh_cache_items(&pool, "LanguageCache", "LanguageName", "Language")?;
-    debug!("Refresh(Tags={}->{} changed={}, Languages={}->{} changed={})", tags.old_items, tags.new_items, tags.changed_items, languages.old_items, languages.new_items, languages.changed_items);
+    refresh_caches(&pool, full)?;
     Ok(())
 }
 
-pub fn start(connection_string: String, update_caches_interval: u64) {
+pub fn start(connection_string: String, update_caches_interval: u64, update_caches_full_interval: u64) {
     if update_caches_interval > 0 {
+        health::register("refresh", update_caches_interval);
         thread::spawn(move || {
+            let mut last_full_refresh: Option<Instant> = None;
             loop {
-                let result = refresh_worker(connection_string.clone());
+                let full = match last_full_refresh {
+                    Some(last_full_refresh) => last_full_refresh.elapsed().as_secs() >= update_caches_full_interval,
+                    None => true,
+                };
+                let result = refresh_worker(connection_string.clone(), full);
+                health::report("refresh", &result);
                 match result {
                     Ok(_)=>{
+                        if full {
+                            last_full_refresh = Some(Instant::now());
+                        }
                     },
                     Err(err)=>{
                         error!("Refresh worker error: {}", err);
@@ -93,4 +296,4 @@ pub fn start(connection_string: String, update_caches_interval: u64) {
             }
         });
     }
-}
\ No newline at end of file
+}
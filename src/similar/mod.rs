@@ -0,0 +1,209 @@
+use crate::db::connect;
+use crate::db::models::StationItem;
+use crate::db::DbConnection;
+use crate::health;
+use crate::thread;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Number of similar stations that are stored for every station
+const MAX_SIMILAR: usize = 20;
+/// Only the most clicked stations of a tag or language are compared,
+/// else very common tags like "pop" would compare every station with every other
+const MAX_CANDIDATES_PER_VALUE: usize = 300;
+/// Clicks of the same client on 2 stations within this timespan count as co-click
+const CO_CLICK_WINDOW_SECONDS: i64 = 3600;
+/// Only clicks of this timespan are used for co-clicks
+const CO_CLICK_MAX_AGE_SECONDS: u64 = 7 * 24 * 3600;
+/// Only the latest clicks are used for co-clicks, so that the calculation does not grow with the click table
+const CO_CLICK_MAX_CLICKS: u32 = 200000;
+/// Clients with more clicks (proxies, crawlers) are ignored, their pairs of clicks would grow quadratic
+const CO_CLICK_MAX_CLICKS_PER_IP: usize = 100;
+
+const WEIGHT_TAGS: f64 = 0.35;
+const WEIGHT_LANGUAGE: f64 = 0.2;
+const WEIGHT_COUNTRY: f64 = 0.1;
+const WEIGHT_CODEC: f64 = 0.05;
+const WEIGHT_BITRATE: f64 = 0.05;
+const WEIGHT_CO_CLICKS: f64 = 0.25;
+
+struct StationInfo<'a> {
+    station: &'a StationItem,
+    tags: HashSet<&'a str>,
+    languages: HashSet<&'a str>,
+}
+
+fn split_values(value: &str) -> HashSet<&str> {
+    value.split(',').map(|item| item.trim()).filter(|item| *item != "").collect()
+}
+
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn bitrate_proximity(a: u32, b: u32) -> f64 {
+    if a == 0 || b == 0 {
+        return 0.0;
+    }
+    let (min, max) = if a < b { (a, b) } else { (b, a) };
+    min as f64 / max as f64
+}
+
+/// Similarity of 2 stations between 0 and 1, co_clicks is already normalized to 0..1
+fn get_score(a: &StationInfo, b: &StationInfo, co_clicks: f64) -> f64 {
+    let mut score = WEIGHT_TAGS * jaccard(&a.tags, &b.tags);
+    score += WEIGHT_LANGUAGE * jaccard(&a.languages, &b.languages);
+    if a.station.countrycode != "" && a.station.countrycode == b.station.countrycode {
+        score += WEIGHT_COUNTRY;
+    }
+    if a.station.codec != "" && a.station.codec.to_lowercase() == b.station.codec.to_lowercase() {
+        score += WEIGHT_CODEC;
+    }
+    score += WEIGHT_BITRATE * bitrate_proximity(a.station.bitrate, b.station.bitrate);
+    score += WEIGHT_CO_CLICKS * co_clicks;
+    score
+}
+
+/// Count the clients that clicked both stations of a pair within CO_CLICK_WINDOW_SECONDS,
+/// every pair is returned in both directions
+fn count_co_clicks(clicks: Vec<(String, String, i64)>) -> Vec<(String, String, u32)> {
+    let mut clicks_by_ip: HashMap<String, Vec<(String, i64)>> = HashMap::new();
+    for (ip, stationuuid, timestamp) in clicks {
+        clicks_by_ip.entry(ip).or_insert_with(Vec::new).push((stationuuid, timestamp));
+    }
+
+    let mut counts: HashMap<(String, String), u32> = HashMap::new();
+    for (_, mut ip_clicks) in clicks_by_ip {
+        if ip_clicks.len() > CO_CLICK_MAX_CLICKS_PER_IP {
+            continue;
+        }
+        ip_clicks.sort_by_key(|(_, timestamp)| *timestamp);
+        // every client counts once per pair
+        let mut pairs: HashSet<(&str, &str)> = HashSet::new();
+        for (i, (a, timestamp_a)) in ip_clicks.iter().enumerate() {
+            for (b, timestamp_b) in ip_clicks[i + 1..].iter() {
+                if timestamp_b - timestamp_a > CO_CLICK_WINDOW_SECONDS {
+                    break;
+                }
+                if a != b {
+                    pairs.insert((a, b));
+                    pairs.insert((b, a));
+                }
+            }
+        }
+        for (a, b) in pairs {
+            *counts.entry((a.to_string(), b.to_string())).or_insert(0) += 1;
+        }
+    }
+    counts.drain().map(|((a, b), count)| (a, b, count)).collect()
+}
+
+/// Calculate the most similar working stations for every station
+fn calc_similar_stations(stations: &Vec<StationItem>, co_clicks: &Vec<(String, String, u32)>) -> Vec<(String, String, f64)> {
+    let infos: Vec<StationInfo> = stations
+        .iter()
+        .map(|station| StationInfo {
+            station,
+            tags: split_values(&station.tags),
+            languages: if station.languagecodes != "" { split_values(&station.languagecodes) } else { split_values(&station.language) },
+        })
+        .collect();
+    let index_by_uuid: HashMap<&str, usize> = infos.iter().enumerate().map(|(i, info)| (info.station.stationuuid.as_str(), i)).collect();
+
+    // candidates by shared tags or languages, most clicked stations first
+    let mut by_value: HashMap<(bool, &str), Vec<usize>> = HashMap::new();
+    for (i, info) in infos.iter().enumerate() {
+        if !info.station.lastcheckok {
+            continue;
+        }
+        for tag in info.tags.iter() {
+            by_value.entry((true, tag)).or_insert_with(Vec::new).push(i);
+        }
+        for language in info.languages.iter() {
+            by_value.entry((false, language)).or_insert_with(Vec::new).push(i);
+        }
+    }
+    for list in by_value.values_mut() {
+        list.sort_by(|a, b| infos[*b].station.clickcount.cmp(&infos[*a].station.clickcount));
+        list.truncate(MAX_CANDIDATES_PER_VALUE);
+    }
+
+    let mut co_clicks_by_station: HashMap<usize, HashMap<usize, u32>> = HashMap::new();
+    for (stationuuid, other_stationuuid, count) in co_clicks {
+        if let (Some(a), Some(b)) = (index_by_uuid.get(stationuuid.as_str()), index_by_uuid.get(other_stationuuid.as_str())) {
+            co_clicks_by_station.entry(*a).or_insert_with(HashMap::new).insert(*b, *count);
+        }
+    }
+
+    let mut result = vec![];
+    for (i, info) in infos.iter().enumerate() {
+        let empty = HashMap::new();
+        let station_co_clicks = co_clicks_by_station.get(&i).unwrap_or(&empty);
+        let max_co_clicks = station_co_clicks.values().max().cloned().unwrap_or(0);
+
+        let mut candidates: HashSet<usize> = station_co_clicks.keys().cloned().collect();
+        for tag in info.tags.iter() {
+            if let Some(list) = by_value.get(&(true, tag)) {
+                candidates.extend(list.iter());
+            }
+        }
+        for language in info.languages.iter() {
+            if let Some(list) = by_value.get(&(false, language)) {
+                candidates.extend(list.iter());
+            }
+        }
+        candidates.remove(&i);
+
+        let mut scored: Vec<(usize, f64)> = candidates
+            .iter()
+            .filter(|candidate| infos[**candidate].station.lastcheckok)
+            .map(|candidate| {
+                let co_click_score = if max_co_clicks > 0 {
+                    *station_co_clicks.get(candidate).unwrap_or(&0) as f64 / max_co_clicks as f64
+                } else {
+                    0.0
+                };
+                (*candidate, get_score(info, &infos[*candidate], co_click_score))
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(MAX_SIMILAR);
+        for (candidate, score) in scored {
+            result.push((info.station.stationuuid.clone(), infos[candidate].station.stationuuid.clone(), score));
+        }
+    }
+    result
+}
+
+fn similar_worker(connection_string: String) -> Result<(), Box<dyn std::error::Error>> {
+    let pool: Box<dyn DbConnection> = connect(connection_string)?;
+    trace!("SIMILAR START");
+    let stations = pool.get_stations_by_all("name", false, false, 0, 10000000)?;
+    let co_clicks = count_co_clicks(pool.get_clicks_with_ip(CO_CLICK_MAX_AGE_SECONDS, CO_CLICK_MAX_CLICKS)?);
+    let similar = calc_similar_stations(&stations, &co_clicks);
+    debug!("Similar(Stations={}, CoClicks={}, Similar={})", stations.len(), co_clicks.len(), similar.len());
+    pool.set_similar_stations(similar)?;
+    Ok(())
+}
+
+pub fn start(connection_string: String, similar_stations_interval: u64) {
+    if similar_stations_interval > 0 {
+        health::register("similar", similar_stations_interval);
+        thread::spawn(move || loop {
+            let result = similar_worker(connection_string.clone());
+            health::report("similar", &result);
+            match result {
+                Ok(_) => {}
+                Err(err) => {
+                    error!("Similar stations worker error: {}", err);
+                }
+            }
+            thread::sleep(::std::time::Duration::new(similar_stations_interval, 0));
+        });
+    }
+}